@@ -14,10 +14,11 @@ use crate::shutdown::Controller;
 use crate::types::IdentityConfig;
 use crate::{
 	api::v1,
-	network::rpc::{self},
+	network::{p2p, rpc},
 	types::{RuntimeConfig, State},
 };
 use color_eyre::eyre::WrapErr;
+use dusk_plonk::commitment_scheme::kzg10::PublicParameters;
 use futures::{Future, FutureExt};
 use std::{
 	net::SocketAddr,
@@ -35,6 +36,8 @@ pub struct Server<T: Database> {
 	pub version: String,
 	pub network_version: String,
 	pub node_client: rpc::Client,
+	pub p2p_client: p2p::Client,
+	pub pp: Arc<PublicParameters>,
 	pub ws_clients: v2::types::WsClients,
 	pub shutdown: Controller<String>,
 }
@@ -53,10 +56,16 @@ impl<T: Database + Clone + Send + Sync + 'static> Server<T> {
 			http_server_host: host,
 			http_server_port: port,
 			app_id,
+			sampling_strategy,
 			..
 		} = self.cfg.clone();
 
-		let v1_api = v1::routes(self.db.clone(), app_id, self.state.clone());
+		let v1_api = v1::routes(
+			self.db.clone(),
+			app_id,
+			sampling_strategy,
+			self.state.clone(),
+		);
 		let v2_api = v2::routes(
 			self.version.clone(),
 			self.network_version.clone(),
@@ -64,14 +73,17 @@ impl<T: Database + Clone + Send + Sync + 'static> Server<T> {
 			self.cfg,
 			self.identity_cfg,
 			self.node_client.clone(),
+			self.p2p_client.clone(),
+			self.pp.clone(),
 			self.ws_clients.clone(),
 			self.db.clone(),
 		);
 
+		// Admin routes (PUT and DELETE) are not allowed from other origins, so web pages cannot call them
 		let cors = warp::cors()
 			.allow_any_origin()
 			.allow_header("content-type")
-			.allow_methods(vec!["GET", "POST", "DELETE"]);
+			.allow_methods(vec!["GET", "POST"]);
 
 		let routes = health_route().or(v1_api).or(v2_api).with(cors);
 
@@ -2,8 +2,8 @@ use super::types::{AppDataQuery, ClientResponse, ConfidenceResponse, LatestBlock
 use crate::{
 	api::v1::types::{Extrinsics, ExtrinsicsDataResponse},
 	data::{Database, Key},
+	sampling::{stored_confidence, SamplingStrategy},
 	types::{Mode, OptionBlockRange, State},
-	utils::calculate_confidence,
 };
 use avail_subxt::{
 	api::runtime_types::{da_control::pallet::Call, da_runtime::RuntimeCall},
@@ -30,12 +30,16 @@ pub fn mode(app_id: Option<u32>) -> ClientResponse<Mode> {
 pub fn confidence(
 	block_num: u32,
 	db: impl Database,
+	sampling_strategy: Arc<dyn SamplingStrategy>,
 	state: Arc<Mutex<State>>,
 ) -> ClientResponse<ConfidenceResponse> {
 	info!("Got request for confidence for block {block_num}");
 	let res = match db.get(Key::VerifiedCellCount(block_num)) {
 		Ok(Some(count)) => {
-			let confidence = calculate_confidence(count);
+			let confidence = match stored_confidence(&db, &*sampling_strategy, block_num, count) {
+				Ok(confidence) => confidence,
+				Err(e) => return ClientResponse::Error(e),
+			};
 			let serialised_confidence = serialised_confidence(block_num, confidence);
 			ClientResponse::Normal(ConfidenceResponse {
 				block: block_num,
@@ -66,6 +70,7 @@ pub fn status(
 	app_id: Option<u32>,
 	state: Arc<Mutex<State>>,
 	db: impl Database,
+	sampling_strategy: Arc<dyn SamplingStrategy>,
 ) -> ClientResponse<Status> {
 	let state = state.lock().unwrap();
 	let Some(last) = state.confidence_achieved.last() else {
@@ -73,7 +78,10 @@ pub fn status(
 	};
 	let res = match db.get(Key::VerifiedCellCount(last)) {
 		Ok(Some(count)) => {
-			let confidence = calculate_confidence(count);
+			let confidence = match stored_confidence(&db, &*sampling_strategy, last, count) {
+				Ok(confidence) => confidence,
+				Err(e) => return ClientResponse::Error(e),
+			};
 			ClientResponse::Normal(Status {
 				block_num: last,
 				confidence,
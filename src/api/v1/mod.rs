@@ -1,4 +1,8 @@
-use crate::{data::Database, types::State};
+use crate::{
+	data::Database,
+	sampling::{self, SamplingStrategy},
+	types::{SamplingStrategyConfig, State},
+};
 
 use self::types::AppDataQuery;
 use std::{
@@ -22,6 +26,12 @@ fn with_db<T: Database + Clone + Send>(
 	warp::any().map(move || db.clone())
 }
 
+fn with_sampling_strategy(
+	sampling_strategy: Arc<dyn SamplingStrategy>,
+) -> impl Filter<Extract = (Arc<dyn SamplingStrategy>,), Error = Infallible> + Clone {
+	warp::any().map(move || sampling_strategy.clone())
+}
+
 fn with_app_id(
 	app_id: Option<u32>,
 ) -> impl Filter<Extract = (Option<u32>,), Error = Infallible> + Clone {
@@ -31,8 +41,11 @@ fn with_app_id(
 pub fn routes(
 	db: impl Database + Clone + Send,
 	app_id: Option<u32>,
+	sampling_strategy: SamplingStrategyConfig,
 	state: Arc<Mutex<State>>,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	let sampling_strategy = sampling::new(sampling_strategy);
+
 	let mode = warp::path!("v1" / "mode")
 		.and(with_app_id(app_id))
 		.map(handlers::mode);
@@ -43,6 +56,7 @@ pub fn routes(
 
 	let confidence = warp::path!("v1" / "confidence" / u32)
 		.and(with_db(db.clone()))
+		.and(with_sampling_strategy(sampling_strategy.clone()))
 		.and(with_state(state.clone()))
 		.map(handlers::confidence);
 
@@ -57,6 +71,7 @@ pub fn routes(
 		.and(with_app_id(app_id))
 		.and(with_state(state))
 		.and(with_db(db))
+		.and(with_sampling_strategy(sampling_strategy))
 		.map(handlers::status);
 
 	warp::get().and(mode.or(latest_block).or(confidence).or(appdata).or(status))
@@ -0,0 +1,124 @@
+use async_trait::async_trait;
+use color_eyre::{eyre::WrapErr, Result};
+use dusk_plonk::commitment_scheme::kzg10::PublicParameters;
+use kate_recovery::{
+	config,
+	data::Cell,
+	matrix::{Dimensions, Position},
+};
+use mockall::automock;
+use std::sync::Arc;
+use tracing::debug;
+
+use crate::{cell_cache, data::Database, network::p2p, proof};
+
+#[async_trait]
+#[automock]
+pub trait Cells {
+	/// Fetches cells and verifies their proofs.
+	/// Returns verified cells and positions of cells which are not fetched or verified.
+	async fn fetch_verified_cells(
+		&self,
+		block_number: u32,
+		dimensions: Dimensions,
+		commitments: &[[u8; config::COMMITMENT_SIZE]],
+		positions: &[Position],
+	) -> Result<(Vec<Cell>, Vec<Position>)>;
+
+	/// Fetches rows and verifies them against the commitments.
+	/// Returns verified rows and indexes of rows which are not fetched or verified.
+	async fn fetch_verified_rows(
+		&self,
+		block_number: u32,
+		dimensions: Dimensions,
+		commitments: &[[u8; config::COMMITMENT_SIZE]],
+		rows: &[u32],
+	) -> Result<(Vec<(u32, Vec<u8>)>, Vec<u32>)>;
+}
+
+/// Cells and rows fetched from the DHT, with verified cells cached in the database
+#[derive(Clone)]
+pub struct DHTCells<T: Database> {
+	pub p2p_client: p2p::Client,
+	pub pp: Arc<PublicParameters>,
+	pub db: T,
+	pub cell_cache_blocks: u32,
+}
+
+#[async_trait]
+impl<T: Database + Send + Sync> Cells for DHTCells<T> {
+	async fn fetch_verified_cells(
+		&self,
+		block_number: u32,
+		dimensions: Dimensions,
+		commitments: &[[u8; config::COMMITMENT_SIZE]],
+		positions: &[Position],
+	) -> Result<(Vec<Cell>, Vec<Position>)> {
+		let (mut cached, positions) = match self.cell_cache_blocks {
+			0 => (vec![], positions.to_vec()),
+			_ => cell_cache::get(&self.db, block_number, positions)
+				.wrap_err("Failed to get cached cells")?,
+		};
+		if positions.is_empty() {
+			return Ok((cached, positions));
+		}
+
+		let (mut fetched, mut unfetched) = self
+			.p2p_client
+			.fetch_cells_from_dht(block_number, &positions)
+			.await;
+
+		let (verified, mut unverified) = proof::verify(
+			block_number,
+			dimensions,
+			&fetched,
+			commitments,
+			self.pp.clone(),
+		)
+		.await
+		.wrap_err("Failed to verify fetched cells")?;
+
+		fetched.retain(|cell| verified.contains(&cell.position));
+		if let Err(error) =
+			cell_cache::put(&self.db, block_number, &fetched, self.cell_cache_blocks)
+		{
+			debug!(block_number, "Cannot cache cells: {error:#}");
+		}
+
+		unfetched.append(&mut unverified);
+		cached.append(&mut fetched);
+		Ok((cached, unfetched))
+	}
+
+	async fn fetch_verified_rows(
+		&self,
+		block_number: u32,
+		dimensions: Dimensions,
+		commitments: &[[u8; config::COMMITMENT_SIZE]],
+		rows: &[u32],
+	) -> Result<(Vec<(u32, Vec<u8>)>, Vec<u32>)> {
+		let fetched_rows = self
+			.p2p_client
+			.fetch_rows_from_dht(block_number, dimensions, rows)
+			.await;
+
+		let mut fetched = rows
+			.iter()
+			.filter_map(|&row| {
+				let data = fetched_rows.get(row as usize)?.clone()?;
+				Some((row, data))
+			})
+			.collect::<Vec<_>>();
+
+		let (verified, _) = proof::verify_rows(dimensions, &fetched, commitments, &self.pp)
+			.wrap_err("Failed to verify fetched rows")?;
+
+		fetched.retain(|(row, _)| verified.contains(row));
+		let missing = rows
+			.iter()
+			.filter(|row| !verified.contains(row))
+			.copied()
+			.collect();
+		Ok((fetched, missing))
+	}
+}
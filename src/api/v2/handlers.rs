@@ -1,9 +1,11 @@
 use super::{
-	transactions,
+	cells, p2p, rpc, transactions,
 	types::{
-		block_status, filter_fields, Block, BlockStatus, DataQuery, DataResponse, DataTransaction,
-		Error, FieldsQueryParameter, Header, Status, SubmitResponse, Subscription, SubscriptionId,
-		Transaction, Version, WsClients,
+		block_status, filter_fields, Block, BlockStatus, BlockedPeers, CellsQuery, CellsResponse,
+		DHTStats, DataQuery, DataResponse, DataTransaction, Error, FieldsQueryParameter, Header,
+		InvalidProofs, InvalidProofsQuery, LocalInfo, Peers, Retries, RowsQuery, RowsResponse,
+		RpcNode, RpcNodes, Status, SubmitResponse, Subscription, SubscriptionId, Transaction,
+		Version, WsClients,
 	},
 	ws,
 };
@@ -11,14 +13,22 @@ use crate::{
 	api::v2::types::{ErrorCode, InternalServerError},
 	data::Database,
 	data::Key,
-	types::{RuntimeConfig, State},
-	utils::calculate_confidence,
+	invalid_proof, retry_queue,
+	sampling::{self, stored_confidence},
+	types::{RpcHealthConfig, RuntimeConfig, State},
+	utils::extract_kate,
 };
 use avail_subxt::primitives;
 use color_eyre::{eyre::eyre, Result};
 use hyper::StatusCode;
+use kate_recovery::{
+	commitments, config,
+	matrix::{Dimensions, Position},
+};
+use libp2p::PeerId;
 use std::{
 	convert::Infallible,
+	str::FromStr,
 	sync::{Arc, Mutex},
 };
 use tracing::error;
@@ -101,10 +111,15 @@ pub async fn block(
 		return Err(Error::not_found());
 	};
 
+	let sampling_strategy = sampling::new(config.sampling_strategy);
 	let confidence = db
 		.get(Key::VerifiedCellCount(block_number))
-		.map_err(Error::internal_server_error)?
-		.map(calculate_confidence);
+		.and_then(|count| {
+			count
+				.map(|count| stored_confidence(&db, &*sampling_strategy, block_number, count))
+				.transpose()
+		})
+		.map_err(Error::internal_server_error)?;
 
 	Ok(Block::new(block_status, confidence))
 }
@@ -123,7 +138,10 @@ pub async fn block_header(
 
 	if matches!(
 		block_status,
-		BlockStatus::Unavailable | BlockStatus::Pending | BlockStatus::VerifyingHeader
+		BlockStatus::Unavailable
+			| BlockStatus::Pending
+			| BlockStatus::VerifyingHeader
+			| BlockStatus::Failed
 	) {
 		return Err(Error::bad_request_unknown("Block header is not available"));
 	};
@@ -182,6 +200,232 @@ pub async fn block_data(
 	})
 }
 
+/// Maximum number of cells which can be requested at once
+const MAX_CELLS_PER_REQUEST: usize = 64;
+
+/// Maximum number of rows which can be requested at once
+const MAX_ROWS_PER_REQUEST: usize = 16;
+
+/// Maximum number of blocks for which invalid proofs can be requested at once
+const MAX_INVALID_PROOF_BLOCKS_PER_REQUEST: u32 = 1000;
+
+/// Gets dimensions and commitments of the block with verified header.
+fn verified_block_matrix(
+	block_number: u32,
+	config: &RuntimeConfig,
+	state: &Arc<Mutex<State>>,
+	db: &impl Database,
+) -> Result<(Dimensions, Vec<[u8; config::COMMITMENT_SIZE]>), Error> {
+	let block_status = {
+		let state = state.lock().expect("Lock should be acquired");
+		block_status(&config.sync_start_block, &state, block_number)
+	};
+
+	if !matches!(
+		block_status,
+		Some(BlockStatus::VerifyingConfidence | BlockStatus::VerifyingData | BlockStatus::Finished)
+	) {
+		return Err(Error::not_found());
+	};
+
+	let Some(header) = db
+		.get::<primitives::Header>(Key::BlockHeader(block_number))
+		.map_err(Error::internal_server_error)?
+	else {
+		return Err(Error::not_found());
+	};
+
+	let (rows, cols, _, commitment) = extract_kate(&header.extension);
+	let dimensions = Dimensions::new(rows, cols)
+		.ok_or_else(|| Error::internal_server_error(eyre!("Invalid dimensions")))?;
+	let commitments = commitments::from_slice(&commitment)
+		.map_err(|error| Error::internal_server_error(eyre!("{error:?}")))?;
+
+	Ok((dimensions, commitments))
+}
+
+pub async fn block_cells(
+	block_number: u32,
+	query: CellsQuery,
+	config: RuntimeConfig,
+	state: Arc<Mutex<State>>,
+	db: impl Database,
+	cells: Arc<impl cells::Cells>,
+) -> Result<CellsResponse, Error> {
+	let positions = query.positions.0;
+	if positions.len() > MAX_CELLS_PER_REQUEST {
+		return Err(Error::bad_request_unknown("Too many positions requested"));
+	}
+
+	let (dimensions, commitments) = verified_block_matrix(block_number, &config, &state, &db)?;
+
+	let is_valid = |position: &Position| {
+		position.row < dimensions.extended_rows() && position.col < dimensions.cols().get()
+	};
+	if !positions.iter().all(is_valid) {
+		return Err(Error::bad_request_unknown("Invalid position"));
+	}
+
+	let (fetched, missing) = cells
+		.fetch_verified_cells(block_number, dimensions, &commitments, &positions)
+		.await
+		.map_err(Error::internal_server_error)?;
+
+	Ok(CellsResponse {
+		block_number,
+		cells: fetched.into_iter().map(From::from).collect(),
+		missing: missing.into_iter().map(From::from).collect(),
+	})
+}
+
+pub async fn block_rows(
+	block_number: u32,
+	query: RowsQuery,
+	config: RuntimeConfig,
+	state: Arc<Mutex<State>>,
+	db: impl Database,
+	cells: Arc<impl cells::Cells>,
+) -> Result<RowsResponse, Error> {
+	let rows = query.rows.0;
+	if rows.len() > MAX_ROWS_PER_REQUEST {
+		return Err(Error::bad_request_unknown("Too many rows requested"));
+	}
+
+	let (dimensions, commitments) = verified_block_matrix(block_number, &config, &state, &db)?;
+
+	if rows.iter().any(|&row| row >= dimensions.extended_rows()) {
+		return Err(Error::bad_request_unknown("Invalid row"));
+	}
+
+	let (fetched, missing) = cells
+		.fetch_verified_rows(block_number, dimensions, &commitments, &rows)
+		.await
+		.map_err(Error::internal_server_error)?;
+
+	Ok((block_number, fetched, missing).into())
+}
+
+pub async fn retries(db: impl Database) -> Result<Retries, Error> {
+	let blocks = retry_queue::list(&db).map_err(Error::internal_server_error)?;
+	Ok(Retries {
+		blocks: blocks.into_iter().map(From::from).collect(),
+	})
+}
+
+pub async fn invalid_proofs(
+	query: InvalidProofsQuery,
+	state: Arc<Mutex<State>>,
+	db: impl Database,
+) -> Result<InvalidProofs, Error> {
+	let to = query.to.unwrap_or_else(|| state.lock().unwrap().latest);
+	let from = query
+		.from
+		.unwrap_or_else(|| to.saturating_sub(MAX_INVALID_PROOF_BLOCKS_PER_REQUEST - 1));
+	if from > to {
+		return Err(Error::bad_request_unknown("Invalid block range"));
+	}
+	if to - from >= MAX_INVALID_PROOF_BLOCKS_PER_REQUEST {
+		return Err(Error::bad_request_unknown("Too many blocks requested"));
+	}
+
+	let invalid_proofs = invalid_proof::list(&db, from..to.saturating_add(1))
+		.map_err(Error::internal_server_error)?;
+	Ok(InvalidProofs {
+		invalid_proofs: invalid_proofs.into_iter().map(From::from).collect(),
+	})
+}
+
+pub async fn rpc_nodes(
+	config: RuntimeConfig,
+	nodes: Arc<impl rpc::Nodes>,
+) -> Result<RpcNodes, Error> {
+	let (health, connected_host) = nodes.nodes().await.map_err(Error::internal_server_error)?;
+	let health_config = RpcHealthConfig::from(&config);
+	Ok(RpcNodes {
+		nodes: health
+			.into_iter()
+			.map(|health| RpcNode::new(health, &connected_host, &health_config))
+			.collect(),
+	})
+}
+
+pub async fn local_info(network: Arc<impl p2p::Network>) -> Result<LocalInfo, Error> {
+	let local_info = network
+		.local_info()
+		.await
+		.map_err(Error::internal_server_error)?;
+	Ok(local_info.into())
+}
+
+pub async fn peers(network: Arc<impl p2p::Network>) -> Result<Peers, Error> {
+	let peers = network
+		.peers()
+		.await
+		.map_err(Error::internal_server_error)?;
+	Ok(Peers {
+		peers: peers.into_iter().map(From::from).collect(),
+	})
+}
+
+pub async fn dht_stats(network: Arc<impl p2p::Network>) -> Result<DHTStats, Error> {
+	let stats = network
+		.dht_stats()
+		.await
+		.map_err(Error::internal_server_error)?;
+	Ok(stats.into())
+}
+
+pub async fn blocked_peers(blocklist: Arc<impl p2p::Blocklist>) -> Result<BlockedPeers, Error> {
+	let peer_ids = blocklist
+		.list()
+		.await
+		.map_err(Error::internal_server_error)?;
+	Ok(BlockedPeers { peer_ids })
+}
+
+fn parse_peer_id(peer_id: &str) -> Result<PeerId, Error> {
+	PeerId::from_str(peer_id).map_err(|_| Error::bad_request_unknown("Invalid peer ID"))
+}
+
+/// Authorizes the request with the configured admin token.
+/// Request is not authorized if the admin token is not configured.
+fn authorize(authorization: Option<String>, admin_token: Option<String>) -> Result<(), Error> {
+	match (authorization, admin_token) {
+		(Some(authorization), Some(token)) if authorization == format!("Bearer {token}") => Ok(()),
+		_ => Err(Error::unauthorized()),
+	}
+}
+
+pub async fn block_peer(
+	peer_id: String,
+	authorization: Option<String>,
+	admin_token: Option<String>,
+	blocklist: Arc<impl p2p::Blocklist>,
+) -> Result<StatusCode, Error> {
+	authorize(authorization, admin_token)?;
+	let peer_id = parse_peer_id(&peer_id)?;
+	blocklist
+		.block(peer_id)
+		.await
+		.map_err(Error::internal_server_error)?;
+	Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn unblock_peer(
+	peer_id: String,
+	authorization: Option<String>,
+	admin_token: Option<String>,
+	blocklist: Arc<impl p2p::Blocklist>,
+) -> Result<StatusCode, Error> {
+	authorize(authorization, admin_token)?;
+	let peer_id = parse_peer_id(&peer_id)?;
+	blocklist
+		.unblock(peer_id)
+		.await
+		.map_err(Error::internal_server_error)?;
+	Ok(StatusCode::NO_CONTENT)
+}
+
 pub async fn handle_rejection(error: Rejection) -> Result<impl Reply, Rejection> {
 	if error.find::<InternalServerError>().is_some() {
 		return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response());
@@ -1,4 +1,5 @@
 use avail_subxt::AvailConfig;
+use dusk_plonk::commitment_scheme::kzg10::PublicParameters;
 use sp_core::sr25519::Pair;
 use std::{
 	convert::Infallible,
@@ -12,17 +13,22 @@ use warp::{Filter, Rejection, Reply};
 
 use self::{
 	handlers::{handle_rejection, log_internal_server_error},
-	types::{DataQuery, PublishMessage, Version, WsClients},
+	types::{
+		CellsQuery, DataQuery, InvalidProofsQuery, PublishMessage, RowsQuery, Version, WsClients,
+	},
 };
 
 use crate::{
 	api::v2::types::Topic,
 	data::Database,
-	network::rpc::Client,
+	network::{self, rpc::Client},
 	types::{IdentityConfig, RuntimeConfig, State},
 };
 
+mod cells;
 mod handlers;
+mod p2p;
+mod rpc;
 mod transactions;
 pub mod types;
 mod ws;
@@ -108,6 +114,165 @@ fn block_data_route(
 		.map(log_internal_server_error)
 }
 
+fn with_cells<T: cells::Cells + Send + Sync>(
+	cells: Arc<T>,
+) -> impl Filter<Extract = (Arc<T>,), Error = Infallible> + Clone {
+	warp::any().map(move || cells.clone())
+}
+
+fn block_cells_route(
+	config: RuntimeConfig,
+	state: Arc<Mutex<State>>,
+	db: impl Database + Clone + Send,
+	cells: Arc<impl cells::Cells + Send + Sync + 'static>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "blocks" / u32 / "cells")
+		.and(warp::get())
+		.and(warp::query::<CellsQuery>())
+		.and(warp::any().map(move || config.clone()))
+		.and(warp::any().map(move || state.clone()))
+		.and(with_db(db))
+		.and(with_cells(cells))
+		.then(handlers::block_cells)
+		.map(log_internal_server_error)
+}
+
+fn block_rows_route(
+	config: RuntimeConfig,
+	state: Arc<Mutex<State>>,
+	db: impl Database + Clone + Send,
+	cells: Arc<impl cells::Cells + Send + Sync + 'static>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "blocks" / u32 / "rows")
+		.and(warp::get())
+		.and(warp::query::<RowsQuery>())
+		.and(warp::any().map(move || config.clone()))
+		.and(warp::any().map(move || state.clone()))
+		.and(with_db(db))
+		.and(with_cells(cells))
+		.then(handlers::block_rows)
+		.map(log_internal_server_error)
+}
+
+fn retries_route(
+	db: impl Database + Clone + Send,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "retries")
+		.and(warp::get())
+		.and(with_db(db))
+		.then(handlers::retries)
+		.map(log_internal_server_error)
+}
+
+fn invalid_proofs_route(
+	state: Arc<Mutex<State>>,
+	db: impl Database + Clone + Send,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "invalid-proofs")
+		.and(warp::get())
+		.and(warp::query::<InvalidProofsQuery>())
+		.and(warp::any().map(move || state.clone()))
+		.and(with_db(db))
+		.then(handlers::invalid_proofs)
+		.map(log_internal_server_error)
+}
+
+fn with_rpc_nodes<T: rpc::Nodes + Send + Sync>(
+	nodes: Arc<T>,
+) -> impl Filter<Extract = (Arc<T>,), Error = Infallible> + Clone {
+	warp::any().map(move || nodes.clone())
+}
+
+fn rpc_nodes_route(
+	config: RuntimeConfig,
+	nodes: Arc<impl rpc::Nodes + Send + Sync + 'static>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "rpc" / "nodes")
+		.and(warp::get())
+		.and(warp::any().map(move || config.clone()))
+		.and(with_rpc_nodes(nodes))
+		.then(handlers::rpc_nodes)
+		.map(log_internal_server_error)
+}
+
+fn with_network<T: p2p::Network + Send + Sync>(
+	network: Arc<T>,
+) -> impl Filter<Extract = (Arc<T>,), Error = Infallible> + Clone {
+	warp::any().map(move || network.clone())
+}
+
+fn local_info_route(
+	network: Arc<impl p2p::Network + Send + Sync + 'static>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "p2p" / "local" / "info")
+		.and(warp::get())
+		.and(with_network(network))
+		.then(handlers::local_info)
+		.map(log_internal_server_error)
+}
+
+fn peers_route(
+	network: Arc<impl p2p::Network + Send + Sync + 'static>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "p2p" / "peers")
+		.and(warp::get())
+		.and(with_network(network))
+		.then(handlers::peers)
+		.map(log_internal_server_error)
+}
+
+fn dht_stats_route(
+	network: Arc<impl p2p::Network + Send + Sync + 'static>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "p2p" / "dht" / "stats")
+		.and(warp::get())
+		.and(with_network(network))
+		.then(handlers::dht_stats)
+		.map(log_internal_server_error)
+}
+
+fn with_blocklist<T: p2p::Blocklist + Send + Sync>(
+	blocklist: Arc<T>,
+) -> impl Filter<Extract = (Arc<T>,), Error = Infallible> + Clone {
+	warp::any().map(move || blocklist.clone())
+}
+
+fn blocked_peers_route(
+	blocklist: Arc<impl p2p::Blocklist + Send + Sync + 'static>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "p2p" / "peers" / "blocked")
+		.and(warp::get())
+		.and(with_blocklist(blocklist))
+		.then(handlers::blocked_peers)
+		.map(log_internal_server_error)
+}
+
+fn block_peer_route(
+	blocklist: Arc<impl p2p::Blocklist + Send + Sync + 'static>,
+	admin_token: Option<String>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "p2p" / "peers" / "blocked" / String)
+		.and(warp::put())
+		.and(warp::header::optional::<String>("authorization"))
+		.and(warp::any().map(move || admin_token.clone()))
+		.and(with_blocklist(blocklist))
+		.then(handlers::block_peer)
+		.map(log_internal_server_error)
+}
+
+fn unblock_peer_route(
+	blocklist: Arc<impl p2p::Blocklist + Send + Sync + 'static>,
+	admin_token: Option<String>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "p2p" / "peers" / "blocked" / String)
+		.and(warp::delete())
+		.and(warp::header::optional::<String>("authorization"))
+		.and(warp::any().map(move || admin_token.clone()))
+		.and(with_blocklist(blocklist))
+		.then(handlers::unblock_peer)
+		.map(log_internal_server_error)
+}
+
 fn submit_route(
 	submitter: Option<Arc<impl transactions::Submit + Clone + Send + Sync>>,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
@@ -192,8 +357,10 @@ pub fn routes(
 	config: RuntimeConfig,
 	identity_config: IdentityConfig,
 	rpc_client: Client,
+	p2p_client: network::p2p::Client,
+	pp: Arc<PublicParameters>,
 	ws_clients: WsClients,
-	db: impl Database + Clone + Send,
+	db: impl Database + Clone + Send + Sync + 'static,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
 	let version = Version {
 		version,
@@ -203,6 +370,7 @@ pub fn routes(
 	let app_id = config.app_id.as_ref();
 	let pair_signer = <PairSigner<AvailConfig, Pair>>::new(identity_config.avail_key_pair);
 
+	let rpc_nodes = Arc::new(rpc_client.clone());
 	let submitter = app_id.map(|&app_id| {
 		Arc::new(transactions::Submitter {
 			rpc_client,
@@ -211,6 +379,15 @@ pub fn routes(
 		})
 	});
 
+	let network = Arc::new(p2p_client.clone());
+	let cells = Arc::new(cells::DHTCells {
+		p2p_client: p2p_client.clone(),
+		pp,
+		db: db.clone(),
+		cell_cache_blocks: config.cell_cache_blocks,
+	});
+	let blocklist = Arc::new(p2p::PeerBlocklist::new(p2p_client, db.clone()));
+
 	version_route(version.clone())
 		.or(status_route(config.clone(), state.clone()))
 		.or(block_route(config.clone(), state.clone(), db.clone()))
@@ -220,6 +397,33 @@ pub fn routes(
 			db.clone(),
 		))
 		.or(block_data_route(config.clone(), state.clone(), db.clone()))
+		.or(block_cells_route(
+			config.clone(),
+			state.clone(),
+			db.clone(),
+			cells.clone(),
+		))
+		.or(block_rows_route(
+			config.clone(),
+			state.clone(),
+			db.clone(),
+			cells,
+		))
+		.or(retries_route(db.clone()))
+		.or(invalid_proofs_route(state.clone(), db.clone()))
+		.or(rpc_nodes_route(config.clone(), rpc_nodes))
+		.or(local_info_route(network.clone()))
+		.or(peers_route(network.clone()))
+		.or(dht_stats_route(network))
+		.or(blocked_peers_route(blocklist.clone()))
+		.or(block_peer_route(
+			blocklist.clone(),
+			config.api_admin_token.clone(),
+		))
+		.or(unblock_peer_route(
+			blocklist,
+			config.api_admin_token.clone(),
+		))
 		.or(subscriptions_route(ws_clients.clone()))
 		.or(submit_route(submitter.clone()))
 		.or(ws_route(ws_clients, version, config, submitter, state))
@@ -228,7 +432,13 @@ pub fn routes(
 
 #[cfg(test)]
 mod tests {
-	use super::{transactions, types::Transaction};
+	use super::{
+		cells::MockCells,
+		p2p::{MockBlocklist, MockNetwork},
+		rpc::MockNodes,
+		transactions,
+		types::Transaction,
+	};
 	use crate::{
 		api::v2::types::{
 			DataField, ErrorCode, SubmitResponse, Subscription, SubscriptionId, Topic, Version,
@@ -236,7 +446,10 @@ mod tests {
 		},
 		data::Key,
 		data::{mem_db, Database},
-		types::{BlockRange, OptionBlockRange, RuntimeConfig, State},
+		invalid_proof::{self, CellSource, InvalidProof},
+		network::{p2p, rpc::NodeHealth},
+		retry_queue::RetryEntry,
+		types::{BlockRange, KademliaMode, OptionBlockRange, RuntimeConfig, State},
 	};
 	use async_trait::async_trait;
 	use avail_subxt::utils::H256;
@@ -249,15 +462,18 @@ mod tests {
 		primitives::Header as DaHeader,
 	};
 	use hyper::StatusCode;
-	use kate_recovery::matrix::Partition;
+	use kate_recovery::{data::Cell, matrix::Partition};
+	use libp2p::{autonat::NatStatus, Multiaddr, PeerId};
 	use std::{
 		collections::HashSet,
 		str::FromStr,
 		sync::{Arc, Mutex},
+		time::Duration,
 	};
 	use subxt::config::substrate::Digest;
 	use test_case::test_case;
 	use uuid::Uuid;
+	use warp::Filter;
 
 	fn v1() -> Version {
 		Version {
@@ -390,6 +606,292 @@ mod tests {
 		);
 	}
 
+	#[tokio::test]
+	async fn retries_route() {
+		let db = mem_db::MemoryDB::default();
+		let entry = RetryEntry {
+			attempts: 1,
+			failed_at: 1000,
+			next_attempt_at: 2000,
+		};
+		_ = db.put(Key::RetryBlock(10), entry);
+		let route = super::retries_route(db);
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/retries")
+			.reply(&route)
+			.await;
+
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(
+			response.body(),
+			r#"{"blocks":[{"block_number":10,"attempts":1,"failed_at":1000,"next_attempt_at":2000}]}"#
+		);
+	}
+
+	#[tokio::test]
+	async fn invalid_proofs_route() {
+		let db = mem_db::MemoryDB::default();
+		let invalid_proof = InvalidProof {
+			block_number: 10,
+			row: 1,
+			col: 2,
+			source: CellSource::Dht { peer_id: None },
+			cell: vec![0; 4],
+			detected_at: 1000,
+		};
+		invalid_proof::store(&db, invalid_proof.clone()).unwrap();
+		invalid_proof::store(
+			&db,
+			InvalidProof {
+				block_number: 20,
+				..invalid_proof
+			},
+		)
+		.unwrap();
+		let state = Arc::new(Mutex::new(State {
+			latest: 15,
+			..Default::default()
+		}));
+		let route = super::invalid_proofs_route(state, db);
+
+		// Latest blocks are returned by default
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/invalid-proofs")
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(
+			response.body(),
+			r#"{"invalid_proofs":[{"block_number":10,"position":{"row":1,"col":2},"source":{"type":"dht","peer_id":null},"cell":"AAAAAA==","detected_at":1000}]}"#
+		);
+
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/invalid-proofs?from=11&to=20")
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(
+			response.body(),
+			r#"{"invalid_proofs":[{"block_number":20,"position":{"row":1,"col":2},"source":{"type":"dht","peer_id":null},"cell":"AAAAAA==","detected_at":1000}]}"#
+		);
+
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/invalid-proofs?from=0&to=1000")
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+	}
+
+	#[tokio::test]
+	async fn local_info_route() {
+		let mut network = MockNetwork::new();
+		network.expect_local_info().returning(|| {
+			let local_info = p2p::LocalInfo {
+				peer_id: PeerId::from_str("12D3KooWMm1c4pzeLPGkkCJMAgFbsfQ8xmVDusg272icWsaNHWzN")
+					.unwrap(),
+				listeners: vec![Multiaddr::from_str("/ip4/127.0.0.1/tcp/37000").unwrap()],
+				external_addresses: vec![],
+				nat_status: NatStatus::Private,
+				kademlia_mode: KademliaMode::Client,
+			};
+			Box::pin(async move { Ok(local_info) })
+		});
+		let route = super::local_info_route(Arc::new(network));
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/p2p/local/info")
+			.reply(&route)
+			.await;
+
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(
+			response.body(),
+			r#"{"peer_id":"12D3KooWMm1c4pzeLPGkkCJMAgFbsfQ8xmVDusg272icWsaNHWzN","listeners":["/ip4/127.0.0.1/tcp/37000"],"external_addresses":[],"nat_status":"private","kademlia_mode":"client"}"#
+		);
+	}
+
+	#[tokio::test]
+	async fn peers_route() {
+		let mut network = MockNetwork::new();
+		network.expect_peers().returning(|| {
+			let peer_id =
+				PeerId::from_str("12D3KooWMm1c4pzeLPGkkCJMAgFbsfQ8xmVDusg272icWsaNHWzN").unwrap();
+			let info = p2p::PeerInfo {
+				agent_version: Some(
+					"avail-light-client/light-client/1.7.10/rust-client".to_string(),
+				),
+				ping_rtt: Some(Duration::from_millis(42)),
+				is_dialer: true,
+			};
+			Box::pin(async move { Ok(vec![(peer_id, info)]) })
+		});
+		let route = super::peers_route(Arc::new(network));
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/p2p/peers")
+			.reply(&route)
+			.await;
+
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(
+			response.body(),
+			r#"{"peers":[{"peer_id":"12D3KooWMm1c4pzeLPGkkCJMAgFbsfQ8xmVDusg272icWsaNHWzN","agent_version":"avail-light-client/light-client/1.7.10/rust-client","ping_rtt":42,"direction":"outbound"}]}"#
+		);
+	}
+
+	#[tokio::test]
+	async fn rpc_nodes_route() {
+		let mut nodes = MockNodes::new();
+		nodes.expect_nodes().returning(|| {
+			let connected = NodeHealth {
+				host: "ws://127.0.0.1:9944".to_string(),
+				latency: Some(Duration::from_millis(120)),
+				requests: 10,
+				errors: 1,
+				error_rate: 0.2,
+				finalized_head: Some(100),
+				head_lag: 0,
+				incompatibility: None,
+			};
+			let incompatible = NodeHealth {
+				host: "ws://127.0.0.1:9945".to_string(),
+				incompatibility: Some("Genesis hash doesn't match".to_string()),
+				..Default::default()
+			};
+			let connected_host = connected.host.clone();
+			Box::pin(async move { Ok((vec![connected, incompatible], connected_host)) })
+		});
+		let route = super::rpc_nodes_route(RuntimeConfig::default(), Arc::new(nodes));
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/rpc/nodes")
+			.reply(&route)
+			.await;
+
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(
+			response.body(),
+			r#"{"nodes":[{"host":"ws://127.0.0.1:9944","connected":true,"healthy":true,"latency":120,"requests":10,"errors":1,"error_rate":0.2,"finalized_head":100,"head_lag":0},{"host":"ws://127.0.0.1:9945","connected":false,"healthy":false,"requests":0,"errors":0,"error_rate":0.0,"head_lag":0,"incompatibility":"Genesis hash doesn't match"}]}"#
+		);
+	}
+
+	#[tokio::test]
+	async fn dht_stats_route() {
+		let mut network = MockNetwork::new();
+		network.expect_dht_stats().returning(|| {
+			let stats = p2p::DHTStats {
+				records: 100,
+				routing_table_peers: 20,
+				routing_table_buckets: 3,
+				active_blocks: 1,
+			};
+			Box::pin(async move { Ok(stats) })
+		});
+		let route = super::dht_stats_route(Arc::new(network));
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/p2p/dht/stats")
+			.reply(&route)
+			.await;
+
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(
+			response.body(),
+			r#"{"records":100,"routing_table_peers":20,"routing_table_buckets":3,"active_blocks":1}"#
+		);
+	}
+
+	#[tokio::test]
+	async fn blocked_peers_route() {
+		let mut blocklist = MockBlocklist::new();
+		blocklist.expect_list().returning(|| {
+			let peer_ids = vec!["12D3KooWMm1c4pzeLPGkkCJMAgFbsfQ8xmVDusg272icWsaNHWzN".to_string()];
+			Box::pin(async move { Ok(peer_ids) })
+		});
+		let route = super::blocked_peers_route(Arc::new(blocklist));
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/p2p/peers/blocked")
+			.reply(&route)
+			.await;
+
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(
+			response.body(),
+			r#"{"peer_ids":["12D3KooWMm1c4pzeLPGkkCJMAgFbsfQ8xmVDusg272icWsaNHWzN"]}"#
+		);
+	}
+
+	#[tokio::test]
+	async fn block_peer_route() {
+		let peer_id = "12D3KooWMm1c4pzeLPGkkCJMAgFbsfQ8xmVDusg272icWsaNHWzN";
+		let mut blocklist = MockBlocklist::new();
+		blocklist
+			.expect_block()
+			.withf(move |blocked| blocked.to_string() == peer_id)
+			.times(1)
+			.returning(|_| Box::pin(async { Ok(()) }));
+		let route = super::block_peer_route(Arc::new(blocklist), Some("secret".to_string()));
+		let response = warp::test::request()
+			.method("PUT")
+			.path(&format!("/v2/p2p/peers/blocked/{peer_id}"))
+			.header("authorization", "Bearer secret")
+			.reply(&route)
+			.await;
+
+		assert_eq!(response.status(), StatusCode::NO_CONTENT);
+	}
+
+	#[test_case("PUT", None, None ; "Block peer without token")]
+	#[test_case("PUT", Some("secret"), None ; "Block peer without authorization")]
+	#[test_case("DELETE", Some("secret"), Some("Bearer other") ; "Unblock peer with wrong token")]
+	#[test_case("DELETE", None, Some("Bearer secret") ; "Unblock peer when token is not configured")]
+	#[tokio::test]
+	async fn blocked_peer_routes_unauthorized(
+		method: &str,
+		admin_token: Option<&str>,
+		authorization: Option<&str>,
+	) {
+		let peer_id = "12D3KooWMm1c4pzeLPGkkCJMAgFbsfQ8xmVDusg272icWsaNHWzN";
+		// Blocklist is not called
+		let blocklist = Arc::new(MockBlocklist::new());
+		let admin_token = admin_token.map(ToString::to_string);
+		let route = super::block_peer_route(blocklist.clone(), admin_token.clone())
+			.or(super::unblock_peer_route(blocklist, admin_token));
+		let mut request = warp::test::request()
+			.method(method)
+			.path(&format!("/v2/p2p/peers/blocked/{peer_id}"));
+		if let Some(authorization) = authorization {
+			request = request.header("authorization", authorization);
+		}
+		let response = request.reply(&route).await;
+
+		assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+	}
+
+	#[test_case("PUT" ; "Block peer")]
+	#[test_case("DELETE" ; "Unblock peer")]
+	#[tokio::test]
+	async fn blocked_peer_routes_bad_request(method: &str) {
+		let blocklist = Arc::new(MockBlocklist::new());
+		let admin_token = Some("secret".to_string());
+		let route = super::block_peer_route(blocklist.clone(), admin_token.clone())
+			.or(super::unblock_peer_route(blocklist, admin_token));
+		let response = warp::test::request()
+			.method(method)
+			.path("/v2/p2p/peers/blocked/invalid")
+			.header("authorization", "Bearer secret")
+			.reply(&route)
+			.await;
+
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+		assert_eq!(response.body(), "Invalid peer ID");
+	}
+
 	#[test_case(0, r#"Block header is not available"#  ; "Block is unavailable")]
 	#[test_case(6, r#"Block header is not available"#  ; "Block is pending")]
 	#[test_case(10, r#"Block header is not available"#  ; "Block is in verifying-header state")]
@@ -473,6 +975,105 @@ mod tests {
 		);
 	}
 
+	fn header_with_commitments() -> DaHeader {
+		let mut header = header();
+		header.extension = HeaderExtension::V3(v3::HeaderExtension {
+			commitment: KateCommitment {
+				rows: 1,
+				cols: 4,
+				data_root: H256::default(),
+				commitment: vec![0; 96],
+			},
+			app_lookup: CompactDataLookup {
+				size: 0,
+				index: vec![],
+			},
+		});
+		header
+	}
+
+	#[tokio::test]
+	async fn block_cells_route_not_found() {
+		let config = RuntimeConfig::default();
+		let state = Arc::new(Mutex::new(State {
+			latest: 2,
+			header_verified: Some(BlockRange::init(1)),
+			..Default::default()
+		}));
+		let db = mem_db::MemoryDB::default();
+		let route = super::block_cells_route(config, state, db, Arc::new(MockCells::new()));
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/blocks/2/cells?positions=0:1")
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::NOT_FOUND);
+	}
+
+	#[test_case("/v2/blocks/1/cells?positions=2:0", "Invalid position" ; "Row out of range")]
+	#[test_case("/v2/blocks/1/cells?positions=0:4", "Invalid position" ; "Column out of range")]
+	#[test_case("/v2/blocks/1/rows?rows=0,2", "Invalid row" ; "Row index out of range")]
+	#[tokio::test]
+	async fn block_cells_routes_bad_request(path: &str, expected: &str) {
+		let config = RuntimeConfig::default();
+		let state = Arc::new(Mutex::new(State {
+			latest: 1,
+			header_verified: Some(BlockRange::init(1)),
+			..Default::default()
+		}));
+		let db = mem_db::MemoryDB::default();
+		_ = db.put(Key::BlockHeader(1), header_with_commitments());
+		let cells = Arc::new(MockCells::new());
+		let route =
+			super::block_cells_route(config.clone(), state.clone(), db.clone(), cells.clone())
+				.or(super::block_rows_route(config, state, db, cells));
+		let response = warp::test::request()
+			.method("GET")
+			.path(path)
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+		assert_eq!(response.body(), expected);
+	}
+
+	#[tokio::test]
+	async fn block_cells_route_ok() {
+		let config = RuntimeConfig::default();
+		let state = Arc::new(Mutex::new(State {
+			latest: 1,
+			header_verified: Some(BlockRange::init(1)),
+			..Default::default()
+		}));
+		let db = mem_db::MemoryDB::default();
+		_ = db.put(Key::BlockHeader(1), header_with_commitments());
+		let mut cells = MockCells::new();
+		cells
+			.expect_fetch_verified_cells()
+			.returning(|_, _, _, positions| {
+				let fetched = vec![Cell {
+					position: positions[0],
+					content: [1; 80],
+				}];
+				let missing = positions[1..].to_vec();
+				Box::pin(async move { Ok((fetched, missing)) })
+			});
+		let route = super::block_cells_route(config, state, db, Arc::new(cells));
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/blocks/1/cells?positions=0:1,1:3")
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::OK);
+		let proof = "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEB";
+		let data = "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=";
+		assert_eq!(
+			response.body(),
+			&format!(
+				r#"{{"block_number":1,"cells":[{{"position":{{"row":0,"col":1}},"proof":"{proof}","data":"{data}"}}],"missing":[{{"row":1,"col":3}}]}}"#
+			)
+		);
+	}
+
 	#[test_case(0, r#"Block data is not available"#  ; "Block is unavailable")]
 	#[test_case(6, r#"Block data is not available"#  ; "Block is pending")]
 	#[test_case(8, r#"Block data is not available"#  ; "Block is in verifying-data state")]
@@ -592,6 +1193,7 @@ mod tests {
 			Topic::HeaderVerified,
 			Topic::ConfidenceAchieved,
 			Topic::DataVerified,
+			Topic::InvalidProof,
 		]
 		.into_iter()
 		.collect()
@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use color_eyre::Result;
+use libp2p::PeerId;
+use mockall::automock;
+use std::sync::{Arc, Mutex};
+
+use crate::{
+	data::Database,
+	network::p2p::{self, blocklist},
+};
+
+#[async_trait]
+#[automock]
+pub trait Blocklist {
+	async fn list(&self) -> Result<Vec<String>>;
+	async fn block(&self, peer_id: PeerId) -> Result<()>;
+	async fn unblock(&self, peer_id: PeerId) -> Result<()>;
+}
+
+/// Blocklist which blocks peers in the P2P network and persists them in the database
+#[derive(Clone)]
+pub struct PeerBlocklist<T: Database> {
+	p2p_client: p2p::Client,
+	db: T,
+	/// Serializes updates of the stored blocked peers
+	update_lock: Arc<Mutex<()>>,
+}
+
+impl<T: Database> PeerBlocklist<T> {
+	pub fn new(p2p_client: p2p::Client, db: T) -> Self {
+		PeerBlocklist {
+			p2p_client,
+			db,
+			update_lock: Default::default(),
+		}
+	}
+}
+
+#[async_trait]
+impl<T: Database + Send + Sync> Blocklist for PeerBlocklist<T> {
+	async fn list(&self) -> Result<Vec<String>> {
+		self.p2p_client.list_blocked_peers().await
+	}
+
+	async fn block(&self, peer_id: PeerId) -> Result<()> {
+		self.p2p_client.block_peer(peer_id).await?;
+		blocklist::add(&self.db, &self.update_lock, peer_id)
+	}
+
+	async fn unblock(&self, peer_id: PeerId) -> Result<()> {
+		self.p2p_client.unblock_peer(peer_id).await?;
+		blocklist::remove(&self.db, &self.update_lock, &peer_id)
+	}
+}
+
+#[async_trait]
+#[automock]
+pub trait Network {
+	async fn local_info(&self) -> Result<p2p::LocalInfo>;
+	async fn peers(&self) -> Result<Vec<(PeerId, p2p::PeerInfo)>>;
+	async fn dht_stats(&self) -> Result<p2p::DHTStats>;
+}
+
+#[async_trait]
+impl Network for p2p::Client {
+	async fn local_info(&self) -> Result<p2p::LocalInfo> {
+		self.get_local_info().await
+	}
+
+	async fn peers(&self) -> Result<Vec<(PeerId, p2p::PeerInfo)>> {
+		self.list_peers().await
+	}
+
+	async fn dht_stats(&self) -> Result<p2p::DHTStats> {
+		self.get_dht_stats().await
+	}
+}
@@ -0,0 +1,19 @@
+use async_trait::async_trait;
+use color_eyre::Result;
+use mockall::automock;
+
+use crate::network::rpc::{Client, NodeHealth};
+
+#[async_trait]
+#[automock]
+pub trait Nodes {
+	/// Returns health of the configured RPC nodes, and host of the connected node
+	async fn nodes(&self) -> Result<(Vec<NodeHealth>, String)>;
+}
+
+#[async_trait]
+impl Nodes for Client {
+	async fn nodes(&self) -> Result<(Vec<NodeHealth>, String)> {
+		Ok((self.nodes_health(), self.connected_host()))
+	}
+}
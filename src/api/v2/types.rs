@@ -10,7 +10,13 @@ use color_eyre::{
 };
 use derive_more::From;
 use hyper::{http, StatusCode};
-use kate_recovery::{com::AppData, commitments, config, matrix::Partition};
+use kate_recovery::{
+	com::AppData,
+	commitments, config,
+	data::Cell,
+	matrix::{Partition, Position},
+};
+use libp2p::{autonat::NatStatus, PeerId};
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use sp_core::{blake2_256, H256};
 use std::{
@@ -25,9 +31,16 @@ use warp::{
 };
 
 use crate::{
-	network::rpc::Event as RpcEvent,
+	confidence::Confidence,
+	invalid_proof::{CellSource, InvalidProof},
+	network::{
+		p2p,
+		rpc::{self, Event as RpcEvent},
+	},
+	retry_queue::RetryEntry,
 	types::{
-		self, block_matrix_partition_format, BlockVerified, OptionBlockRange, RuntimeConfig, State,
+		self, block_matrix_partition_format, BlockSet, BlockVerified, OptionBlockRange,
+		RpcHealthConfig, RuntimeConfig, State,
 	},
 	utils::decode_app_data,
 };
@@ -53,17 +66,50 @@ impl Reply for Version {
 pub struct BlockRange {
 	pub first: u32,
 	pub last: u32,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub gaps: Vec<BlockRange>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub failed: Vec<BlockRange>,
 }
 
-impl From<&types::BlockRange> for BlockRange {
-	fn from(value: &types::BlockRange) -> Self {
+impl From<(u32, u32)> for BlockRange {
+	fn from((first, last): (u32, u32)) -> Self {
 		BlockRange {
-			first: value.first,
-			last: value.last,
+			first,
+			last,
+			gaps: vec![],
+			failed: vec![],
 		}
 	}
 }
 
+impl BlockRange {
+	/// Creates block range which includes failed blocks within the range
+	pub fn new(range: &types::BlockRange, failed: &BlockSet) -> Self {
+		BlockRange {
+			first: range.first,
+			last: range.last,
+			gaps: range
+				.gaps
+				.intervals()
+				.iter()
+				.copied()
+				.map(From::from)
+				.collect(),
+			failed: failed
+				.intervals_within(range.first, range.last)
+				.map(From::from)
+				.collect(),
+		}
+	}
+}
+
+impl From<&types::BlockRange> for BlockRange {
+	fn from(value: &types::BlockRange) -> Self {
+		BlockRange::new(value, &BlockSet::default())
+	}
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct HistoricalSync {
 	pub synced: bool,
@@ -71,12 +117,37 @@ pub struct HistoricalSync {
 	pub available: Option<BlockRange>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub app_data: Option<BlockRange>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub progress: Option<SyncProgress>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SyncProgress {
+	pub total: u32,
+	pub processed: u32,
+	/// Estimated time until the sync is finished, in seconds
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub eta: Option<u64>,
+}
+
+impl From<&types::SyncProgress> for SyncProgress {
+	fn from(progress: &types::SyncProgress) -> Self {
+		SyncProgress {
+			total: progress.total,
+			processed: progress.processed,
+			eta: progress.eta.map(|eta| eta.as_secs()),
+		}
+	}
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct Blocks {
 	pub latest: u32,
 	#[serde(skip_serializing_if = "Option::is_none")]
+	pub oldest_available: Option<u32>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub pending: Option<BlockRange>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub available: Option<BlockRange>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub app_data: Option<BlockRange>,
@@ -97,6 +168,8 @@ pub struct Status {
 		with = "block_matrix_partition_format"
 	)]
 	pub partition: Option<Partition>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub degraded: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
@@ -161,15 +234,34 @@ impl Reply for SubmitResponse {
 
 impl Status {
 	pub fn new(config: &RuntimeConfig, state: &State) -> Self {
+		let mut failed = state.confidence_failed.clone();
+		for &(first, last) in state.header_failed.intervals() {
+			failed.insert_range(first, last);
+		}
+
 		let historical_sync = state.synced.map(|synced| HistoricalSync {
 			synced,
-			available: state.sync_confidence_achieved.as_ref().map(From::from),
+			available: (state.sync_confidence_achieved.as_ref())
+				.map(|range| BlockRange::new(range, &failed)),
 			app_data: state.sync_data_verified.as_ref().map(From::from),
+			progress: state.sync_progress.as_ref().map(From::from),
+		});
+
+		// Blocks with verified header, which are waiting for the confidence
+		let pending = state.header_verified.last().and_then(|last| {
+			let first = match state.confidence_achieved.last() {
+				Some(confidence_last) => confidence_last + 1,
+				None => state.header_verified.first()?,
+			};
+			(first <= last).then_some(BlockRange::from((first, last)))
 		});
 
 		let blocks = Blocks {
 			latest: state.latest,
-			available: state.confidence_achieved.as_ref().map(From::from),
+			oldest_available: state.oldest_available,
+			pending,
+			available: (state.confidence_achieved.as_ref())
+				.map(|range| BlockRange::new(range, &failed)),
 			app_data: state.data_verified.as_ref().map(From::from),
 			historical_sync,
 		};
@@ -183,6 +275,7 @@ impl Status {
 			network: node.network(),
 			blocks,
 			partition: config.block_matrix_partition,
+			degraded: state.degraded.clone(),
 		}
 	}
 }
@@ -221,6 +314,7 @@ pub enum Topic {
 	HeaderVerified,
 	ConfidenceAchieved,
 	DataVerified,
+	InvalidProof,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -251,6 +345,7 @@ pub enum BlockStatus {
 	VerifyingConfidence,
 	VerifyingData,
 	Finished,
+	Failed,
 }
 
 pub fn block_status(
@@ -269,6 +364,16 @@ pub fn block_status(
 		return Some(BlockStatus::Unavailable);
 	}
 
+	// Blocks older than the oldest available block are pruned
+	if state.oldest_available.unwrap_or(0) > block_number {
+		return Some(BlockStatus::Unavailable);
+	}
+
+	if state.header_failed.contains(block_number) || state.confidence_failed.contains(block_number)
+	{
+		return Some(BlockStatus::Failed);
+	}
+
 	if block_number < first_block {
 		if state.sync_data_verified.contains(block_number) {
 			return Some(BlockStatus::Finished);
@@ -319,6 +424,210 @@ impl Reply for Block {
 	}
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct RetryBlock {
+	pub block_number: u32,
+	pub attempts: u32,
+	pub failed_at: u64,
+	pub next_attempt_at: u64,
+}
+
+impl From<(u32, RetryEntry)> for RetryBlock {
+	fn from((block_number, entry): (u32, RetryEntry)) -> Self {
+		RetryBlock {
+			block_number,
+			attempts: entry.attempts,
+			failed_at: entry.failed_at,
+			next_attempt_at: entry.next_attempt_at,
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Retries {
+	pub blocks: Vec<RetryBlock>,
+}
+
+impl Reply for Retries {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BlockedPeers {
+	pub peer_ids: Vec<String>,
+}
+
+impl Reply for BlockedPeers {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum NatStatusType {
+	Public,
+	Private,
+	Unknown,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LocalInfo {
+	pub peer_id: String,
+	pub listeners: Vec<String>,
+	pub external_addresses: Vec<String>,
+	pub nat_status: NatStatusType,
+	/// Public address confirmed by AutoNAT, if NAT status is public
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub public_address: Option<String>,
+	pub kademlia_mode: String,
+}
+
+impl From<p2p::LocalInfo> for LocalInfo {
+	fn from(info: p2p::LocalInfo) -> Self {
+		let (nat_status, public_address) = match info.nat_status {
+			NatStatus::Public(address) => (NatStatusType::Public, Some(address.to_string())),
+			NatStatus::Private => (NatStatusType::Private, None),
+			NatStatus::Unknown => (NatStatusType::Unknown, None),
+		};
+		LocalInfo {
+			peer_id: info.peer_id.to_string(),
+			listeners: info.listeners.iter().map(ToString::to_string).collect(),
+			external_addresses: info
+				.external_addresses
+				.iter()
+				.map(ToString::to_string)
+				.collect(),
+			nat_status,
+			public_address,
+			kademlia_mode: info.kademlia_mode.to_string(),
+		}
+	}
+}
+
+impl Reply for LocalInfo {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConnectionDirection {
+	Inbound,
+	Outbound,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Peer {
+	pub peer_id: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub agent_version: Option<String>,
+	/// Latest round-trip time measured via ping protocol, in milliseconds
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub ping_rtt: Option<u64>,
+	pub direction: ConnectionDirection,
+}
+
+impl From<(PeerId, p2p::PeerInfo)> for Peer {
+	fn from((peer_id, info): (PeerId, p2p::PeerInfo)) -> Self {
+		Peer {
+			peer_id: peer_id.to_string(),
+			agent_version: info.agent_version,
+			ping_rtt: info.ping_rtt.map(|rtt| rtt.as_millis() as u64),
+			direction: if info.is_dialer {
+				ConnectionDirection::Outbound
+			} else {
+				ConnectionDirection::Inbound
+			},
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Peers {
+	pub peers: Vec<Peer>,
+}
+
+impl Reply for Peers {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DHTStats {
+	pub records: usize,
+	pub routing_table_peers: usize,
+	pub routing_table_buckets: usize,
+	pub active_blocks: usize,
+}
+
+impl From<p2p::DHTStats> for DHTStats {
+	fn from(stats: p2p::DHTStats) -> Self {
+		DHTStats {
+			records: stats.records,
+			routing_table_peers: stats.routing_table_peers,
+			routing_table_buckets: stats.routing_table_buckets,
+			active_blocks: stats.active_blocks,
+		}
+	}
+}
+
+impl Reply for DHTStats {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RpcNode {
+	pub host: String,
+	pub connected: bool,
+	pub healthy: bool,
+	/// Average latency of the RPC calls, in milliseconds
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub latency: Option<u64>,
+	pub requests: u64,
+	pub errors: u64,
+	pub error_rate: f64,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub finalized_head: Option<u32>,
+	pub head_lag: u32,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub incompatibility: Option<String>,
+}
+
+impl RpcNode {
+	pub fn new(health: rpc::NodeHealth, connected_host: &str, cfg: &RpcHealthConfig) -> Self {
+		RpcNode {
+			connected: health.host == connected_host,
+			healthy: health.is_healthy(cfg),
+			latency: health.latency.map(|latency| latency.as_millis() as u64),
+			requests: health.requests,
+			errors: health.errors,
+			error_rate: health.error_rate,
+			finalized_head: health.finalized_head,
+			head_lag: health.head_lag,
+			incompatibility: health.incompatibility,
+			host: health.host,
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RpcNodes {
+	pub nodes: Vec<RpcNode>,
+}
+
+impl Reply for RpcNodes {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
 impl TryFrom<avail_subxt::primitives::Header> for HeaderMessage {
 	type Error = Report;
 
@@ -451,6 +760,8 @@ pub struct ConfidenceMessage {
 	block_number: u32,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	confidence: Option<f64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	details: Option<Confidence>,
 }
 
 impl TryFrom<BlockVerified> for PublishMessage {
@@ -459,11 +770,65 @@ impl TryFrom<BlockVerified> for PublishMessage {
 	fn try_from(value: BlockVerified) -> Result<Self, Self::Error> {
 		Ok(PublishMessage::ConfidenceAchieved(ConfidenceMessage {
 			block_number: value.block_num,
-			confidence: value.confidence,
+			confidence: value.confidence.as_ref().map(|confidence| confidence.value),
+			details: value.confidence,
 		}))
 	}
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CellPosition {
+	row: u32,
+	col: u16,
+}
+
+impl From<Position> for CellPosition {
+	fn from(Position { row, col }: Position) -> Self {
+		CellPosition { row, col }
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InvalidProofMessage {
+	block_number: u32,
+	position: CellPosition,
+	source: CellSource,
+	cell: Base64,
+	detected_at: u64,
+}
+
+impl From<InvalidProof> for InvalidProofMessage {
+	fn from(value: InvalidProof) -> Self {
+		InvalidProofMessage {
+			block_number: value.block_number,
+			position: CellPosition {
+				row: value.row,
+				col: value.col,
+			},
+			source: value.source,
+			cell: Base64(value.cell),
+			detected_at: value.detected_at,
+		}
+	}
+}
+
+impl From<InvalidProof> for PublishMessage {
+	fn from(value: InvalidProof) -> Self {
+		PublishMessage::InvalidProof(value.into())
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct InvalidProofs {
+	pub invalid_proofs: Vec<InvalidProofMessage>,
+}
+
+impl Reply for InvalidProofs {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(try_from = "String")]
 pub struct FieldsQueryParameter(pub HashSet<DataField>);
@@ -481,6 +846,131 @@ impl TryFrom<String> for FieldsQueryParameter {
 	}
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(try_from = "String")]
+pub struct PositionsQueryParameter(pub Vec<Position>);
+
+impl TryFrom<String> for PositionsQueryParameter {
+	type Error = Report;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value
+			.split(',')
+			.map(|part| {
+				let (row, col) = part
+					.split_once(':')
+					.ok_or_else(|| eyre!("Invalid position {part}"))?;
+				let row = row.parse::<u32>().wrap_err("Cannot parse row")?;
+				let col = col.parse::<u16>().wrap_err("Cannot parse column")?;
+				Ok(Position { row, col })
+			})
+			.collect::<Result<Vec<_>>>()
+			.map(PositionsQueryParameter)
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CellsQuery {
+	pub positions: PositionsQueryParameter,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(try_from = "String")]
+pub struct RowsQueryParameter(pub Vec<u32>);
+
+impl TryFrom<String> for RowsQueryParameter {
+	type Error = Report;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value
+			.split(',')
+			.map(|part| part.parse::<u32>().wrap_err("Cannot parse row"))
+			.collect::<Result<Vec<_>>>()
+			.map(RowsQueryParameter)
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RowsQuery {
+	pub rows: RowsQueryParameter,
+}
+
+/// Inclusive range of blocks, defaults to the latest blocks
+#[derive(Serialize, Deserialize)]
+pub struct InvalidProofsQuery {
+	pub from: Option<u32>,
+	pub to: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VerifiedCell {
+	position: CellPosition,
+	proof: Base64,
+	data: Base64,
+}
+
+impl From<Cell> for VerifiedCell {
+	fn from(cell: Cell) -> Self {
+		// Cell content is the proof, followed by the data
+		let (proof, data) = cell.content.split_at(config::COMMITMENT_SIZE);
+		VerifiedCell {
+			position: cell.position.into(),
+			proof: Base64(proof.to_vec()),
+			data: Base64(data.to_vec()),
+		}
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CellsResponse {
+	pub block_number: u32,
+	pub cells: Vec<VerifiedCell>,
+	/// Positions of the cells which are not available or failed proof verification
+	pub missing: Vec<CellPosition>,
+}
+
+impl Reply for CellsResponse {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VerifiedRow {
+	row: u32,
+	data: Base64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RowsResponse {
+	pub block_number: u32,
+	pub rows: Vec<VerifiedRow>,
+	/// Indexes of the rows which are not available or failed verification
+	pub missing: Vec<u32>,
+}
+
+impl From<(u32, Vec<(u32, Vec<u8>)>, Vec<u32>)> for RowsResponse {
+	fn from((block_number, rows, missing): (u32, Vec<(u32, Vec<u8>)>, Vec<u32>)) -> Self {
+		RowsResponse {
+			block_number,
+			rows: rows
+				.into_iter()
+				.map(|(row, data)| VerifiedRow {
+					row,
+					data: Base64(data),
+				})
+				.collect(),
+			missing,
+		}
+	}
+}
+
+impl Reply for RowsResponse {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct DataQuery {
 	pub fields: Option<FieldsQueryParameter>,
@@ -557,6 +1047,7 @@ pub enum PublishMessage {
 	HeaderVerified(Box<HeaderMessage>),
 	ConfidenceAchieved(ConfidenceMessage),
 	DataVerified(DataMessage),
+	InvalidProof(InvalidProofMessage),
 }
 
 impl PublishMessage {
@@ -567,6 +1058,7 @@ impl PublishMessage {
 			PublishMessage::DataVerified(data) => {
 				filter_fields(&mut data.data_transactions, fields)
 			},
+			PublishMessage::InvalidProof(_) => (),
 		}
 	}
 }
@@ -708,6 +1200,7 @@ impl TryFrom<ws::Message> for Request {
 pub enum ErrorCode {
 	NotFound,
 	BadRequest,
+	Unauthorized,
 	InternalServerError,
 }
 
@@ -757,10 +1250,15 @@ impl Error {
 		Self::new(Some(request_id), None, ErrorCode::BadRequest, message)
 	}
 
+	pub fn unauthorized() -> Self {
+		Self::new(None, None, ErrorCode::Unauthorized, "Unauthorized")
+	}
+
 	fn status(&self) -> StatusCode {
 		match self.error_code {
 			ErrorCode::NotFound => StatusCode::NOT_FOUND,
 			ErrorCode::BadRequest => StatusCode::BAD_REQUEST,
+			ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
 			ErrorCode::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
 		}
 	}
@@ -848,6 +1346,7 @@ mod tests {
 		PublishMessage::ConfidenceAchieved(ConfidenceMessage {
 			block_number: 1,
 			confidence: Some(1.0),
+			details: None,
 		})
 	}
 
@@ -951,6 +1450,14 @@ mod tests {
 		assert_eq!(block_status(&Some(10), &state, 0), unavailable);
 		assert_eq!(block_status(&Some(10), &state, 9), unavailable);
 		assert_ne!(block_status(&Some(9), &state, 9), unavailable);
+
+		let state = State {
+			latest: 10,
+			oldest_available: Some(5),
+			..Default::default()
+		};
+		assert_eq!(block_status(&Some(1), &state, 4), unavailable);
+		assert_ne!(block_status(&Some(1), &state, 5), unavailable);
 	}
 
 	#[test]
@@ -1049,6 +1556,42 @@ mod tests {
 		assert_ne!(block_status(&Some(1), &state, 6), verifying_data);
 	}
 
+	#[test]
+	fn block_status_failed() {
+		let mut state = State::default();
+		let failed = Some(BlockStatus::Failed);
+		state.latest = 10;
+		state.header_verified.set(1);
+		state.confidence_achieved.set(1);
+		state.data_verified.set(1);
+		state.confidence_failed.insert(3);
+		state.confidence_failed.insert(5);
+		state.confidence_failed.insert(4);
+		state.header_verified.set(6);
+		state.confidence_achieved.set(6);
+		state.data_verified.set(6);
+		assert_eq!(block_status(&None, &state, 3), failed);
+		assert_eq!(block_status(&None, &state, 4), failed);
+		assert_eq!(block_status(&None, &state, 5), failed);
+		assert_ne!(block_status(&None, &state, 2), failed);
+		assert_ne!(block_status(&None, &state, 6), failed);
+		assert_eq!(state.confidence_failed.intervals(), &[(3, 5)]);
+
+		state.confidence_failed.remove(4);
+		assert_eq!(block_status(&None, &state, 4), Some(BlockStatus::Finished));
+		assert_eq!(state.confidence_failed.intervals(), &[(3, 3), (5, 5)]);
+
+		let mut state = State {
+			latest: 10,
+			..Default::default()
+		};
+		state.sync_header_verified.set(1);
+		state.header_failed.insert(2);
+		state.sync_header_verified.set(3);
+		assert_eq!(block_status(&Some(1), &state, 2), failed);
+		assert_ne!(block_status(&Some(1), &state, 3), failed);
+	}
+
 	#[test]
 	fn block_status_finished() {
 		let mut state = State::default();
@@ -435,9 +435,10 @@ pub async fn run(
 		block_number: u32,
 	) {
 		let mut state = state.lock().expect("State lock can be acquired");
+		// Retried and synced blocks can be received out of order
 		match sync_range.contains(&block_number) {
-			true => state.sync_data_verified.set(block_number),
-			false => state.data_verified.set(block_number),
+			true => state.sync_data_verified.insert(block_number),
+			false => state.data_verified.insert(block_number),
 		}
 		if state.synced == Some(false) && sync_range.clone().last() == Some(block_number) {
 			state.synced.replace(true);
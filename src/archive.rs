@@ -0,0 +1,375 @@
+//! Portable archive of the verified light client history.
+//!
+//! Archive contains verified block headers, verified cell counts (confidence) and app data of the configured app.
+//! File starts with the manifest, followed by the records, where both are SCALE encoded and prefixed with the length
+//! as little endian `u32`. Headers are exported in ascending order, so their parent hash linkage is validated on
+//! import, before anything is written into the database. Archived headers are not trusted on their own, so the
+//! archived chain has to be linked to the trusted headers stored before and after the archived blocks.
+//! Finality sync checkpoint is not archived, since it cannot be verified on import.
+
+use avail_subxt::primitives::Header;
+use codec::{Decode, Encode};
+use color_eyre::{
+	eyre::{eyre, WrapErr},
+	Result,
+};
+use std::{
+	io::{ErrorKind, Read, Seek, SeekFrom, Write},
+	ops::RangeInclusive,
+};
+use tracing::info;
+
+use crate::{
+	data::{Database, Key},
+	header_chain::{self, header_hash},
+	types::DEV_FLAG_GENHASH,
+};
+
+/// Version of the archive format
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// Maximum size of the archived item, which is well above the size of the block app data
+const MAX_ITEM_SIZE: u32 = 64 * 1024 * 1024;
+
+#[derive(Clone, Debug, PartialEq, Encode, Decode)]
+pub struct Manifest {
+	pub version: u32,
+	pub genesis_hash: String,
+	pub first_block: u32,
+	pub last_block: u32,
+	pub app_id: Option<u32>,
+}
+
+#[derive(Encode, Decode)]
+enum Record {
+	BlockHeader(Header),
+	VerifiedCellCount(u32, u32),
+	AppData(u32, Vec<Vec<u8>>),
+}
+
+fn write_item(writer: &mut impl Write, item: &impl Encode) -> Result<()> {
+	let bytes = item.encode();
+	let length = u32::try_from(bytes.len()).wrap_err("Archive item is too large")?;
+	writer.write_all(&length.to_le_bytes())?;
+	writer.write_all(&bytes)?;
+	Ok(())
+}
+
+/// Reads next item, returns `None` at the end of the archive.
+fn read_item<T: Decode>(reader: &mut impl Read) -> Result<Option<T>> {
+	let mut length = [0u8; 4];
+	match reader.read_exact(&mut length) {
+		Ok(()) => (),
+		Err(error) if error.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+		Err(error) => return Err(error.into()),
+	};
+	let length = u32::from_le_bytes(length);
+	if length > MAX_ITEM_SIZE {
+		return Err(eyre!("Archive item size {length} exceeds the maximum size"));
+	}
+	let mut bytes = vec![0u8; length as usize];
+	reader
+		.read_exact(&mut bytes)
+		.wrap_err("Archive is truncated")?;
+	let item = T::decode(&mut &bytes[..]).wrap_err("Failed to decode archive item")?;
+	Ok(Some(item))
+}
+
+/// Exports verified history of the given blocks into the archive.
+/// All headers within the range have to be stored, so the archived header chain is contiguous.
+pub fn export(
+	db: &impl Database,
+	genesis_hash: &str,
+	app_id: Option<u32>,
+	blocks: RangeInclusive<u32>,
+	writer: &mut impl Write,
+) -> Result<Manifest> {
+	if blocks.is_empty() {
+		return Err(eyre!("Block range to export is empty"));
+	}
+
+	let manifest = Manifest {
+		version: ARCHIVE_VERSION,
+		genesis_hash: genesis_hash.to_string(),
+		first_block: *blocks.start(),
+		last_block: *blocks.end(),
+		app_id,
+	};
+	write_item(writer, &manifest)?;
+
+	for block_number in blocks {
+		let header = db
+			.get::<Header>(Key::BlockHeader(block_number))?
+			.ok_or_else(|| eyre!("Header of the block {block_number} is not stored"))?;
+		write_item(writer, &Record::BlockHeader(header))?;
+
+		if let Some(count) = db.get::<u32>(Key::VerifiedCellCount(block_number))? {
+			write_item(writer, &Record::VerifiedCellCount(block_number, count))?;
+		}
+
+		let Some(app_id) = app_id else {
+			continue;
+		};
+		if let Some(data) = db.get::<Vec<Vec<u8>>>(Key::AppData(app_id, block_number))? {
+			write_item(writer, &Record::AppData(block_number, data))?;
+		}
+	}
+
+	writer.flush()?;
+	Ok(manifest)
+}
+
+fn trusted_header(db: &impl Database, block_number: Option<u32>) -> Result<Option<Header>> {
+	let Some(block_number) = block_number else {
+		return Ok(None);
+	};
+	header_chain::get_trusted(db, block_number)
+}
+
+/// Validates that the records belong to the archived blocks, and that the headers are linked by parent hash
+/// to each other, to the trusted stored parent of the first archived block,
+/// and to the trusted stored child of the last archived block.
+fn validate(db: &impl Database, manifest: &Manifest, reader: &mut impl Read) -> Result<()> {
+	let blocks = manifest.first_block..=manifest.last_block;
+	let parent = trusted_header(db, manifest.first_block.checked_sub(1))?.ok_or_else(|| {
+		eyre!(
+			"Trusted parent of the block {} is not stored",
+			manifest.first_block
+		)
+	})?;
+	let child = trusted_header(db, manifest.last_block.checked_add(1))?.ok_or_else(|| {
+		eyre!(
+			"Trusted child of the block {} is not stored",
+			manifest.last_block
+		)
+	})?;
+	let mut parent_hash = header_hash(&parent);
+	let mut next_block = manifest.first_block;
+
+	while let Some(record) = read_item::<Record>(reader)? {
+		match record {
+			Record::BlockHeader(header) => {
+				if header.number != next_block {
+					return Err(eyre!(
+						"Expected header of the block {next_block}, found block {}",
+						header.number
+					));
+				}
+				if header.parent_hash != parent_hash {
+					return Err(eyre!(
+						"Header of the block {} is not linked to its parent",
+						header.number
+					));
+				}
+				parent_hash = header_hash(&header);
+				next_block += 1;
+			},
+			Record::VerifiedCellCount(block_number, _) | Record::AppData(block_number, _) => {
+				if !blocks.contains(&block_number) || block_number >= next_block {
+					return Err(eyre!("Unexpected record of the block {block_number}"));
+				}
+			},
+		}
+	}
+
+	if next_block <= manifest.last_block {
+		return Err(eyre!("Header of the block {next_block} is missing"));
+	}
+	if child.parent_hash != parent_hash {
+		return Err(eyre!(
+			"Header of the block {} is not linked to its child",
+			manifest.last_block
+		));
+	}
+	Ok(())
+}
+
+/// Imports the archive into the database, after validating the manifest and the header chain.
+/// Genesis hash is not checked if the configured one is prefixed with `DEV`.
+/// Imported headers are marked as trusted, since they are linked to the trusted headers at both ends.
+pub fn import(
+	db: &impl Database,
+	genesis_hash: &str,
+	reader: &mut (impl Read + Seek),
+) -> Result<Manifest> {
+	let manifest = read_item::<Manifest>(reader)?.ok_or_else(|| eyre!("Archive is empty"))?;
+	if manifest.version != ARCHIVE_VERSION {
+		return Err(eyre!(
+			"Archive version {} is not supported, expected version {ARCHIVE_VERSION}",
+			manifest.version
+		));
+	}
+	if !genesis_hash.starts_with(DEV_FLAG_GENHASH)
+		&& !manifest.genesis_hash.eq_ignore_ascii_case(genesis_hash)
+	{
+		return Err(eyre!(
+			"Archive genesis hash {} doesn't match configured genesis hash {genesis_hash}",
+			manifest.genesis_hash
+		));
+	}
+
+	let records_start = reader.stream_position()?;
+	validate(db, &manifest, reader).wrap_err("Archive validation failed")?;
+	reader.seek(SeekFrom::Start(records_start))?;
+
+	let app_id = manifest.app_id.unwrap_or_default();
+	let (mut headers, mut counts, mut app_data) = (0, 0, 0);
+	while let Some(record) = read_item::<Record>(reader)? {
+		match record {
+			Record::BlockHeader(header) => {
+				header_chain::store_trusted(db, &header)?;
+				headers += 1;
+			},
+			Record::VerifiedCellCount(block_number, count) => {
+				db.put(Key::VerifiedCellCount(block_number), count)?;
+				counts += 1;
+			},
+			Record::AppData(block_number, data) => {
+				db.put(Key::AppData(app_id, block_number), data)?;
+				app_data += 1;
+			},
+		}
+	}
+
+	info!(headers, counts, app_data, "Archive imported");
+	Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{
+		export, header_hash, import, read_item, write_item, Manifest, Record, ARCHIVE_VERSION,
+		MAX_ITEM_SIZE,
+	};
+	use crate::{
+		data::{mem_db::MemoryDB, Database, Key},
+		header_chain::{get_trusted, store_trusted},
+		test_utils::header,
+	};
+	use avail_subxt::primitives::Header;
+	use sp_core::H256;
+	use std::io::Cursor;
+
+	const GENESIS_HASH: &str = "0x0000000000000000000000000000000000000000000000000000000000000001";
+
+	fn chain(blocks: u32) -> Vec<Header> {
+		let mut headers: Vec<Header> = vec![];
+		for number in 0..blocks {
+			let parent_hash = headers.last().map(header_hash).unwrap_or_default();
+			headers.push(header(number, parent_hash));
+		}
+		headers
+	}
+
+	fn store_chain(db: &MemoryDB, headers: &[Header]) {
+		for header in headers {
+			let number = header.number;
+			store_trusted(db, header).unwrap();
+			db.put(Key::VerifiedCellCount(number), number).unwrap();
+			db.put(Key::AppData(1, number), vec![vec![number as u8]])
+				.unwrap();
+		}
+	}
+
+	#[test]
+	fn exported_archive_is_imported() {
+		let headers = chain(5);
+		let source = MemoryDB::default();
+		store_chain(&source, &headers);
+
+		let mut archive = Cursor::new(vec![]);
+		export(&source, GENESIS_HASH, Some(1), 1..=3, &mut archive).unwrap();
+
+		let target = MemoryDB::default();
+		store_trusted(&target, &headers[0]).unwrap();
+		store_trusted(&target, &headers[4]).unwrap();
+		archive.set_position(0);
+		let manifest = import(&target, GENESIS_HASH, &mut archive).unwrap();
+		assert_eq!((manifest.first_block, manifest.last_block), (1, 3));
+
+		assert_eq!(get_trusted(&target, 3).unwrap(), Some(headers[3].clone()));
+		assert_eq!(
+			target.get::<u32>(Key::VerifiedCellCount(2)).unwrap(),
+			Some(2)
+		);
+		assert_eq!(
+			target.get::<Vec<Vec<u8>>>(Key::AppData(1, 3)).unwrap(),
+			Some(vec![vec![3]])
+		);
+
+		archive.set_position(0);
+		let other_genesis_hash = GENESIS_HASH.replace('1', "2");
+		assert!(import(&target, &other_genesis_hash, &mut archive).is_err());
+	}
+
+	#[test]
+	fn archive_without_trusted_ends_is_rejected() {
+		let headers = chain(5);
+		let source = MemoryDB::default();
+		store_chain(&source, &headers);
+
+		let mut archive = Cursor::new(vec![]);
+		export(&source, GENESIS_HASH, None, 1..=3, &mut archive).unwrap();
+
+		let import_into = |target: &MemoryDB, archive: &mut Cursor<Vec<u8>>| {
+			archive.set_position(0);
+			import(target, GENESIS_HASH, archive)
+		};
+
+		// Stored ends are not marked as trusted
+		let target = MemoryDB::default();
+		target.put(Key::BlockHeader(0), headers[0].clone()).unwrap();
+		target.put(Key::BlockHeader(4), headers[4].clone()).unwrap();
+		assert!(import_into(&target, &mut archive).is_err());
+
+		// Trusted child is missing
+		store_trusted(&target, &headers[0]).unwrap();
+		assert!(import_into(&target, &mut archive).is_err());
+
+		// Trusted child is not linked to the last archived header
+		store_trusted(&target, &header(4, H256::repeat_byte(1))).unwrap();
+		assert!(import_into(&target, &mut archive).is_err());
+		assert!(target.get::<Header>(Key::BlockHeader(2)).unwrap().is_none());
+
+		store_trusted(&target, &headers[4]).unwrap();
+		assert!(import_into(&target, &mut archive).is_ok());
+		assert_eq!(get_trusted(&target, 2).unwrap(), Some(headers[2].clone()));
+	}
+
+	#[test]
+	fn unlinked_headers_are_rejected() {
+		let manifest = Manifest {
+			version: ARCHIVE_VERSION,
+			genesis_hash: GENESIS_HASH.to_string(),
+			first_block: 1,
+			last_block: 2,
+			app_id: None,
+		};
+		let headers = chain(4);
+		let mut archive = Cursor::new(vec![]);
+		write_item(&mut archive, &manifest).unwrap();
+		write_item(&mut archive, &Record::BlockHeader(headers[1].clone())).unwrap();
+		write_item(&mut archive, &Record::VerifiedCellCount(1, 10)).unwrap();
+		write_item(
+			&mut archive,
+			&Record::BlockHeader(header(2, H256::repeat_byte(1))),
+		)
+		.unwrap();
+
+		let db = MemoryDB::default();
+		store_trusted(&db, &headers[0]).unwrap();
+		store_trusted(&db, &headers[3]).unwrap();
+		archive.set_position(0);
+		assert!(import(&db, GENESIS_HASH, &mut archive).is_err());
+		// Nothing is written if validation fails
+		assert!(db.get::<Header>(Key::BlockHeader(1)).unwrap().is_none());
+		assert!(db.get::<u32>(Key::VerifiedCellCount(1)).unwrap().is_none());
+	}
+
+	#[test]
+	fn oversized_item_is_rejected() {
+		let mut archive = Cursor::new((MAX_ITEM_SIZE + 1).to_le_bytes().to_vec());
+		let error = read_item::<Manifest>(&mut archive).unwrap_err();
+		assert!(error.to_string().contains("exceeds the maximum size"));
+	}
+}
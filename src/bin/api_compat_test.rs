@@ -1,7 +1,7 @@
 use avail_light::{
 	data::rocks_db::RocksDB,
 	network::rpc,
-	types::{ExponentialConfig, RetryConfig, State},
+	types::{ExponentialConfig, RetryConfig, RuntimeConfig, State},
 };
 use clap::Parser;
 use color_eyre::{eyre::Context, Result};
@@ -31,8 +31,19 @@ async fn main() -> Result<()> {
 		retries: 4,
 	});
 
-	let (rpc_client, _, subscriptions) =
-		rpc::init(db, state, &[command_args.url], "DEV", retry_cfg).await?;
+	let runtime_cfg = RuntimeConfig::default();
+	let health_cfg = (&runtime_cfg).into();
+
+	let (rpc_client, _, subscriptions) = rpc::init(
+		db,
+		state,
+		&[command_args.url],
+		"DEV",
+		retry_cfg,
+		health_cfg,
+		runtime_cfg.supported_spec_versions,
+	)
+	.await?;
 	tokio::spawn(subscriptions.run());
 
 	let mut correct: bool = true;
@@ -2,16 +2,20 @@
 
 use avail_core::AppId;
 use avail_light::{
-	api,
+	api, archive,
 	consts::EXPECTED_SYSTEM_VERSION,
-	data::rocks_db::RocksDB,
+	data::{mem_db::MemoryDB, rocks_db::RocksDB, Database},
+	invalid_proof::InvalidProof,
 	maintenance::StaticConfigParams,
 	network::{self, p2p, rpc},
 	shutdown::Controller,
 	sync_client::SyncClient,
 	sync_finality::SyncFinality,
-	telemetry::{self, otlp::MetricAttributes},
-	types::{CliOpts, IdentityConfig, LibP2PConfig, RuntimeConfig, State},
+	telemetry::{self, MetricAttributes, Metrics},
+	types::{
+		CliOpts, Command, IdentityConfig, LibP2PConfig, MetricsExporter, RuntimeConfig, State,
+		StorageBackend,
+	},
 };
 use clap::Parser;
 use color_eyre::{
@@ -21,8 +25,9 @@ use color_eyre::{
 use kate_recovery::com::AppData;
 use libp2p::{multiaddr::Protocol, Multiaddr};
 use std::{
-	fs,
-	net::Ipv4Addr,
+	fs::{self, File},
+	io::{BufReader, BufWriter},
+	net::{Ipv4Addr, SocketAddr},
 	path::Path,
 	sync::{Arc, Mutex},
 };
@@ -70,9 +75,44 @@ fn parse_log_level(log_level: &str, default: Level) -> (Level, Option<ParseLevel
 		.unwrap_or_else(|parse_err| (default, Some(parse_err)))
 }
 
-async fn run(shutdown: Controller<String>) -> Result<()> {
-	let opts = CliOpts::parse();
+/// Runs the archive command against the local database, without starting the light client.
+fn run_command(opts: &CliOpts, command: &Command) -> Result<()> {
+	let mut cfg: RuntimeConfig = RuntimeConfig::default();
+	cfg.load_runtime_config(opts)?;
+
+	let (log_level, _) = parse_log_level(&cfg.log_level, Level::INFO);
+	tracing::subscriber::set_global_default(default_subscriber(log_level))
+		.expect("global default subscriber is set");
+
+	let db = RocksDB::open(&cfg.avail_path).wrap_err("Failed to open database")?;
+
+	match command {
+		Command::Export { from, to, output } => {
+			let file = File::create(output).wrap_err("Failed to create archive file")?;
+			let manifest = archive::export(
+				&db,
+				&cfg.genesis_hash,
+				cfg.app_id,
+				*from..=*to,
+				&mut BufWriter::new(file),
+			)
+			.wrap_err("Failed to export archive")?;
+			info!(
+				first_block = manifest.first_block,
+				last_block = manifest.last_block,
+				"Archive exported to {output}"
+			);
+		},
+		Command::Import { input } => {
+			let file = File::open(input).wrap_err("Failed to open archive file")?;
+			archive::import(&db, &cfg.genesis_hash, &mut BufReader::new(file))
+				.wrap_err("Failed to import archive")?;
+		},
+	}
+	Ok(())
+}
 
+async fn run(opts: CliOpts, shutdown: Controller<String>) -> Result<()> {
 	let mut cfg: RuntimeConfig = RuntimeConfig::default();
 	cfg.load_runtime_config(&opts)?;
 
@@ -115,15 +155,34 @@ async fn run(shutdown: Controller<String>) -> Result<()> {
 		Err(eyre!("Bootstrap node list must not be empty. Either use a '--network' flag or add a list of bootstrap nodes in the configuration file"))?
 	}
 
-	let db =
-		RocksDB::open(&cfg.avail_path).wrap_err("Avail Light could not initialize database")?;
+	match cfg.storage_backend {
+		StorageBackend::RocksDB => {
+			let db = RocksDB::open(&cfg.avail_path)
+				.wrap_err("Avail Light could not initialize database")?;
+			run_with_db(db, cfg, identity_cfg, client_role, shutdown).await
+		},
+		StorageBackend::Memory => {
+			warn!("Using ephemeral in-memory storage, state is lost on restart");
+			let db = MemoryDB::default();
+			run_with_db(db, cfg, identity_cfg, client_role, shutdown).await
+		},
+	}
+}
 
+async fn run_with_db(
+	db: impl Database + Clone + Send + Sync + 'static,
+	cfg: RuntimeConfig,
+	identity_cfg: IdentityConfig,
+	client_role: &str,
+	shutdown: Controller<String>,
+) -> Result<()> {
 	let cfg_libp2p: LibP2PConfig = (&cfg).into();
 	let (id_keys, peer_id) = p2p::keypair(&cfg_libp2p)?;
 
-	let metric_attributes = MetricAttributes {
+	// Each metrics backend keeps its own copy of the attributes
+	let metric_attributes = || MetricAttributes {
 		role: client_role.into(),
-		peer_id,
+		peer_id: peer_id.clone(),
 		ip: RwLock::new("".to_string()),
 		multiaddress: RwLock::new("".to_string()), // Default value is empty until first processed block triggers an update,
 		origin: cfg.origin.clone(),
@@ -145,10 +204,42 @@ async fn run(shutdown: Controller<String>) -> Result<()> {
 			.unwrap_or("n/a".to_string()),
 	};
 
-	let ot_metrics = Arc::new(
-		telemetry::otlp::initialize(cfg.ot_collector_endpoint.clone(), metric_attributes)
-			.wrap_err("Unable to initialize OpenTelemetry service")?,
-	);
+	let mut metrics_backends: Vec<Box<dyn Metrics + Send + Sync>> = vec![];
+	for exporter in &cfg.metrics_exporters {
+		match exporter {
+			MetricsExporter::Otlp => {
+				let endpoint = cfg.ot_collector_endpoint.clone();
+				match telemetry::otlp::initialize(endpoint, metric_attributes()) {
+					Ok(metrics) => metrics_backends.push(Box::new(metrics)),
+					// Light client doesn't depend on the collector, so it keeps running without it
+					Err(error) => error!("Unable to initialize OpenTelemetry service: {error:#}"),
+				}
+			},
+			MetricsExporter::Prometheus => {
+				let metrics = telemetry::prometheus::initialize(metric_attributes());
+				let addr = format!("{}:{}", cfg.http_server_host, cfg.prometheus_port)
+					.parse::<SocketAddr>()
+					.wrap_err("Invalid Prometheus exporter address")?;
+				// Light client doesn't depend on the metrics scraping, so it keeps running without the exporter
+				match metrics.bind(addr) {
+					Ok(server) => {
+						tokio::spawn(shutdown.with_cancel(server));
+						metrics_backends.push(Box::new(metrics));
+					},
+					Err(error) => error!("Unable to start Prometheus metrics exporter: {error:#}"),
+				}
+			},
+		}
+	}
+
+	let ot_metrics: Arc<Box<dyn Metrics + Send + Sync>> = if metrics_backends.is_empty() {
+		info!("Metrics export is disabled");
+		Arc::new(Box::new(telemetry::noop::Metrics))
+	} else {
+		Arc::new(Box::new(telemetry::composite::Metrics::new(
+			metrics_backends,
+		)))
+	};
 
 	// Create sender channel for P2P event loop commands
 	let (p2p_event_loop_sender, p2p_event_loop_receiver) = mpsc::unbounded_channel();
@@ -182,6 +273,10 @@ async fn run(shutdown: Controller<String>) -> Result<()> {
 		.wrap_err("Listening on TCP not to fail.")?;
 	info!("TCP listener started on port {}", cfg.port);
 
+	p2p::blocklist::restore(&p2p_client, &db, &cfg.blocked_peers)
+		.await
+		.wrap_err("Unable to restore blocked peers")?;
+
 	let p2p_clone = p2p_client.to_owned();
 	let cfg_clone = cfg.to_owned();
 	tokio::spawn(shutdown.with_cancel(async move {
@@ -209,12 +304,16 @@ async fn run(shutdown: Controller<String>) -> Result<()> {
 	trace!("Public params ({public_params_len}): hash: {public_params_hash}");
 
 	let state = Arc::new(Mutex::new(State::default()));
+	avail_light::state::restore(&db, &state).wrap_err("Avail Light could not restore state")?;
+
 	let (rpc_client, rpc_events, rpc_subscriptions) = rpc::init(
 		db.clone(),
 		state.clone(),
 		&cfg.full_node_ws,
 		&cfg.genesis_hash,
 		cfg.retry_config.clone(),
+		(&cfg).into(),
+		cfg.supported_spec_versions.clone(),
 	)
 	.await?;
 
@@ -238,6 +337,9 @@ async fn run(shutdown: Controller<String>) -> Result<()> {
 		},
 	)));
 
+	// spawn the RPC nodes health monitor, which switches away from the unhealthy connected node
+	tokio::spawn(shutdown.with_cancel(rpc_client.clone().monitor_health(ot_metrics.clone())));
+
 	info!("Waiting for first finalized header...");
 	let block_header = match shutdown
 		.with_cancel(rpc::wait_for_finalized_header(
@@ -281,6 +383,8 @@ async fn run(shutdown: Controller<String>) -> Result<()> {
 		version: format!("v{}", clap::crate_version!()),
 		network_version: EXPECTED_SYSTEM_VERSION[0].to_string(),
 		node_client: rpc_client.clone(),
+		p2p_client: p2p_client.clone(),
+		pp: pp.clone(),
 		ws_clients: ws_clients.clone(),
 		shutdown: shutdown.clone(),
 	};
@@ -288,6 +392,14 @@ async fn run(shutdown: Controller<String>) -> Result<()> {
 
 	let (block_tx, block_rx) = broadcast::channel::<avail_light::types::BlockVerified>(1 << 7);
 
+	let (invalid_proof_tx, invalid_proof_rx) = broadcast::channel::<InvalidProof>(1 << 7);
+
+	tokio::task::spawn(shutdown.with_cancel(avail_light::invalid_proof::run(
+		db.clone(),
+		invalid_proof_rx,
+		ot_metrics.clone(),
+	)));
+
 	let data_rx = cfg.app_id.map(AppId).map(|app_id| {
 		let (data_tx, data_rx) = broadcast::channel::<(u32, AppData)>(1 << 7);
 		tokio::task::spawn(shutdown.with_cancel(avail_light::app_client::run(
@@ -318,6 +430,12 @@ async fn run(shutdown: Controller<String>) -> Result<()> {
 		ws_clients.clone(),
 	)));
 
+	tokio::task::spawn(shutdown.with_cancel(api::v2::publish(
+		api::v2::types::Topic::InvalidProof,
+		invalid_proof_tx.subscribe(),
+		ws_clients.clone(),
+	)));
+
 	if let Some(data_rx) = data_rx {
 		tokio::task::spawn(shutdown.with_cancel(api::v2::publish(
 			api::v2::types::Topic::DataVerified,
@@ -332,6 +450,7 @@ async fn run(shutdown: Controller<String>) -> Result<()> {
 		tokio::task::spawn(shutdown.with_cancel(avail_light::crawl_client::run(
 			crawler_rpc_event_receiver,
 			p2p_client.clone(),
+			rpc_client.clone(),
 			cfg.crawl.crawl_block_delay,
 			ot_metrics.clone(),
 			cfg.crawl.crawl_block_mode,
@@ -339,13 +458,16 @@ async fn run(shutdown: Controller<String>) -> Result<()> {
 		)));
 	}
 
-	let sync_client = SyncClient::new(db.clone(), rpc_client.clone());
+	let sync_client = SyncClient::new(db.clone(), rpc_client.clone(), block_header.clone());
 
 	let sync_network_client = network::new(
 		p2p_client.clone(),
 		rpc_client.clone(),
 		pp.clone(),
 		cfg.disable_rpc,
+		invalid_proof_tx.clone(),
+		db.clone(),
+		cfg.cell_cache_blocks,
 	);
 
 	if cfg.sync_start_block.is_some() {
@@ -391,6 +513,44 @@ async fn run(shutdown: Controller<String>) -> Result<()> {
 		shutdown.clone(),
 	)));
 
+	let retention_config: avail_light::types::RetentionConfig = (&cfg).into();
+	if retention_config.is_enabled() {
+		tokio::task::spawn(shutdown.with_cancel(avail_light::retention::run(
+			db.clone(),
+			state.clone(),
+			retention_config,
+			block_tx.subscribe(),
+			shutdown.clone(),
+		)));
+	}
+
+	tokio::task::spawn(shutdown.with_cancel(avail_light::state::run(
+		db.clone(),
+		state.clone(),
+		block_tx.subscribe(),
+		shutdown.clone(),
+	)));
+
+	let retry_network_client = network::new(
+		p2p_client.clone(),
+		rpc_client.clone(),
+		pp.clone(),
+		cfg.disable_rpc,
+		invalid_proof_tx.clone(),
+		db.clone(),
+		cfg.cell_cache_blocks,
+	);
+
+	tokio::task::spawn(shutdown.with_cancel(avail_light::retry_queue::run(
+		db.clone(),
+		retry_network_client,
+		rpc_client.clone(),
+		(&cfg).into(),
+		ot_metrics.clone(),
+		state.clone(),
+		block_tx.clone(),
+	)));
+
 	let channels = avail_light::types::ClientChannels {
 		block_sender: block_tx,
 		rpc_event_receiver: client_rpc_event_receiver,
@@ -401,6 +561,7 @@ async fn run(shutdown: Controller<String>) -> Result<()> {
 
 		tokio::task::spawn(shutdown.with_cancel(avail_light::fat_client::run(
 			fat_client,
+			rpc_client.clone(),
 			db.clone(),
 			(&cfg).into(),
 			ot_metrics.clone(),
@@ -409,11 +570,20 @@ async fn run(shutdown: Controller<String>) -> Result<()> {
 			shutdown.clone(),
 		)));
 	} else {
-		let light_network_client = network::new(p2p_client, rpc_client, pp, cfg.disable_rpc);
+		let light_network_client = network::new(
+			p2p_client,
+			rpc_client.clone(),
+			pp,
+			cfg.disable_rpc,
+			invalid_proof_tx,
+			db.clone(),
+			cfg.cell_cache_blocks,
+		);
 
 		tokio::task::spawn(shutdown.with_cancel(avail_light::light_client::run(
 			db.clone(),
 			light_network_client,
+			rpc_client,
 			(&cfg).into(),
 			ot_metrics,
 			state.clone(),
@@ -511,6 +681,11 @@ async fn user_signal() {
 
 #[tokio::main]
 pub async fn main() -> Result<()> {
+	let opts = CliOpts::parse();
+	if let Some(command) = &opts.command {
+		return run_command(&opts, command);
+	}
+
 	let shutdown = Controller::new();
 
 	// install custom panic hooks
@@ -519,7 +694,7 @@ pub async fn main() -> Result<()> {
 	// spawn a task to watch for ctrl-c signals from user to trigger the shutdown
 	tokio::spawn(shutdown.with_trigger("user signaled shutdown".to_string(), user_signal()));
 
-	if let Err(error) = run(shutdown.clone()).await {
+	if let Err(error) = run(opts, shutdown.clone()).await {
 		error!("{error:#}");
 		return Err(error.wrap_err("Starting Light Client failed"));
 	};
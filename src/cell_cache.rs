@@ -0,0 +1,135 @@
+//! Cache of the verified cells.
+//!
+//! Cells which passed proof verification are stored in the database, keyed by block number and position,
+//! so they are not fetched again from the DHT by the sampling, retries or the HTTP API.
+//! Only the cells of the latest blocks are kept, cells of the older blocks are evicted on insert,
+//! once the oldest cached block advances.
+
+use color_eyre::{
+	eyre::{eyre, WrapErr},
+	Result,
+};
+use kate_recovery::{data::Cell, matrix::Position};
+
+use crate::data::{Database, Key, KeyRange};
+
+/// Gets cached cells on the given positions.
+/// Returns cached cells and positions of the cells which are not cached.
+pub fn get(
+	db: &impl Database,
+	block_number: u32,
+	positions: &[Position],
+) -> Result<(Vec<Cell>, Vec<Position>)> {
+	let mut cached = vec![];
+	let mut uncached = vec![];
+	for &position in positions {
+		let key = Key::Cell(block_number, position.row, position.col);
+		let Some(content) = db.get::<Vec<u8>>(key)? else {
+			uncached.push(position);
+			continue;
+		};
+		let content = content
+			.try_into()
+			.map_err(|_| eyre!("Invalid size of the cached cell {position:?}"))?;
+		cached.push(Cell { position, content });
+	}
+	Ok((cached, uncached))
+}
+
+/// Stores verified cells of the block, and evicts cells of the blocks
+/// which are not within the last `cache_blocks` blocks, counting from the given block.
+/// Nothing is stored if `cache_blocks` is zero.
+pub fn put(db: &impl Database, block_number: u32, cells: &[Cell], cache_blocks: u32) -> Result<()> {
+	let cutoff = block_number.saturating_add(1).saturating_sub(cache_blocks);
+	let evicted = db
+		.get::<u32>(Key::CellCacheCutoff)
+		.wrap_err("Failed to get cell cache cutoff")?
+		.unwrap_or(0);
+	// Cells of the evicted blocks are not stored, since they wouldn't be evicted again
+	if block_number < cutoff.max(evicted) {
+		return Ok(());
+	}
+
+	for cell in cells {
+		let key = Key::Cell(block_number, cell.position.row, cell.position.col);
+		db.put(key, cell.content.to_vec())
+			.wrap_err("Failed to store cached cell")?;
+	}
+
+	if cutoff <= evicted {
+		return Ok(());
+	}
+	db.delete_range(KeyRange::Cells(evicted..cutoff))
+		.wrap_err("Failed to evict cached cells")?;
+	db.put(Key::CellCacheCutoff, cutoff)
+		.wrap_err("Failed to store cell cache cutoff")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{get, put};
+	use crate::data::{mem_db::MemoryDB, Database, Key};
+	use kate_recovery::{data::Cell, matrix::Position};
+
+	fn cell(row: u32, col: u16) -> Cell {
+		Cell {
+			position: Position { row, col },
+			content: [row as u8; 80],
+		}
+	}
+
+	fn positions(cells: &[Cell]) -> Vec<(u32, u16)> {
+		cells
+			.iter()
+			.map(|cell| (cell.position.row, cell.position.col))
+			.collect()
+	}
+
+	#[test]
+	fn cached_cells_of_old_blocks_are_evicted() {
+		let db = MemoryDB::default();
+		let requested = [Position { row: 0, col: 1 }, Position { row: 1, col: 2 }];
+
+		put(&db, 1, &[cell(0, 1)], 2).unwrap();
+		put(&db, 2, &[cell(0, 1), cell(1, 2)], 2).unwrap();
+
+		let (cached, uncached) = get(&db, 1, &requested).unwrap();
+		assert_eq!(positions(&cached), vec![(0, 1)]);
+		assert_eq!(cached[0].content, [0; 80]);
+		assert_eq!(uncached, vec![Position { row: 1, col: 2 }]);
+
+		put(&db, 3, &[cell(1, 2)], 2).unwrap();
+
+		let (cached, uncached) = get(&db, 1, &requested).unwrap();
+		assert!(cached.is_empty());
+		assert_eq!(uncached, requested.to_vec());
+
+		let (cached, uncached) = get(&db, 2, &requested).unwrap();
+		assert_eq!(positions(&cached), vec![(0, 1), (1, 2)]);
+		assert_eq!(cached[1].content, [1; 80]);
+		assert!(uncached.is_empty());
+	}
+
+	#[test]
+	fn eviction_cutoff_only_advances() {
+		let db = MemoryDB::default();
+		put(&db, 10, &[cell(0, 1)], 2).unwrap();
+		assert_eq!(db.get::<u32>(Key::CellCacheCutoff).unwrap(), Some(9));
+
+		// Older block doesn't move the cutoff back, and its cells are kept until the cutoff advances
+		put(&db, 9, &[cell(0, 1)], 2).unwrap();
+		assert_eq!(db.get::<u32>(Key::CellCacheCutoff).unwrap(), Some(9));
+		let (cached, _) = get(&db, 9, &[cell(0, 1).position]).unwrap();
+		assert_eq!(cached.len(), 1);
+
+		put(&db, 11, &[cell(0, 1)], 2).unwrap();
+		assert_eq!(db.get::<u32>(Key::CellCacheCutoff).unwrap(), Some(10));
+		let (cached, _) = get(&db, 9, &[cell(0, 1).position]).unwrap();
+		assert!(cached.is_empty());
+
+		// Cells of the evicted block are not stored
+		put(&db, 9, &[cell(0, 1)], 2).unwrap();
+		let (cached, _) = get(&db, 9, &[cell(0, 1).position]).unwrap();
+		assert!(cached.is_empty());
+	}
+}
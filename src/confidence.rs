@@ -0,0 +1,224 @@
+//! Block confidence model.
+//!
+//! Confidence is calculated from the sampling result (number of requested cells, verified cells,
+//! and cells which failed proof verification), using the matrix dimensions, erasure coding extension,
+//! and the formula of the sampling strategy which selected the cells.
+//!
+//! # Model
+//!
+//! Rows of the matrix are erasure coded with the extension factor of 2, so the block can be reconstructed
+//! unless at least half of the extended matrix is withheld. Each verified cell misses the withheld data
+//! with the probability of `1 / EXTENSION_FACTOR`, and the block is available if all cells of the extended matrix
+//! are verified. Confidence is the lower bound of the probability that the block is available, given the verified cells.
+//! Cells which are not fetched are not taken into account.
+//! Cell with invalid proof means that the served data doesn't match the commitments, which is a strong
+//! signal of withholding, so it is reported with the invalid proof flag, and each such cell cancels one verified cell.
+//! Confidence is not zeroed for such block, since the invalid cells may be served by a faulty peer,
+//! so the block can still achieve the confidence with the additional samples.
+
+use codec::{Decode, Encode, Input, Output};
+use kate_recovery::matrix::Dimensions;
+use serde::{Deserialize, Serialize};
+
+use crate::sampling::SamplingStrategy;
+
+/// Erasure coding extension factor of the matrix rows
+pub const EXTENSION_FACTOR: u32 = 2;
+
+/// Number of cells requested and verified in a sampling round
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct SamplingResult {
+	/// Number of cells selected for sampling
+	pub requested: u32,
+	/// Number of fetched cells with valid proofs
+	pub verified: u32,
+	/// Number of fetched cells which failed proof verification
+	pub invalid_proofs: u32,
+}
+
+impl SamplingResult {
+	/// Number of cells which are neither verified nor fetched with invalid proof
+	pub fn unfetched(&self) -> u32 {
+		self.requested
+			.saturating_sub(self.verified)
+			.saturating_sub(self.invalid_proofs)
+	}
+
+	/// Number of verified cells which are counted towards the confidence, since each invalid proof cancels one
+	pub fn counted(&self) -> u32 {
+		self.verified.saturating_sub(self.invalid_proofs)
+	}
+}
+
+/// Assumptions under which the confidence is calculated
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct Assumptions {
+	/// Sampling strategy used to select the cells
+	pub strategy: String,
+	/// Erasure coding extension factor of the matrix rows
+	pub extension_factor: u32,
+	/// Number of rows of the extended matrix
+	pub extended_rows: u32,
+	/// Number of columns of the matrix
+	pub cols: u16,
+}
+
+/// Confidence that the block is available
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Confidence {
+	/// Confidence in percents
+	pub value: f64,
+	/// Upper bound of the probability that the block is unavailable, given the verified cells
+	pub probability_bound: f64,
+	#[serde(flatten)]
+	pub result: SamplingResult,
+	/// True if any of the fetched cells failed proof verification
+	pub invalid_proof: bool,
+	pub assumptions: Assumptions,
+}
+
+impl Confidence {
+	/// Returns true if the confidence is at least the required confidence
+	pub fn is_achieved(&self, required: f64) -> bool {
+		self.value >= required
+	}
+}
+
+// Floating point numbers are not supported by SCALE codec, so they are encoded as bits
+impl Encode for Confidence {
+	fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+		self.value.to_bits().encode_to(dest);
+		self.probability_bound.to_bits().encode_to(dest);
+		self.result.encode_to(dest);
+		self.invalid_proof.encode_to(dest);
+		self.assumptions.encode_to(dest);
+	}
+}
+
+impl Decode for Confidence {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+		Ok(Confidence {
+			value: f64::from_bits(u64::decode(input)?),
+			probability_bound: f64::from_bits(u64::decode(input)?),
+			result: SamplingResult::decode(input)?,
+			invalid_proof: bool::decode(input)?,
+			assumptions: Assumptions::decode(input)?,
+		})
+	}
+}
+
+/// Returns confidence required to consider the block available, which is the confidence
+/// of the cells the strategy requires for the configured confidence.
+/// It differs from the configured confidence if the number of the required cells is capped.
+pub fn required(strategy: &dyn SamplingStrategy, dimensions: Dimensions, confidence: f64) -> f64 {
+	strategy.confidence(dimensions, strategy.cell_count(dimensions, confidence))
+}
+
+/// Calculates confidence from the sampling result
+pub fn calculate(
+	strategy: &dyn SamplingStrategy,
+	dimensions: Dimensions,
+	result: SamplingResult,
+) -> Confidence {
+	let value = strategy.confidence(dimensions, result.counted());
+
+	Confidence {
+		value,
+		probability_bound: 1f64 - value / 100f64,
+		result,
+		invalid_proof: result.invalid_proofs > 0,
+		assumptions: Assumptions {
+			strategy: strategy.name().to_string(),
+			extension_factor: EXTENSION_FACTOR,
+			extended_rows: dimensions.extended_rows(),
+			cols: dimensions.cols().get(),
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{calculate, required, Confidence, SamplingResult};
+	use crate::{sampling::Uniform, utils::calculate_confidence};
+	use codec::{Decode, Encode};
+	use kate_recovery::matrix::Dimensions;
+
+	fn result(requested: u32, verified: u32, invalid_proofs: u32) -> SamplingResult {
+		SamplingResult {
+			requested,
+			verified,
+			invalid_proofs,
+		}
+	}
+
+	#[test]
+	fn confidence_accounts_for_extended_matrix_size() {
+		let dimensions = Dimensions::new(16, 32).unwrap();
+		let confidence = calculate(&Uniform, dimensions, result(8, 8, 0));
+		assert!(!confidence.invalid_proof);
+		assert_eq!(confidence.value, calculate_confidence(8));
+		assert_eq!(confidence.probability_bound, 0.5f64.powi(8));
+		assert_eq!(confidence.assumptions.extension_factor, 2);
+		assert_eq!(confidence.assumptions.extended_rows, 32);
+		assert_eq!(confidence.assumptions.strategy, "uniform");
+
+		// All cells of the extended matrix of 2 rows and 4 columns are verified
+		let small = Dimensions::new(1, 4).unwrap();
+		let confidence = calculate(&Uniform, small, result(8, 8, 0));
+		assert_eq!(confidence.value, 100f64);
+		assert_eq!(confidence.probability_bound, 0f64);
+		assert_eq!(confidence.assumptions.extended_rows, 2);
+	}
+
+	#[test]
+	fn confidence_with_partial_failures() {
+		let dimensions = Dimensions::new(16, 32).unwrap();
+		let required = required(&Uniform, dimensions, 99.9);
+		let complete = calculate(&Uniform, dimensions, result(10, 10, 0));
+		assert!(complete.is_achieved(required));
+
+		let partial = calculate(&Uniform, dimensions, result(10, 8, 0));
+		assert!(!partial.is_achieved(required));
+		assert_eq!(partial.result.unfetched(), 2);
+		assert!(partial.value < complete.value);
+
+		// Invalid proof cancels one of the verified cells
+		let invalid = calculate(&Uniform, dimensions, result(12, 10, 1));
+		assert!(invalid.invalid_proof);
+		assert!(!invalid.is_achieved(required));
+		assert_eq!(invalid.result.unfetched(), 1);
+		assert!(invalid.value < complete.value);
+		assert_eq!(
+			invalid.value,
+			calculate(&Uniform, dimensions, result(9, 9, 0)).value
+		);
+
+		// Invalid proofs are replaced by the additional cells
+		let replaced = calculate(&Uniform, dimensions, result(13, 11, 1));
+		assert!(replaced.is_achieved(required));
+	}
+
+	#[test]
+	fn required_confidence_is_capped_with_cell_count() {
+		let dimensions = Dimensions::new(16, 32).unwrap();
+		assert_eq!(
+			required(&Uniform, dimensions, 99.9),
+			calculate_confidence(10)
+		);
+		// Number of cells is capped for the confidence above 99.99%
+		assert!(required(&Uniform, dimensions, 99.9999) < 99.9999);
+		// Extended matrix of 2 rows and 4 columns is sampled entirely
+		assert_eq!(
+			required(&Uniform, Dimensions::new(1, 4).unwrap(), 99.9),
+			100f64
+		);
+	}
+
+	#[test]
+	fn confidence_encoding() {
+		let dimensions = Dimensions::new(16, 32).unwrap();
+		let confidence = calculate(&Uniform, dimensions, result(10, 10, 0));
+		let decoded = Confidence::decode(&mut &confidence.encode()[..]).unwrap();
+		assert_eq!(decoded, confidence);
+	}
+}
@@ -1,20 +1,32 @@
 //! Column family names and other constants.
 
+use std::ops::RangeInclusive;
+
 /// Expected network Node versions. First version should be the main supported version,
 /// while all subsequent versions should be for backward compatibility/fallback/future-proofing versions.
 pub const EXPECTED_SYSTEM_VERSION: &[&str] = &["2.0"];
 pub const EXPECTED_SPEC_NAME: &str = "avail";
+/// Default runtime specification versions supported by the light client,
+/// which can be changed with the `supported_spec_versions` configuration.
+/// Light client runs in degraded mode if the runtime version is outside of this range.
+pub const EXPECTED_SPEC_VERSIONS: RangeInclusive<u32> = 12..=19;
+/// Supported versions of the block header extension, as named in the node RPC responses.
+pub const EXPECTED_HEADER_EXTENSION_VERSIONS: &[&str] = &["V3"];
 
 #[derive(Clone)]
 pub struct ExpectedNodeVariant {
 	pub system_version: &'static [&'static str],
 	pub spec_name: &'static str,
+	pub spec_versions: RangeInclusive<u32>,
+	pub header_extension_versions: &'static [&'static str],
 }
 impl ExpectedNodeVariant {
-	pub const fn new() -> Self {
+	pub const fn new(spec_versions: RangeInclusive<u32>) -> Self {
 		Self {
 			system_version: EXPECTED_SYSTEM_VERSION,
 			spec_name: EXPECTED_SPEC_NAME,
+			spec_versions,
+			header_extension_versions: EXPECTED_HEADER_EXTENSION_VERSIONS,
 		}
 	}
 
@@ -22,8 +34,7 @@ impl ExpectedNodeVariant {
 	/// Since the light client uses subset of the node APIs, `matches` checks only prefix of a node version.
 	/// This means that if expected version is `1.6`, versions `1.6.x` of the node will match.
 	/// Specification name is checked for exact match.
-	/// Since runtime `spec_version` can be changed with runtime upgrade, `spec_version` is removed.
-	/// NOTE: Runtime compatibility check is currently not implemented.
+	/// Since runtime `spec_version` can be changed with runtime upgrade, it is checked separately by [`Self::runtime_incompatibility`].
 	pub fn matches(&self, system_version: &str, spec_name: &str) -> bool {
 		for supported_network_version in self.system_version {
 			if system_version.starts_with(supported_network_version) && self.spec_name == spec_name
@@ -33,4 +44,46 @@ impl ExpectedNodeVariant {
 		}
 		false
 	}
+
+	/// Checks if the runtime specification version and the header extension version are supported.
+	/// Returns the reason if the runtime is not supported.
+	pub fn runtime_incompatibility(
+		&self,
+		spec_version: u32,
+		header_extension_version: &str,
+	) -> Option<String> {
+		if !self.spec_versions.contains(&spec_version) {
+			return Some(format!(
+				"Runtime spec version {spec_version} is not supported, supported versions are {:?}",
+				self.spec_versions
+			));
+		}
+		let is_supported = (self.header_extension_versions.iter())
+			.any(|version| version.eq_ignore_ascii_case(header_extension_version));
+		if !is_supported {
+			return Some(format!(
+				"Header extension version {header_extension_version} is not supported, supported versions are {:?}",
+				self.header_extension_versions
+			));
+		}
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ExpectedNodeVariant, EXPECTED_SPEC_VERSIONS};
+
+	#[test]
+	fn runtime_incompatibility() {
+		let expected = ExpectedNodeVariant::new(EXPECTED_SPEC_VERSIONS);
+		assert_eq!(expected.runtime_incompatibility(12, "V3"), None);
+		assert_eq!(expected.runtime_incompatibility(19, "v3"), None);
+		assert!(expected.runtime_incompatibility(12, "V4").is_some());
+		assert!(expected.runtime_incompatibility(11, "V3").is_some());
+		assert!(expected.runtime_incompatibility(20, "V3").is_some());
+
+		let expected = ExpectedNodeVariant::new(12..=20);
+		assert_eq!(expected.runtime_incompatibility(20, "V3"), None);
+	}
 }
@@ -54,8 +54,9 @@ impl Default for CrawlConfig {
 }
 
 pub async fn run(
-	mut message_rx: broadcast::Receiver<Event>,
+	message_rx: broadcast::Receiver<Event>,
 	network_client: Client,
+	rpc_client: rpc::Client,
 	delay: u64,
 	metrics: Arc<impl Metrics>,
 	mode: CrawlMode,
@@ -64,6 +65,7 @@ pub async fn run(
 	info!("Starting crawl client...");
 
 	let delay = Delay(Some(Duration::from_secs(delay)));
+	let mut message_rx = rpc::RecoveringReceiver::new(message_rx, rpc_client, metrics.clone());
 
 	while let Ok(rpc::Event::HeaderUpdate {
 		header,
@@ -2,11 +2,10 @@ use codec::{Decode, Encode};
 use color_eyre::eyre::Result;
 use serde::{Deserialize, Serialize};
 use sp_core::ed25519;
+use std::ops::Range;
 
-pub mod rocks_db;
-
-#[cfg(test)]
 pub mod mem_db;
+pub mod rocks_db;
 
 pub trait Database {
 	/// Type of the database key which we can get from the custom key.
@@ -26,11 +25,30 @@ pub trait Database {
 
 	/// Deletes value from the database for the given key.
 	fn delete(&self, key: Key) -> Result<()>;
+
+	/// Gets all key/value pairs within the given key range, ordered by block number.
+	/// Values are deserialized into the given type.
+	fn get_range<T>(&self, range: KeyRange) -> Result<Vec<(Key, T)>>
+	where
+		for<'a> T: Deserialize<'a> + Decode;
+
+	/// Deletes all values from the database within the given key range.
+	fn delete_range(&self, range: KeyRange) -> Result<()>;
+
+	/// Reclaims the space of the deleted values within the given key range,
+	/// so the deletion is reflected in the database size.
+	fn compact_range(&self, range: KeyRange) -> Result<()>;
+
+	/// Gets estimated size of the stored data, in bytes.
+	fn size(&self) -> Result<u64>;
 }
 
 /// Column family for confidence factor
 pub const CONFIDENCE_FACTOR_CF: &str = "avail_light_confidence_factor_cf";
 
+/// Column family for block confidence
+pub const BLOCK_CONFIDENCE_CF: &str = "avail_light_block_confidence_cf";
+
 /// Column family for block header
 pub const BLOCK_HEADER_CF: &str = "avail_light_block_header_cf";
 
@@ -40,15 +58,107 @@ pub const APP_DATA_CF: &str = "avail_light_app_data_cf";
 /// Column family for state
 pub const STATE_CF: &str = "avail_light_state_cf";
 
+/// Column family for blocks queued for sampling retry
+pub const RETRY_QUEUE_CF: &str = "avail_light_retry_queue_cf";
+
+/// Column family for cells which failed proof verification
+pub const INVALID_PROOF_CF: &str = "avail_light_invalid_proof_cf";
+
+/// Column family for verified cells cache
+pub const CELLS_CF: &str = "avail_light_cells_cf";
+
+/// Column family for hashes of the trusted block headers
+pub const TRUSTED_HEADER_CF: &str = "avail_light_trusted_header_cf";
+
 /// Sync finality checkpoint key name
 const FINALITY_SYNC_CHECKPOINT_KEY: &str = "finality_sync_checkpoint";
 
-#[derive(Clone)]
+/// Database schema version key name
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// State checkpoint key name
+const STATE_CHECKPOINT_KEY: &str = "state_checkpoint";
+
+/// Sync cursor key name
+const SYNC_CURSOR_KEY: &str = "sync_cursor";
+
+/// Cell cache cutoff key name
+const CELL_CACHE_CUTOFF_KEY: &str = "cell_cache_cutoff";
+
+/// Blocked peers key name
+const BLOCKED_PEERS_KEY: &str = "blocked_peers";
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Key {
 	AppData(u32, u32),
 	BlockHeader(u32),
+	/// Hash of the finality verified, or linked block header
+	TrustedHeader(u32),
 	VerifiedCellCount(u32),
+	BlockConfidence(u32),
+	RetryBlock(u32),
+	InvalidProofs(u32),
+	Cell(u32, u32, u16),
 	FinalitySyncCheckpoint,
+	SchemaVersion,
+	StateCheckpoint,
+	SyncCursor,
+	BlockedPeers,
+	CellCacheCutoff,
+}
+
+/// Range of keys of the same kind, bounded inclusively below and exclusively above by block number.
+#[derive(Clone, Debug)]
+pub enum KeyRange {
+	AppData(u32, Range<u32>),
+	BlockHeader(Range<u32>),
+	TrustedHeader(Range<u32>),
+	VerifiedCellCount(Range<u32>),
+	BlockConfidence(Range<u32>),
+	RetryBlock(Range<u32>),
+	InvalidProofs(Range<u32>),
+	/// Cells of the blocks within the range, bounded by the first cell of the block
+	Cells(Range<u32>),
+}
+
+impl KeyRange {
+	/// Returns range of block numbers
+	pub fn blocks(&self) -> &Range<u32> {
+		match self {
+			KeyRange::AppData(_, blocks) => blocks,
+			KeyRange::BlockHeader(blocks) => blocks,
+			KeyRange::TrustedHeader(blocks) => blocks,
+			KeyRange::VerifiedCellCount(blocks) => blocks,
+			KeyRange::BlockConfidence(blocks) => blocks,
+			KeyRange::RetryBlock(blocks) => blocks,
+			KeyRange::InvalidProofs(blocks) => blocks,
+			KeyRange::Cells(blocks) => blocks,
+		}
+	}
+
+	/// Returns key of the same kind for given block number
+	pub fn key(&self, block_number: u32) -> Key {
+		match self {
+			KeyRange::AppData(app_id, _) => Key::AppData(*app_id, block_number),
+			KeyRange::BlockHeader(_) => Key::BlockHeader(block_number),
+			KeyRange::TrustedHeader(_) => Key::TrustedHeader(block_number),
+			KeyRange::VerifiedCellCount(_) => Key::VerifiedCellCount(block_number),
+			KeyRange::BlockConfidence(_) => Key::BlockConfidence(block_number),
+			KeyRange::RetryBlock(_) => Key::RetryBlock(block_number),
+			KeyRange::InvalidProofs(_) => Key::InvalidProofs(block_number),
+			KeyRange::Cells(_) => Key::Cell(block_number, 0, 0),
+		}
+	}
+
+	/// Returns first key in the range (inclusive)
+	pub fn start(&self) -> Key {
+		self.key(self.blocks().start)
+	}
+
+	/// Returns last key of the range (exclusive)
+	pub fn end(&self) -> Key {
+		self.key(self.blocks().end)
+	}
 }
 
 #[derive(Serialize, Deserialize, Debug, Decode, Encode)]
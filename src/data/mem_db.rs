@@ -1,71 +1,224 @@
 use crate::data::{
-	Database, Key, APP_DATA_CF, BLOCK_HEADER_CF, CONFIDENCE_FACTOR_CF, FINALITY_SYNC_CHECKPOINT_KEY,
+	Database, Key, KeyRange, APP_DATA_CF, BLOCKED_PEERS_KEY, BLOCK_CONFIDENCE_CF, BLOCK_HEADER_CF,
+	CELLS_CF, CELL_CACHE_CUTOFF_KEY, CONFIDENCE_FACTOR_CF, FINALITY_SYNC_CHECKPOINT_KEY,
+	INVALID_PROOF_CF, RETRY_QUEUE_CF, SCHEMA_VERSION_KEY, STATE_CHECKPOINT_KEY, SYNC_CURSOR_KEY,
+	TRUSTED_HEADER_CF,
 };
-use color_eyre::eyre::{eyre, Result};
-use serde::{Deserialize, Serialize};
+use codec::{Decode, Encode};
+use color_eyre::eyre::{eyre, Result, WrapErr};
 use std::{
-	collections::HashMap,
+	collections::BTreeMap,
 	sync::{Arc, RwLock},
 };
 
-#[derive(Eq, Hash, PartialEq)]
+#[derive(Eq, Hash, PartialEq, Ord, PartialOrd)]
 pub struct HashMapKey(pub String);
 
-#[derive(Clone)]
-pub struct MemoryDB {
-	map: Arc<RwLock<HashMap<HashMapKey, String>>>,
+#[derive(Default)]
+struct Entries {
+	map: BTreeMap<HashMapKey, Vec<u8>>,
+	/// Total size of the stored keys and values, in bytes
+	size: u64,
 }
 
-impl Default for MemoryDB {
-	fn default() -> Self {
-		MemoryDB {
-			map: Arc::new(RwLock::new(HashMap::new())),
-		}
-	}
+fn entry_size(HashMapKey(key): &HashMapKey, value: &[u8]) -> u64 {
+	(key.len() + value.len()) as u64
+}
+
+/// In-memory database, with values encoded the same way as in the RocksDB.
+/// Used as an ephemeral storage, where the state is lost on restart.
+/// Size of the stored data is tracked, so it can be bounded by the retention.
+#[derive(Clone, Default)]
+pub struct MemoryDB {
+	entries: Arc<RwLock<Entries>>,
 }
 
 impl Database for MemoryDB {
 	type Key = HashMapKey;
+
 	fn put<T>(&self, key: Key, value: T) -> Result<()>
 	where
-		T: Serialize,
+		T: Encode,
 	{
-		let mut map = self.map.write().expect("Lock acquired");
+		let key: HashMapKey = key.into();
+		let value = value.encode();
+		let mut entries = self.entries.write().expect("Lock acquired");
 
-		map.insert(key.into(), serde_json::to_string(&value)?);
+		let replaced_size = entries
+			.map
+			.get(&key)
+			.map_or(0, |replaced| entry_size(&key, replaced));
+		entries.size = entries.size - replaced_size + entry_size(&key, &value);
+		entries.map.insert(key, value);
 		Ok(())
 	}
 
 	fn get<T>(&self, key: Key) -> Result<Option<T>>
 	where
-		T: for<'a> Deserialize<'a>,
+		T: Decode,
 	{
-		let map = self.map.read().expect("Lock acquired");
-		map.get(&key.into())
-			.map(|value| serde_json::from_str(value).map_err(|error| eyre!("{error}")))
+		let entries = self.entries.read().expect("Lock acquired");
+		entries
+			.map
+			.get(&key.into())
+			.map(|value| T::decode(&mut &value[..]).wrap_err("Failed decoding the value."))
 			.transpose()
 	}
 
 	fn delete(&self, key: Key) -> Result<()> {
-		let mut map = self.map.write().expect("Lock acquired");
-		map.remove(&key.into());
+		let key: HashMapKey = key.into();
+		let mut entries = self.entries.write().expect("Lock acquired");
+		if let Some(value) = entries.map.remove(&key) {
+			entries.size -= entry_size(&key, &value);
+		}
+		Ok(())
+	}
+
+	fn get_range<T>(&self, range: KeyRange) -> Result<Vec<(Key, T)>>
+	where
+		T: Decode,
+	{
+		if range.blocks().is_empty() {
+			return Ok(vec![]);
+		}
+
+		let entries = self.entries.read().expect("Lock acquired");
+		let (start, end): (HashMapKey, HashMapKey) = (range.start().into(), range.end().into());
+		entries
+			.map
+			.range(start..end)
+			.map(|(HashMapKey(key), value)| {
+				let key = decode_key(&range, key).ok_or_else(|| eyre!("Invalid key {key}"))?;
+				let value = T::decode(&mut &value[..]).wrap_err("Failed decoding the value.")?;
+				Ok((key, value))
+			})
+			.collect()
+	}
+
+	fn delete_range(&self, range: KeyRange) -> Result<()> {
+		if range.blocks().is_empty() {
+			return Ok(());
+		}
+
+		let mut entries = self.entries.write().expect("Lock acquired");
+		let (start, end): (HashMapKey, HashMapKey) = (range.start().into(), range.end().into());
+		let mut tail = entries.map.split_off(&start);
+		let mut rest = tail.split_off(&end);
+		entries.map.append(&mut rest);
+		let deleted_size: u64 = tail.iter().map(|(key, value)| entry_size(key, value)).sum();
+		entries.size -= deleted_size;
+		Ok(())
+	}
+
+	fn compact_range(&self, _: KeyRange) -> Result<()> {
+		// Space is reclaimed on delete
 		Ok(())
 	}
+
+	fn size(&self) -> Result<u64> {
+		Ok(self.entries.read().expect("Lock acquired").size)
+	}
+}
+
+/// Decodes key of the same kind as the keys in the given range
+fn decode_key(range: &KeyRange, key: &str) -> Option<Key> {
+	let mut parts = key.rsplit(':');
+	match range {
+		// Cell key ends with block number, row and column
+		KeyRange::Cells(_) => {
+			let col = parts.next()?.parse::<u16>().ok()?;
+			let row = parts.next()?.parse::<u32>().ok()?;
+			let block_number = parts.next()?.parse::<u32>().ok()?;
+			Some(Key::Cell(block_number, row, col))
+		},
+		_ => Some(range.key(parts.next()?.parse::<u32>().ok()?)),
+	}
 }
 
 impl From<Key> for HashMapKey {
+	// Block numbers are zero padded, so the keys are sorted the same way as in RocksDB
 	fn from(key: Key) -> Self {
 		match key {
 			Key::AppData(app_id, block_number) => {
-				HashMapKey(format!("{APP_DATA_CF}:{app_id}:{block_number}"))
+				HashMapKey(format!("{APP_DATA_CF}:{app_id:010}:{block_number:010}"))
 			},
 			Key::BlockHeader(block_number) => {
-				HashMapKey(format!("{BLOCK_HEADER_CF}:{block_number}"))
+				HashMapKey(format!("{BLOCK_HEADER_CF}:{block_number:010}"))
+			},
+			Key::TrustedHeader(block_number) => {
+				HashMapKey(format!("{TRUSTED_HEADER_CF}:{block_number:010}"))
 			},
 			Key::VerifiedCellCount(block_number) => {
-				HashMapKey(format!("{CONFIDENCE_FACTOR_CF}:{block_number}"))
+				HashMapKey(format!("{CONFIDENCE_FACTOR_CF}:{block_number:010}"))
+			},
+			Key::BlockConfidence(block_number) => {
+				HashMapKey(format!("{BLOCK_CONFIDENCE_CF}:{block_number:010}"))
+			},
+			Key::RetryBlock(block_number) => {
+				HashMapKey(format!("{RETRY_QUEUE_CF}:{block_number:010}"))
+			},
+			Key::InvalidProofs(block_number) => {
+				HashMapKey(format!("{INVALID_PROOF_CF}:{block_number:010}"))
+			},
+			Key::Cell(block_number, row, col) => {
+				HashMapKey(format!("{CELLS_CF}:{block_number:010}:{row:010}:{col:05}"))
 			},
 			Key::FinalitySyncCheckpoint => HashMapKey(FINALITY_SYNC_CHECKPOINT_KEY.to_string()),
+			Key::SchemaVersion => HashMapKey(SCHEMA_VERSION_KEY.to_string()),
+			Key::StateCheckpoint => HashMapKey(STATE_CHECKPOINT_KEY.to_string()),
+			Key::SyncCursor => HashMapKey(SYNC_CURSOR_KEY.to_string()),
+			Key::BlockedPeers => HashMapKey(BLOCKED_PEERS_KEY.to_string()),
+			Key::CellCacheCutoff => HashMapKey(CELL_CACHE_CUTOFF_KEY.to_string()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::MemoryDB;
+	use crate::data::{Database, Key, KeyRange};
+
+	#[test]
+	fn get_range_is_sorted_by_block_number() {
+		let db = MemoryDB::default();
+		for block_number in [10, 2, 9, 100, 1] {
+			db.put(Key::VerifiedCellCount(block_number), block_number)
+				.unwrap();
 		}
+		db.put(Key::AppData(1, 5), 5u32).unwrap();
+
+		let values: Vec<(Key, u32)> = db.get_range(KeyRange::VerifiedCellCount(2..100)).unwrap();
+		let expected = vec![
+			(Key::VerifiedCellCount(2), 2),
+			(Key::VerifiedCellCount(9), 9),
+			(Key::VerifiedCellCount(10), 10),
+		];
+		assert_eq!(values, expected);
+
+		let values: Vec<(Key, u32)> = db.get_range(KeyRange::AppData(1, 0..u32::MAX)).unwrap();
+		assert_eq!(values, vec![(Key::AppData(1, 5), 5)]);
+
+		let values: Vec<(Key, u32)> = db.get_range(KeyRange::AppData(2, 0..u32::MAX)).unwrap();
+		assert!(values.is_empty());
+
+		db.put(Key::Cell(3, 1, 2), 3u32).unwrap();
+		let values: Vec<(Key, u32)> = db.get_range(KeyRange::Cells(0..10)).unwrap();
+		assert_eq!(values, vec![(Key::Cell(3, 1, 2), 3)]);
+	}
+
+	#[test]
+	fn size_is_tracked() {
+		let db = MemoryDB::default();
+		db.put(Key::BlockHeader(1), vec![0u8; 40]).unwrap();
+		let size = db.size().unwrap();
+		assert!(size > 40);
+
+		// Replaced and deleted values are not counted
+		db.put(Key::BlockHeader(1), vec![0u8; 10]).unwrap();
+		assert_eq!(db.size().unwrap(), size - 30);
+		db.delete_range(KeyRange::BlockHeader(0..2)).unwrap();
+		assert_eq!(db.size().unwrap(), 0);
+		db.put(Key::BlockHeader(2), vec![0u8; 40]).unwrap();
+		assert_eq!(db.size().unwrap(), size);
 	}
 }
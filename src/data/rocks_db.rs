@@ -1,11 +1,31 @@
-use crate::data::{self, Key, APP_DATA_CF, BLOCK_HEADER_CF, CONFIDENCE_FACTOR_CF, STATE_CF};
+use crate::data::{
+	self, Key, KeyRange, APP_DATA_CF, BLOCK_CONFIDENCE_CF, BLOCK_HEADER_CF, CELLS_CF,
+	CONFIDENCE_FACTOR_CF, INVALID_PROOF_CF, RETRY_QUEUE_CF, STATE_CF, TRUSTED_HEADER_CF,
+};
 use codec::{Decode, Encode};
 use color_eyre::eyre::{eyre, Context, Result};
-use rocksdb::{ColumnFamilyDescriptor, Options};
+use rocksdb::{ColumnFamilyDescriptor, IteratorMode, Options, ReadOptions};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
-use super::FINALITY_SYNC_CHECKPOINT_KEY;
+use super::{
+	BLOCKED_PEERS_KEY, CELL_CACHE_CUTOFF_KEY, FINALITY_SYNC_CHECKPOINT_KEY, SCHEMA_VERSION_KEY,
+	STATE_CHECKPOINT_KEY, SYNC_CURSOR_KEY,
+};
+
+mod migrations;
+
+const COLUMN_FAMILIES: [&str; 9] = [
+	CONFIDENCE_FACTOR_CF,
+	BLOCK_CONFIDENCE_CF,
+	BLOCK_HEADER_CF,
+	APP_DATA_CF,
+	STATE_CF,
+	RETRY_QUEUE_CF,
+	INVALID_PROOF_CF,
+	CELLS_CF,
+	TRUSTED_HEADER_CF,
+];
 
 #[derive(Clone)]
 pub struct RocksDB {
@@ -16,9 +36,14 @@ impl RocksDB {
 	pub fn open(path: &str) -> Result<RocksDB> {
 		let cf_opts = vec![
 			ColumnFamilyDescriptor::new(CONFIDENCE_FACTOR_CF, Options::default()),
+			ColumnFamilyDescriptor::new(BLOCK_CONFIDENCE_CF, Options::default()),
 			ColumnFamilyDescriptor::new(BLOCK_HEADER_CF, Options::default()),
 			ColumnFamilyDescriptor::new(APP_DATA_CF, Options::default()),
 			ColumnFamilyDescriptor::new(STATE_CF, Options::default()),
+			ColumnFamilyDescriptor::new(RETRY_QUEUE_CF, Options::default()),
+			ColumnFamilyDescriptor::new(INVALID_PROOF_CF, Options::default()),
+			ColumnFamilyDescriptor::new(CELLS_CF, Options::default()),
+			ColumnFamilyDescriptor::new(TRUSTED_HEADER_CF, Options::default()),
 		];
 
 		let mut db_opts = Options::default();
@@ -26,7 +51,9 @@ impl RocksDB {
 		db_opts.create_missing_column_families(true);
 
 		let db = rocksdb::DB::open_cf_descriptors(&db_opts, path, cf_opts)?;
-		Ok(RocksDB { db: Arc::new(db) })
+		let db = RocksDB { db: Arc::new(db) };
+		migrations::run(&db).wrap_err("Database migration failed")?;
+		Ok(db)
 	}
 }
 
@@ -35,25 +62,83 @@ type RocksKey = (Option<&'static str>, Vec<u8>);
 impl From<Key> for (Option<&'static str>, Vec<u8>) {
 	fn from(key: Key) -> Self {
 		match key {
+			// Big endian encoding keeps app data keys sorted by app ID, then by block number
 			Key::AppData(app_id, block_number) => (
 				Some(APP_DATA_CF),
-				format!("{app_id}:{block_number}").into_bytes(),
+				[app_id.to_be_bytes(), block_number.to_be_bytes()].concat(),
 			),
 			Key::BlockHeader(block_number) => {
 				(Some(BLOCK_HEADER_CF), block_number.to_be_bytes().to_vec())
 			},
+			Key::TrustedHeader(block_number) => {
+				(Some(TRUSTED_HEADER_CF), block_number.to_be_bytes().to_vec())
+			},
 			Key::VerifiedCellCount(block_number) => (
 				Some(CONFIDENCE_FACTOR_CF),
 				block_number.to_be_bytes().to_vec(),
 			),
+			Key::BlockConfidence(block_number) => (
+				Some(BLOCK_CONFIDENCE_CF),
+				block_number.to_be_bytes().to_vec(),
+			),
+			Key::RetryBlock(block_number) => {
+				(Some(RETRY_QUEUE_CF), block_number.to_be_bytes().to_vec())
+			},
+			Key::InvalidProofs(block_number) => {
+				(Some(INVALID_PROOF_CF), block_number.to_be_bytes().to_vec())
+			},
+			// Cell keys are sorted by block number first, so the cells can be evicted by block range
+			Key::Cell(block_number, row, col) => (
+				Some(CELLS_CF),
+				[
+					&block_number.to_be_bytes()[..],
+					&row.to_be_bytes()[..],
+					&col.to_be_bytes()[..],
+				]
+				.concat(),
+			),
 			Key::FinalitySyncCheckpoint => (
 				Some(STATE_CF),
 				FINALITY_SYNC_CHECKPOINT_KEY.as_bytes().to_vec(),
 			),
+			Key::SchemaVersion => (Some(STATE_CF), SCHEMA_VERSION_KEY.as_bytes().to_vec()),
+			Key::StateCheckpoint => (Some(STATE_CF), STATE_CHECKPOINT_KEY.as_bytes().to_vec()),
+			Key::SyncCursor => (Some(STATE_CF), SYNC_CURSOR_KEY.as_bytes().to_vec()),
+			Key::CellCacheCutoff => (Some(STATE_CF), CELL_CACHE_CUTOFF_KEY.as_bytes().to_vec()),
+			Key::BlockedPeers => (Some(STATE_CF), BLOCKED_PEERS_KEY.as_bytes().to_vec()),
 		}
 	}
 }
 
+/// Decodes database key of the same kind as the keys in the given range
+fn decode_key(range: &KeyRange, key: &[u8]) -> Result<Key> {
+	let invalid_key = || eyre!("Invalid database key {key:?}");
+	let block_number = |bytes: &[u8]| -> Result<u32> {
+		Ok(u32::from_be_bytes(
+			bytes.try_into().map_err(|_| invalid_key())?,
+		))
+	};
+	match range {
+		// Cell key is encoded as block number, row and column
+		KeyRange::Cells(_) => {
+			if key.len() != 10 {
+				return Err(invalid_key());
+			}
+			let row = u32::from_be_bytes(key[4..8].try_into()?);
+			let col = u16::from_be_bytes(key[8..10].try_into()?);
+			Ok(Key::Cell(block_number(&key[..4])?, row, col))
+		},
+		// App data key is encoded as app ID and block number
+		KeyRange::AppData(..) => {
+			if key.len() != 8 {
+				return Err(invalid_key());
+			}
+			Ok(range.key(block_number(&key[4..])?))
+		},
+		_ => Ok(range.key(block_number(key)?)),
+	}
+}
+
 impl data::Database for RocksDB {
 	type Key = RocksKey;
 
@@ -126,4 +211,119 @@ impl data::Database for RocksDB {
 			.delete_cf(&cf_handle, key)
 			.wrap_err("Delete operation with Column Family failed on RocksDB")
 	}
+
+	fn get_range<T>(&self, range: KeyRange) -> Result<Vec<(Key, T)>>
+	where
+		for<'a> T: Deserialize<'a> + Decode,
+	{
+		if range.blocks().is_empty() {
+			return Ok(vec![]);
+		}
+
+		let (column_family, start): RocksKey = range.start().into();
+		let (_, end): RocksKey = range.end().into();
+		let cf = column_family.ok_or_else(|| eyre!("Range keys must have Column Family"))?;
+		let cf_handle = self
+			.db
+			.cf_handle(cf)
+			.ok_or_else(|| eyre!("Couldn't get Column Family handle from RocksDB"))?;
+
+		let mut read_options = ReadOptions::default();
+		read_options.set_iterate_lower_bound(start);
+		read_options.set_iterate_upper_bound(end);
+
+		self.db
+			.iterator_cf_opt(&cf_handle, read_options, IteratorMode::Start)
+			.map(|result| {
+				let (key, value) = result.wrap_err("Iterate operation failed on RocksDB")?;
+				let key = decode_key(&range, &key)?;
+				let value = <T>::decode(&mut &value[..]).wrap_err("Failed decoding the value.")?;
+				Ok((key, value))
+			})
+			.collect::<Result<Vec<_>>>()
+			.wrap_err("Get range operation with Column Family failed on RocksDB")
+	}
+
+	fn delete_range(&self, range: KeyRange) -> Result<()> {
+		if range.blocks().is_empty() {
+			return Ok(());
+		}
+
+		let (column_family, start): RocksKey = range.start().into();
+		let (_, end): RocksKey = range.end().into();
+		let cf = column_family.ok_or_else(|| eyre!("Range keys must have Column Family"))?;
+		let cf_handle = self
+			.db
+			.cf_handle(cf)
+			.ok_or_else(|| eyre!("Couldn't get Column Family handle from RocksDB"))?;
+
+		// Range delete only writes a tombstone, disk space is reclaimed by the background compaction
+		self.db
+			.delete_range_cf(&cf_handle, &start, &end)
+			.wrap_err("Delete range operation with Column Family failed on RocksDB")
+	}
+
+	fn compact_range(&self, range: KeyRange) -> Result<()> {
+		if range.blocks().is_empty() {
+			return Ok(());
+		}
+
+		let (column_family, start): RocksKey = range.start().into();
+		let (_, end): RocksKey = range.end().into();
+		let cf = column_family.ok_or_else(|| eyre!("Range keys must have Column Family"))?;
+		let cf_handle = self
+			.db
+			.cf_handle(cf)
+			.ok_or_else(|| eyre!("Couldn't get Column Family handle from RocksDB"))?;
+
+		// Compaction drops the range tombstones together with the deleted values
+		self.db
+			.compact_range_cf(&cf_handle, Some(&start), Some(&end));
+		Ok(())
+	}
+
+	fn size(&self) -> Result<u64> {
+		COLUMN_FAMILIES
+			.into_iter()
+			.map(|cf| -> Result<u64> {
+				let cf_handle = self
+					.db
+					.cf_handle(cf)
+					.ok_or_else(|| eyre!("Couldn't get Column Family handle from RocksDB"))?;
+				let size = self
+					.db
+					.property_int_value_cf(&cf_handle, "rocksdb.total-sst-files-size")
+					.wrap_err("Property operation with Column Family failed on RocksDB")?;
+				Ok(size.unwrap_or(0))
+			})
+			.sum()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{decode_key, RocksKey};
+	use crate::data::{Key, KeyRange};
+
+	#[test]
+	fn app_data_keys_are_sorted_by_block_number() {
+		let (_, key_9): RocksKey = Key::AppData(1, 9).into();
+		let (_, key_10): RocksKey = Key::AppData(1, 10).into();
+		let (_, key_next_app): RocksKey = Key::AppData(2, 0).into();
+		assert!(key_9 < key_10);
+		assert!(key_10 < key_next_app);
+	}
+
+	#[test]
+	fn keys_are_decoded_by_kind() {
+		for (range, key) in [
+			(KeyRange::Cells(0..10), Key::Cell(5, 70_000, 3)),
+			(KeyRange::AppData(2, 0..10), Key::AppData(2, 5)),
+			(KeyRange::BlockHeader(0..10), Key::BlockHeader(5)),
+		] {
+			let (_, encoded): RocksKey = key.clone().into();
+			assert_eq!(decode_key(&range, &encoded).unwrap(), key);
+		}
+		assert!(decode_key(&KeyRange::Cells(0..10), &[0, 0, 0, 5]).is_err());
+	}
 }
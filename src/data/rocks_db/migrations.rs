@@ -0,0 +1,123 @@
+use color_eyre::eyre::{eyre, Context, Result};
+use rocksdb::{IteratorMode, WriteBatch};
+use tracing::info;
+
+use super::RocksDB;
+use crate::data::{Database, Key, APP_DATA_CF};
+
+/// Current version of the database schema
+pub const SCHEMA_VERSION: u32 = 1;
+
+type Migration = fn(&RocksDB) -> Result<()>;
+
+/// Registry of the database migrations.
+/// Migration at index `N` upgrades the database schema from version `N` to version `N + 1`.
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [migrate_to_v1];
+
+/// Runs pending migrations and updates schema version of the database.
+/// Database without schema version is considered to be at version 0.
+pub fn run(db: &RocksDB) -> Result<()> {
+	let version = db
+		.get::<u32>(Key::SchemaVersion)
+		.wrap_err("Failed to get database schema version")?
+		.unwrap_or(0);
+
+	if version > SCHEMA_VERSION {
+		return Err(eyre!(
+			"Database schema version {version} is newer than supported version {SCHEMA_VERSION}, upgrade the light client or remove the database"
+		));
+	}
+
+	for (from_version, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+		let to_version = from_version as u32 + 1;
+		info!(from_version, to_version, "Migrating database schema...");
+		migration(db).wrap_err_with(|| {
+			format!("Failed to migrate database schema to version {to_version}")
+		})?;
+		db.put(Key::SchemaVersion, to_version)
+			.wrap_err("Failed to update database schema version")?;
+	}
+
+	Ok(())
+}
+
+/// Converts app data key from `{app_id}:{block_number}` string format into the big endian format.
+fn app_data_key(key: &[u8]) -> Option<Vec<u8>> {
+	let (app_id, block_number) = std::str::from_utf8(key).ok()?.split_once(':')?;
+	let app_id = app_id.parse::<u32>().ok()?;
+	let block_number = block_number.parse::<u32>().ok()?;
+	Some([app_id.to_be_bytes(), block_number.to_be_bytes()].concat())
+}
+
+/// Re-keys app data, so the keys are sorted by app ID, then by block number.
+fn rekey_app_data(db: &RocksDB) -> Result<()> {
+	let cf_handle = db
+		.db
+		.cf_handle(APP_DATA_CF)
+		.ok_or_else(|| eyre!("Couldn't get Column Family handle from RocksDB"))?;
+
+	let mut batch = WriteBatch::default();
+	for result in db.db.iterator_cf(&cf_handle, IteratorMode::Start) {
+		let (key, value) = result.wrap_err("Iterate operation failed on RocksDB")?;
+		let Some(new_key) = app_data_key(&key) else {
+			continue;
+		};
+		batch.delete_cf(&cf_handle, &key);
+		batch.put_cf(&cf_handle, new_key, value);
+	}
+
+	info!(keys = batch.len() / 2, "Re-keying app data...");
+	db.db
+		.write(batch)
+		.wrap_err("Write operation failed on RocksDB")
+}
+
+/// Migrates the database without schema version.
+/// App data is re-keyed, and state checkpoint stored in the format without failed block sets is removed,
+/// so the state is restored from the next stored checkpoint.
+fn migrate_to_v1(db: &RocksDB) -> Result<()> {
+	rekey_app_data(db)?;
+	db.delete(Key::StateCheckpoint)
+		.wrap_err("Failed to remove state checkpoint")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{app_data_key, run, SCHEMA_VERSION};
+	use crate::data::{rocks_db::RocksDB, Database, Key, APP_DATA_CF};
+
+	#[test]
+	fn app_data_key_is_converted() {
+		let key = app_data_key(b"1:10").unwrap();
+		assert_eq!(key, vec![0, 0, 0, 1, 0, 0, 0, 10]);
+		assert!(app_data_key(b"1").is_none());
+		assert!(app_data_key(b"a:10").is_none());
+		assert!(app_data_key(&[0, 0, 0, 1, 0, 0, 0, 10]).is_none());
+	}
+
+	#[test]
+	fn newer_schema_is_not_migrated() {
+		let path = std::env::temp_dir().join(format!(
+			"avail_light_newer_schema_test_{}",
+			std::process::id()
+		));
+		let db = RocksDB::open(path.to_str().unwrap()).unwrap();
+		db.put(Key::SchemaVersion, SCHEMA_VERSION + 1).unwrap();
+		let cf_handle = db.db.cf_handle(APP_DATA_CF).unwrap();
+		db.db.put_cf(&cf_handle, b"1:10", b"data").unwrap();
+
+		assert!(run(&db).is_err());
+		assert_eq!(
+			db.get::<u32>(Key::SchemaVersion).unwrap(),
+			Some(SCHEMA_VERSION + 1)
+		);
+		assert_eq!(
+			db.db.get_cf(&cf_handle, b"1:10").unwrap(),
+			Some(b"data".to_vec())
+		);
+
+		drop(cf_handle);
+		drop(db);
+		std::fs::remove_dir_all(path).unwrap();
+	}
+}
@@ -25,10 +25,11 @@ use std::{sync::Arc, time::Instant};
 use tracing::{debug, error, info, warn};
 
 use crate::{
-	data::{Database, Key},
+	data::Database,
+	header_chain,
 	network::{
 		p2p::Client as P2pClient,
-		rpc::{Client as RpcClient, Event},
+		rpc::{Client as RpcClient, Event, RecoveringReceiver},
 	},
 	shutdown::Controller,
 	telemetry::{MetricCounter, MetricValue, Metrics},
@@ -113,7 +114,7 @@ pub async fn process_block(
 	// another competing thread, which syncs all block headers
 	// in range [0, LATEST], where LATEST = latest block number
 	// when this process started
-	db.put(Key::BlockHeader(block_number), header)
+	header_chain::store_trusted(&db, &header)
 		.wrap_err("Fat Client failed to store Block Header")?;
 
 	// Fat client partition upload logic
@@ -190,6 +191,7 @@ pub async fn process_block(
 /// # Arguments
 ///
 /// * `fat_client` - Fat client implementation
+/// * `rpc_client` - RPC client used to recover blocks skipped due to the lag
 /// * `cfg` - Fat client configuration
 /// * `metrics` -  Metrics registry
 /// * `channels` - Communication channels
@@ -197,17 +199,21 @@ pub async fn process_block(
 /// * `shutdown` - Shutdown controller
 pub async fn run(
 	client: impl Client,
+	rpc_client: RpcClient,
 	db: impl Database + Clone,
 	cfg: FatClientConfig,
 	metrics: Arc<impl Metrics>,
-	mut channels: ClientChannels,
+	channels: ClientChannels,
 	partition: Partition,
 	shutdown: Controller<String>,
 ) {
 	info!("Starting fat client...");
 
+	let mut receiver =
+		RecoveringReceiver::new(channels.rpc_event_receiver, rpc_client, metrics.clone());
+
 	loop {
-		let (header, received_at) = match channels.rpc_event_receiver.recv().await {
+		let (header, received_at) = match receiver.recv().await {
 			Ok(event) => match event {
 				Event::HeaderUpdate {
 					header,
@@ -0,0 +1,362 @@
+//! Header chain linkage verification.
+//!
+//! Headers of the blocks before the finalized head, fetched from RPC, are not covered by the finality proofs.
+//! Such header is accepted only if it is linked by parent hash to a trusted header.
+//! Trusted header is either finalized head, or stored header marked as trusted, since not every stored header
+//! is verified. Header is marked as trusted once it is finality verified, or linked to the trusted header.
+//! Linking walks down from the nearest trusted header above the block, in batches, and each linked batch is
+//! stored and marked as trusted before the next one is fetched, so the following walks are short.
+//! Concurrent walks are serialized per batch, so each batch extends the chain linked by the other walks.
+
+use avail_subxt::primitives::Header;
+use codec::Encode;
+use color_eyre::{
+	eyre::{eyre, WrapErr},
+	Result,
+};
+use futures::future::try_join_all;
+use sp_core::{blake2_256, H256};
+use tokio::sync::Mutex;
+
+use crate::{
+	data::{Database, Key, KeyRange},
+	network::rpc::HeaderSource,
+};
+
+/// Number of headers fetched and linked at once
+const LINK_BATCH_SIZE: u32 = 64;
+
+/// Number of blocks scanned at once while searching for the nearest trusted header
+const TRUSTED_SEARCH_WINDOW: u32 = 1024;
+
+/// Returns the hash of the header, which is the parent hash of its child.
+pub fn header_hash(header: &Header) -> H256 {
+	Encode::using_encoded(header, blake2_256).into()
+}
+
+/// Verifies that the headers, ordered by descending block number, are linked by parent hash to the trusted header.
+/// Returns number of the first header which is not linked, if any.
+pub fn verify(trusted: &Header, headers: &[Header]) -> Result<(), u32> {
+	let mut parent = trusted;
+	for header in headers {
+		if parent.number != header.number + 1 || parent.parent_hash != header_hash(header) {
+			return Err(header.number);
+		}
+		parent = header;
+	}
+	Ok(())
+}
+
+/// Stores the header and marks it as trusted.
+/// Only finality verified headers, or headers linked to the trusted header are to be stored this way.
+pub fn store_trusted(db: &impl Database, header: &Header) -> Result<()> {
+	db.put(Key::BlockHeader(header.number), header.clone())
+		.wrap_err("Failed to store block header")?;
+	db.put(Key::TrustedHeader(header.number), header_hash(header))
+		.wrap_err("Failed to mark block header as trusted")
+}
+
+/// Gets stored header of the block, if it is marked as trusted.
+/// Header which was overwritten after it was marked is not trusted.
+pub fn get_trusted(db: &impl Database, block_number: u32) -> Result<Option<Header>> {
+	let Some(hash) = db
+		.get::<H256>(Key::TrustedHeader(block_number))
+		.wrap_err("Failed to get trusted header hash from the storage")?
+	else {
+		return Ok(None);
+	};
+	let header = db
+		.get::<Header>(Key::BlockHeader(block_number))
+		.wrap_err("Failed to get block header from the storage")?;
+	Ok(header.filter(|header| header_hash(header) == hash))
+}
+
+/// Finds the nearest trusted header above the block, up to the last block, or returns the given anchor.
+fn nearest_trusted(
+	db: &impl Database,
+	anchor: Option<&Header>,
+	last: u32,
+	block_number: u32,
+) -> Result<Header> {
+	let mut start = block_number.saturating_add(1);
+	while start <= last {
+		let end = start
+			.saturating_add(TRUSTED_SEARCH_WINDOW)
+			.min(last.saturating_add(1));
+		for (key, _) in db.get_range::<H256>(KeyRange::TrustedHeader(start..end))? {
+			let Key::TrustedHeader(number) = key else {
+				continue;
+			};
+			if let Some(header) = get_trusted(db, number)? {
+				return Ok(header);
+			}
+		}
+		start = end;
+	}
+	anchor.cloned().ok_or_else(|| {
+		eyre!("Header of the block {block_number} cannot be linked to a trusted header")
+	})
+}
+
+/// Gets trusted header of the block from the database, or fetches it from RPC.
+/// Fetched headers are linked down from the nearest trusted header above the block, up to the given finalized head,
+/// or up to the latest block if finalized head is not given. Linked headers are stored and marked as trusted.
+/// Linking lock is held while a batch is linked, and the walks sharing the lock continue from each other's batches.
+pub async fn get_linked_header(
+	db: &impl Database,
+	header_source: &impl HeaderSource,
+	linking: &Mutex<()>,
+	finalized_head: Option<&Header>,
+	latest: u32,
+	block_number: u32,
+) -> Result<(Header, H256)> {
+	if let Some(head) = finalized_head.filter(|head| head.number == block_number) {
+		return Ok((head.clone(), header_hash(head)));
+	}
+	if let Some(header) = get_trusted(db, block_number)? {
+		let hash = header_hash(&header);
+		return Ok((header, hash));
+	}
+	if finalized_head.map_or(false, |head| block_number > head.number) {
+		return Err(eyre!(
+			"Header of the block {block_number} cannot be linked to the finalized head"
+		));
+	}
+
+	let mut anchor = finalized_head.cloned();
+	let mut last = finalized_head.map_or(latest, |head| head.number);
+	loop {
+		let _linking = linking.lock().await;
+		// Block could be linked by the concurrent walk, while waiting for the lock
+		if let Some(header) = get_trusted(db, block_number)? {
+			let hash = header_hash(&header);
+			return Ok((header, hash));
+		}
+
+		// Concurrent walks could link the headers below the last linked batch
+		let trusted = nearest_trusted(db, anchor.as_ref(), last, block_number)?;
+		let first = trusted
+			.number
+			.saturating_sub(LINK_BATCH_SIZE)
+			.max(block_number);
+		// Headers are fetched in descending order, since the linkage is verified from the trusted header down
+		let headers = try_join_all((first..trusted.number).rev().map(|number| async move {
+			header_source
+				.get_header(number)
+				.await
+				.wrap_err_with(|| format!("Failed to get header of the block {number} from RPC"))
+		}))
+		.await?;
+
+		if let Err(unlinked) = verify(&trusted, &headers) {
+			return Err(eyre!(
+				"Header of the block {unlinked} is not linked to the trusted header of the block {}",
+				trusted.number
+			));
+		}
+
+		for header in &headers {
+			store_trusted(db, header).wrap_err("Failed to store linked block header")?;
+		}
+		let linked = headers
+			.into_iter()
+			.last()
+			.expect("Batch should not be empty");
+		if linked.number == block_number {
+			let hash = header_hash(&linked);
+			return Ok((linked, hash));
+		}
+		last = linked.number - 1;
+		anchor = Some(linked);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{get_linked_header, get_trusted, header_hash, store_trusted, verify};
+	use crate::{
+		data::{mem_db::MemoryDB, Database, Key},
+		network::rpc::HeaderSource,
+		test_utils::header,
+	};
+	use async_trait::async_trait;
+	use avail_subxt::primitives::Header;
+	use color_eyre::{eyre::eyre, Result};
+	use sp_core::H256;
+	use std::sync::atomic::{AtomicU32, Ordering};
+	use tokio::sync::Mutex;
+
+	#[test]
+	fn unlinked_headers_are_rejected() {
+		let first = header(1, H256::default());
+		let second = header(2, header_hash(&first));
+		let head = header(3, header_hash(&second));
+
+		assert_eq!(verify(&head, &[second.clone(), first.clone()]), Ok(()));
+		assert_eq!(verify(&head, &[]), Ok(()));
+		// Gap in the chain
+		assert_eq!(verify(&head, &[first.clone()]), Err(1));
+
+		let forged = header(1, H256::repeat_byte(1));
+		assert_eq!(verify(&head, &[second, forged]), Err(1));
+
+		let forged_head = header(3, H256::repeat_byte(1));
+		assert_eq!(verify(&forged_head, &[header(2, H256::default())]), Err(2));
+	}
+
+	/// Returns chain of the headers, from the genesis up to the given block
+	fn chain(last: u32) -> Vec<Header> {
+		let mut headers = vec![header(0, H256::default())];
+		for number in 1..=last {
+			let parent_hash = header_hash(&headers[number as usize - 1]);
+			headers.push(header(number, parent_hash));
+		}
+		headers
+	}
+
+	struct Headers(Vec<Header>);
+
+	#[async_trait]
+	impl HeaderSource for Headers {
+		async fn get_header(&self, block_number: u32) -> Result<Header> {
+			self.0
+				.get(block_number as usize)
+				.cloned()
+				.ok_or_else(|| eyre!("Header {block_number} is not available"))
+		}
+	}
+
+	/// Header source which counts the fetched headers
+	struct CountedHeaders(Vec<Header>, AtomicU32);
+
+	#[async_trait]
+	impl HeaderSource for CountedHeaders {
+		async fn get_header(&self, block_number: u32) -> Result<Header> {
+			self.1.fetch_add(1, Ordering::Relaxed);
+			self.0
+				.get(block_number as usize)
+				.cloned()
+				.ok_or_else(|| eyre!("Header {block_number} is not available"))
+		}
+	}
+
+	#[test]
+	fn only_marked_headers_are_trusted() {
+		let db = MemoryDB::default();
+		let headers = chain(2);
+
+		store_trusted(&db, &headers[1]).unwrap();
+		assert_eq!(get_trusted(&db, 1).unwrap(), Some(headers[1].clone()));
+
+		// Stored without the marker
+		db.put(Key::BlockHeader(2), headers[2].clone()).unwrap();
+		assert_eq!(get_trusted(&db, 2).unwrap(), None);
+
+		// Overwritten after it was marked
+		db.put(Key::BlockHeader(1), header(1, H256::repeat_byte(1)))
+			.unwrap();
+		assert_eq!(get_trusted(&db, 1).unwrap(), None);
+	}
+
+	#[tokio::test]
+	async fn headers_are_linked_down_from_finalized_head() {
+		let db = MemoryDB::default();
+		let linking = Mutex::default();
+		let headers = chain(200);
+		let head = headers[200].clone();
+
+		// Untrusted stored header is neither used as the anchor, nor returned
+		db.put(Key::BlockHeader(50), header(50, H256::repeat_byte(1)))
+			.unwrap();
+
+		let (linked, hash) = get_linked_header(
+			&db,
+			&Headers(headers.clone()),
+			&linking,
+			Some(&head),
+			200,
+			10,
+		)
+		.await
+		.unwrap();
+		assert_eq!(linked, headers[10]);
+		assert_eq!(hash, header_hash(&headers[10]));
+
+		for number in 10..200 {
+			assert_eq!(
+				get_trusted(&db, number).unwrap(),
+				Some(headers[number as usize].clone())
+			);
+		}
+		assert_eq!(get_trusted(&db, 9).unwrap(), None);
+
+		// Linked down from the nearest trusted header, without the finalized head
+		let (linked, _) = get_linked_header(&db, &Headers(headers.clone()), &linking, None, 200, 5)
+			.await
+			.unwrap();
+		assert_eq!(linked, headers[5]);
+
+		// Blocks above the finalized head cannot be linked
+		assert!(
+			get_linked_header(&db, &Headers(headers), &linking, Some(&head), 200, 201)
+				.await
+				.is_err()
+		);
+	}
+
+	#[tokio::test]
+	async fn linked_batches_are_stored_before_unlinked_header() {
+		let db = MemoryDB::default();
+		let linking = Mutex::default();
+		let mut headers = chain(200);
+		let head = headers[200].clone();
+		headers[100] = header(100, H256::repeat_byte(1));
+
+		assert!(get_linked_header(
+			&db,
+			&Headers(headers.clone()),
+			&linking,
+			Some(&head),
+			200,
+			10
+		)
+		.await
+		.is_err());
+		assert_eq!(get_trusted(&db, 136).unwrap(), Some(headers[136].clone()));
+		assert_eq!(get_trusted(&db, 135).unwrap(), None);
+		assert_eq!(get_trusted(&db, 100).unwrap(), None);
+	}
+
+	#[tokio::test]
+	async fn unlinked_header_without_trusted_header_is_rejected() {
+		let db = MemoryDB::default();
+		let linking = Mutex::default();
+		let headers = chain(10);
+		db.put(Key::BlockHeader(10), headers[10].clone()).unwrap();
+
+		assert!(
+			get_linked_header(&db, &Headers(headers), &linking, None, 10, 5)
+				.await
+				.is_err()
+		);
+	}
+
+	#[tokio::test]
+	async fn concurrent_walks_continue_from_each_other() {
+		let db = MemoryDB::default();
+		let linking = Mutex::default();
+		let headers = chain(200);
+		let head = headers[200].clone();
+		let source = CountedHeaders(headers.clone(), AtomicU32::new(0));
+
+		let (first, second) = tokio::join!(
+			get_linked_header(&db, &source, &linking, Some(&head), 200, 10),
+			get_linked_header(&db, &source, &linking, Some(&head), 200, 5),
+		);
+		assert_eq!(first.unwrap().0, headers[10]);
+		assert_eq!(second.unwrap().0, headers[5]);
+
+		// Every header is fetched only once
+		assert_eq!(source.1.load(Ordering::Relaxed), 195);
+	}
+}
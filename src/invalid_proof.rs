@@ -0,0 +1,184 @@
+//! Alerting on cells which failed proof verification.
+//!
+//! Cell with invalid proof means that the served data doesn't match the commitments,
+//! which is a signal of fraud or data withholding. Network client sends an event for each such cell,
+//! which is persisted in the database, counted in metrics, and published to the v2 API clients.
+
+use codec::{Decode, Encode};
+use color_eyre::{eyre::WrapErr, Result};
+use kate_recovery::data::Cell;
+use serde::{Deserialize, Serialize};
+use std::{
+	ops::Range,
+	sync::Arc,
+	time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{error, info, warn};
+
+use crate::{
+	data::{Database, Key, KeyRange},
+	telemetry::{MetricCounter, Metrics},
+};
+
+/// Source from which the cell was fetched
+#[derive(Clone, Debug, PartialEq, Eq, Decode, Encode, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum CellSource {
+	/// Cell is fetched from the DHT, from the peer if known
+	Dht { peer_id: Option<String> },
+	/// Cell is fetched from the RPC node with the given host
+	Rpc { host: String },
+}
+
+/// Cell which failed proof verification
+#[derive(Clone, Debug, PartialEq, Eq, Decode, Encode, Serialize, Deserialize)]
+pub struct InvalidProof {
+	pub block_number: u32,
+	pub row: u32,
+	pub col: u16,
+	pub source: CellSource,
+	/// Raw cell content, with the proof
+	pub cell: Vec<u8>,
+	/// Time when invalid proof is detected, in milliseconds since UNIX epoch
+	pub detected_at: u64,
+}
+
+impl InvalidProof {
+	pub fn new(block_number: u32, cell: &Cell, source: CellSource) -> Self {
+		let detected_at = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|duration| duration.as_millis() as u64)
+			.unwrap_or(0);
+
+		InvalidProof {
+			block_number,
+			row: cell.position.row,
+			col: cell.position.col,
+			source,
+			cell: cell.content.to_vec(),
+			detected_at,
+		}
+	}
+}
+
+/// Maximum number of invalid proofs stored per block
+pub const MAX_INVALID_PROOFS_PER_BLOCK: usize = 64;
+
+/// Stores invalid proof, along with the other invalid proofs of the same block.
+/// Invalid proofs above the per block limit are not stored, they are only reported and counted.
+/// Returns false if the invalid proof is not stored.
+pub fn store(db: &impl Database, invalid_proof: InvalidProof) -> Result<bool> {
+	let key = Key::InvalidProofs(invalid_proof.block_number);
+	let mut invalid_proofs = db
+		.get::<Vec<InvalidProof>>(key.clone())?
+		.unwrap_or_default();
+	if invalid_proofs.len() >= MAX_INVALID_PROOFS_PER_BLOCK {
+		return Ok(false);
+	}
+	invalid_proofs.push(invalid_proof);
+	db.put(key, invalid_proofs)?;
+	Ok(true)
+}
+
+/// Gets stored invalid proofs of the blocks in the range, ordered by block number.
+pub fn list(db: &impl Database, blocks: Range<u32>) -> Result<Vec<InvalidProof>> {
+	let entries = db.get_range::<Vec<InvalidProof>>(KeyRange::InvalidProofs(blocks))?;
+	Ok(entries
+		.into_iter()
+		.flat_map(|(_, invalid_proofs)| invalid_proofs)
+		.collect())
+}
+
+/// Runs invalid proof alerting, persisting and counting received invalid proofs.
+pub async fn run(
+	db: impl Database,
+	mut receiver: broadcast::Receiver<InvalidProof>,
+	metrics: Arc<impl Metrics>,
+) {
+	info!("Starting invalid proof alerting...");
+
+	loop {
+		let invalid_proof = match receiver.recv().await {
+			Ok(invalid_proof) => invalid_proof,
+			Err(RecvError::Lagged(skipped)) => {
+				warn!(skipped, "Invalid proof receiver lagged behind the sender");
+				continue;
+			},
+			Err(error @ RecvError::Closed) => {
+				error!("Cannot receive invalid proof: {error}");
+				return;
+			},
+		};
+
+		error!(
+			block_number = invalid_proof.block_number,
+			row = invalid_proof.row,
+			col = invalid_proof.col,
+			source = ?invalid_proof.source,
+			"Cell proof verification failed"
+		);
+		metrics.count(MetricCounter::InvalidProof).await;
+
+		let block_number = invalid_proof.block_number;
+		match store(&db, invalid_proof).wrap_err("Failed to store invalid proof") {
+			Ok(true) => (),
+			Ok(false) => warn!(block_number, "Too many invalid proofs, not storing"),
+			Err(error) => error!("{error:#}"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{list, store, CellSource, InvalidProof, MAX_INVALID_PROOFS_PER_BLOCK};
+	use crate::data::mem_db::MemoryDB;
+	use kate_recovery::{data::Cell, matrix::Position};
+
+	fn invalid_proof(block_number: u32, col: u16) -> InvalidProof {
+		let cell = Cell {
+			position: Position { row: 0, col },
+			content: [0; 80],
+		};
+		let source = CellSource::Rpc {
+			host: "ws://127.0.0.1:9944".to_string(),
+		};
+		InvalidProof::new(block_number, &cell, source)
+	}
+
+	#[test]
+	fn store_keeps_invalid_proofs_of_the_same_block() {
+		let db = MemoryDB::default();
+		store(&db, invalid_proof(10, 1)).unwrap();
+		store(&db, invalid_proof(5, 1)).unwrap();
+		store(&db, invalid_proof(10, 2)).unwrap();
+
+		let invalid_proofs = list(&db, 0..100).unwrap();
+		let positions = invalid_proofs
+			.iter()
+			.map(|invalid_proof| (invalid_proof.block_number, invalid_proof.col))
+			.collect::<Vec<_>>();
+		assert_eq!(positions, vec![(5, 1), (10, 1), (10, 2)]);
+		assert_eq!(invalid_proofs[0].cell.len(), 80);
+
+		let invalid_proofs = list(&db, 6..11).unwrap();
+		assert_eq!(invalid_proofs.len(), 2);
+		assert!(list(&db, 0..5).unwrap().is_empty());
+	}
+
+	#[test]
+	fn store_limits_invalid_proofs_per_block() {
+		let db = MemoryDB::default();
+		for col in 0..MAX_INVALID_PROOFS_PER_BLOCK as u16 {
+			assert!(store(&db, invalid_proof(10, col)).unwrap());
+		}
+		assert!(!store(&db, invalid_proof(10, 100)).unwrap());
+		assert!(store(&db, invalid_proof(11, 100)).unwrap());
+
+		let invalid_proofs = list(&db, 10..11).unwrap();
+		assert_eq!(invalid_proofs.len(), MAX_INVALID_PROOFS_PER_BLOCK);
+		assert!(invalid_proofs
+			.iter()
+			.all(|invalid_proof| invalid_proof.col != 100));
+	}
+}
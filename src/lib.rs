@@ -1,18 +1,29 @@
 pub mod api;
 pub mod app_client;
+pub mod archive;
+pub mod cell_cache;
+pub mod confidence;
 pub mod consts;
 #[cfg(feature = "crawl")]
 pub mod crawl_client;
 pub mod data;
 pub mod fat_client;
 pub mod finality;
+pub mod header_chain;
+pub mod invalid_proof;
 pub mod light_client;
 pub mod maintenance;
 pub mod network;
 pub mod proof;
+pub mod retention;
+pub mod retry_queue;
+pub mod sampling;
 pub mod shutdown;
+pub mod state;
 pub mod sync_client;
 pub mod sync_finality;
 pub mod telemetry;
+#[cfg(test)]
+mod test_utils;
 pub mod types;
 pub mod utils;
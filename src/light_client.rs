@@ -8,6 +8,7 @@
 //! * Generate random cells for random data sampling (8 cells currently)
 //! * Retrieve cell proofs from a) DHT and/or b) via RPC call from the node, in that order
 //! * Verify proof using the received cells
+//! * Sample additional cells if some cells cannot be fetched or verified, up to the configured limits
 //! * Calculate block confidence and store it in RocksDB
 //! * Insert cells to to DHT for remote fetch
 //! * Notify the consumer (app client) a new block has been verified
@@ -16,30 +17,125 @@
 //!
 //! In case delay is configured, block processing is delayed for configured time.
 //! In case RPC is disabled, RPC calls will be skipped.
+//! Multiple blocks are sampled concurrently, up to the configured limit, but verified blocks are emitted in order.
 
 use avail_subxt::{primitives::Header, utils::H256};
 use codec::Encode;
 use color_eyre::{eyre::WrapErr, Result};
-use kate_recovery::{commitments, matrix::Dimensions};
+use futures::stream::{self, StreamExt};
+use kate_recovery::{
+	commitments,
+	data::Cell,
+	matrix::{Dimensions, Position},
+};
 use sp_core::blake2_256;
 use std::{
-	sync::{Arc, Mutex},
+	pin::pin,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc, Mutex,
+	},
 	time::Instant,
 };
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use crate::{
+	confidence::{self, Confidence, SamplingResult},
 	data::{Database, Key},
+	header_chain,
 	network::{
 		self,
-		rpc::{self, Event},
+		rpc::{self, Event, RecoveringReceiver},
+		FetchStats,
 	},
+	retry_queue,
+	sampling::SamplingStrategy,
 	shutdown::Controller,
 	telemetry::{MetricCounter, MetricValue, Metrics},
 	types::{self, ClientChannels, LightClientConfig, OptionBlockRange, State},
-	utils::{calculate_confidence, extract_kate},
+	utils::extract_kate,
 };
 
+/// Fetches and verifies cells required to achieve configured confidence.
+/// If some cells cannot be fetched or verified, additional cells are sampled in the next round,
+/// until the required number of cells is verified, or maximum number of rounds or cells is reached.
+/// Returns all requested positions, fetched cells, unfetched positions and fetch stats of all rounds.
+/// Each cell with invalid proof cancels one verified cell, so it is replaced by the additional cells,
+/// and the block can still achieve the confidence, since invalid proofs may be served by a faulty peer,
+/// not by the block author. Such block is reported with the invalid proof flag of the confidence.
+#[allow(clippy::too_many_arguments)]
+pub async fn fetch_verified_adaptive(
+	network_client: &impl network::Client,
+	strategy: &dyn SamplingStrategy,
+	max_sampling_rounds: u32,
+	max_sampled_cells: u32,
+	block_number: u32,
+	header_hash: H256,
+	dimensions: Dimensions,
+	commitments: &[[u8; 48]],
+	required: u32,
+) -> Result<(Vec<Position>, Vec<Cell>, Vec<Position>, FetchStats)> {
+	let mut positions = strategy.select_cells(block_number, dimensions, required);
+	info!(
+		block_number,
+		"cells_requested" = positions.len(),
+		"Random cells generated: {}",
+		positions.len()
+	);
+
+	let (mut fetched, mut unfetched, mut stats) = network_client
+		.fetch_verified(
+			block_number,
+			header_hash,
+			dimensions,
+			commitments,
+			&positions,
+		)
+		.await?;
+
+	// Verified cells which are counted towards the confidence
+	let counted = |fetched: &[Cell], stats: &FetchStats| {
+		(fetched.len() as u32).saturating_sub(stats.invalid_proofs as u32)
+	};
+	while counted(&fetched, &stats) < required && stats.rounds < max_sampling_rounds {
+		let missing = required - counted(&fetched, &stats);
+		let allowed = max_sampled_cells.saturating_sub(positions.len() as u32);
+		let additional = strategy.select_additional_cells(
+			block_number,
+			dimensions,
+			&positions,
+			missing.min(allowed),
+		);
+		if additional.is_empty() {
+			break;
+		}
+
+		info!(
+			block_number,
+			round = stats.rounds + 1,
+			additional_cells = additional.len(),
+			"Sampling additional cells"
+		);
+		let (round_fetched, round_unfetched, round_stats) = network_client
+			.fetch_verified(
+				block_number,
+				header_hash,
+				dimensions,
+				commitments,
+				&additional,
+			)
+			.await?;
+
+		let additional_cells = additional.len();
+		positions.extend(additional);
+		fetched.extend(round_fetched);
+		unfetched.extend(round_unfetched);
+		stats.add_round(round_stats, positions.len(), additional_cells);
+	}
+
+	Ok((positions, fetched, unfetched, stats))
+}
+
 pub async fn process_block(
 	db: impl Database,
 	network_client: &impl network::Client,
@@ -48,7 +144,7 @@ pub async fn process_block(
 	header: Header,
 	received_at: Instant,
 	state: Arc<Mutex<State>>,
-) -> Result<Option<f64>> {
+) -> Result<Option<Confidence>> {
 	metrics.count(MetricCounter::SessionBlock).await;
 	metrics
 		.record(MetricValue::TotalBlockNumber(header.number))
@@ -77,24 +173,20 @@ pub async fn process_block(
 	}
 
 	let commitments = commitments::from_slice(&commitment)?;
-	let cell_count = rpc::cell_count_for_confidence(cfg.confidence);
-	let positions = rpc::generate_random_cells(dimensions, cell_count);
-	info!(
-		block_number,
-		"cells_requested" = positions.len(),
-		"Random cells generated: {}",
-		positions.len()
-	);
+	let required = (cfg.sampling_strategy).cell_count(dimensions, cfg.confidence);
 
-	let (fetched, unfetched, fetch_stats) = network_client
-		.fetch_verified(
-			block_number,
-			header_hash,
-			dimensions,
-			&commitments,
-			&positions,
-		)
-		.await?;
+	let (positions, fetched, unfetched, fetch_stats) = fetch_verified_adaptive(
+		network_client,
+		&*cfg.sampling_strategy,
+		cfg.max_sampling_rounds,
+		cfg.max_sampled_cells,
+		block_number,
+		header_hash,
+		dimensions,
+		&commitments,
+		required,
+	)
+	.await?;
 
 	metrics
 		.record(MetricValue::DHTFetched(fetch_stats.dht_fetched))
@@ -124,26 +216,63 @@ pub async fn process_block(
 			.await?;
 	}
 
-	if positions.len() > fetched.len() {
-		error!(block_number, "Failed to fetch {} cells", unfetched.len());
+	metrics
+		.record(MetricValue::SamplingRounds(fetch_stats.rounds))
+		.await?;
+
+	metrics
+		.record(MetricValue::SamplingAdditionalCells(
+			fetch_stats.additional_cells,
+		))
+		.await?;
+
+	let sampling_result = SamplingResult {
+		requested: positions.len() as u32,
+		verified: fetched.len() as u32,
+		invalid_proofs: fetch_stats.invalid_proofs as u32,
+	};
+	let confidence = confidence::calculate(&*cfg.sampling_strategy, dimensions, sampling_result);
+
+	if confidence.invalid_proof {
+		warn!(
+			block_number,
+			invalid_proofs = sampling_result.invalid_proofs,
+			"Fetched cells with invalid proofs"
+		);
+	}
+
+	let required_confidence =
+		confidence::required(&*cfg.sampling_strategy, dimensions, cfg.confidence);
+	if !confidence.is_achieved(required_confidence) {
+		error!(
+			block_number,
+			rounds = fetch_stats.rounds,
+			"Failed to fetch {} cells",
+			unfetched.len()
+		);
+		state.lock().unwrap().confidence_failed.insert(block_number);
+		retry_queue::enqueue(&db, block_number)
+			.wrap_err("Light Client failed to queue block for retry")?;
 		return Ok(None);
 	}
 
 	// write confidence factor into on-disk database
-	db.put(Key::VerifiedCellCount(block_number), fetched.len() as u32)
-		.wrap_err("Light Client failed to store Confidence Factor")?;
-
-	state.lock().unwrap().confidence_achieved.set(block_number);
+	db.put(Key::BlockConfidence(block_number), confidence.clone())
+		.wrap_err("Light Client failed to store Block Confidence")?;
+	db.put(
+		Key::VerifiedCellCount(block_number),
+		sampling_result.verified,
+	)
+	.wrap_err("Light Client failed to store Confidence Factor")?;
 
-	let confidence = calculate_confidence(fetched.len() as u32);
 	info!(
 		block_number,
-		"confidence" = confidence,
+		"confidence" = confidence.value,
 		"Confidence factor: {}",
-		confidence
+		confidence.value
 	);
 	metrics
-		.record(MetricValue::BlockConfidence(confidence))
+		.record(MetricValue::BlockConfidence(confidence.value))
 		.await?;
 
 	// push latest mined block's header into column family specified
@@ -154,17 +283,45 @@ pub async fn process_block(
 	// another competing thread, which syncs all block headers
 	// in range [0, LATEST], where LATEST = latest block number
 	// when this process started
-	db.put(Key::BlockHeader(block_number), header)
+	header_chain::store_trusted(&db, &header)
 		.wrap_err("Light Client failed to store Block Header")?;
 
 	Ok(Some(confidence))
 }
 
+/// Delays block processing if configured, and processes the block.
+async fn delay_and_process_block(
+	db: impl Database,
+	network_client: &impl network::Client,
+	metrics: &Arc<impl Metrics>,
+	cfg: &LightClientConfig,
+	header: Header,
+	received_at: Instant,
+	state: Arc<Mutex<State>>,
+) -> Result<Option<Confidence>> {
+	if let Some(seconds) = cfg.block_processing_delay.sleep_duration(received_at) {
+		if let Err(error) = metrics
+			.record(MetricValue::BlockProcessingDelay(seconds.as_secs_f64()))
+			.await
+		{
+			error!("Cannot record block processing delay: {}", error);
+		}
+		info!("Sleeping for {seconds:?} seconds");
+		tokio::time::sleep(seconds).await;
+	}
+
+	process_block(db, network_client, metrics, cfg, header, received_at, state).await
+}
+
 /// Runs light client.
 ///
+/// Finalized blocks are sampled concurrently, up to the configured limit,
+/// while verified blocks are emitted in the order they are received.
+///
 /// # Arguments
 ///
 /// * `light_client` - Light client implementation
+/// * `rpc_client` - RPC client used to recover blocks skipped due to the lag
 /// * `cfg` - Light client configuration
 /// * `metrics` - Metrics registry
 /// * `state` - Processed blocks state
@@ -173,49 +330,63 @@ pub async fn process_block(
 pub async fn run(
 	db: impl Database + Clone,
 	network_client: impl network::Client,
+	rpc_client: rpc::Client,
 	cfg: LightClientConfig,
 	metrics: Arc<impl Metrics>,
 	state: Arc<Mutex<State>>,
-	mut channels: ClientChannels,
+	channels: ClientChannels,
 	shutdown: Controller<String>,
 ) {
 	info!("Starting light client...");
 
-	loop {
-		let (header, received_at) = match channels.rpc_event_receiver.recv().await {
-			Ok(event) => match event {
-				Event::HeaderUpdate {
-					header,
-					received_at,
-				} => (header, received_at),
-			},
+	let receiver =
+		RecoveringReceiver::new(channels.rpc_event_receiver, rpc_client, metrics.clone());
+	let shutdown = &shutdown;
+	let headers = stream::unfold(receiver, |mut receiver| async move {
+		match receiver.recv().await {
+			Ok(Event::HeaderUpdate {
+				header,
+				received_at,
+			}) => Some(((header, received_at, receiver.len()), receiver)),
 			Err(error) => {
 				error!("Cannot receive message: {error}");
-				return;
+				let _ = shutdown.trigger_shutdown(format!("Cannot receive message: {error:#}"));
+				None
 			},
-		};
+		}
+	});
 
-		if let Some(seconds) = cfg.block_processing_delay.sleep_duration(received_at) {
-			if let Err(error) = metrics
-				.record(MetricValue::BlockProcessingDelay(seconds.as_secs_f64()))
-				.await
-			{
-				error!("Cannot record block processing delay: {}", error);
+	// Number of blocks which are being processed
+	let in_flight = AtomicUsize::new(0);
+	let (network_client, metrics, cfg, in_flight) = (&network_client, &metrics, &cfg, &in_flight);
+
+	let blocks = headers
+		.map(|(header, received_at, backlog)| {
+			let (db, state) = (db.clone(), state.clone());
+			let depth = backlog + in_flight.fetch_add(1, Ordering::Relaxed) + 1;
+			async move {
+				if let Err(error) = metrics.record(MetricValue::BlockQueueDepth(depth)).await {
+					error!("Cannot record block queue depth: {error}");
+				}
+				let result = delay_and_process_block(
+					db,
+					network_client,
+					metrics,
+					cfg,
+					header.clone(),
+					received_at,
+					state,
+				)
+				.await;
+				(header, result)
 			}
-			info!("Sleeping for {seconds:?} seconds");
-			tokio::time::sleep(seconds).await;
-		}
+		})
+		.buffered(cfg.block_processing_concurrency);
+	let mut blocks = pin!(blocks);
+
+	while let Some((header, process_block_result)) = blocks.next().await {
+		in_flight.fetch_sub(1, Ordering::Relaxed);
 
-		let process_block_result = process_block(
-			db.clone(),
-			&network_client,
-			&metrics,
-			&cfg,
-			header.clone(),
-			received_at,
-			state.clone(),
-		)
-		.await;
 		let confidence = match process_block_result {
 			Ok(confidence) => confidence,
 			Err(error) => {
@@ -225,6 +396,19 @@ pub async fn run(
 			},
 		};
 
+		let lag = {
+			let mut state = state.lock().unwrap();
+			// Blocks are completed in order, so the range doesn't cover blocks which are still being sampled
+			if confidence.is_some() {
+				state.confidence_achieved.set(header.number);
+				state.confidence_failed.remove(header.number);
+			}
+			state.latest.saturating_sub(header.number)
+		};
+		if let Err(error) = metrics.record(MetricValue::BlockLag(lag)).await {
+			error!("Cannot record block lag: {error}");
+		}
+
 		let Ok(client_msg) = types::BlockVerified::try_from((header, confidence)) else {
 			error!("Cannot create message from header");
 			continue;
@@ -241,7 +425,7 @@ pub async fn run(
 
 #[cfg(test)]
 mod tests {
-	use std::time::Duration;
+	use std::{collections::HashSet, time::Duration};
 
 	use super::*;
 	use crate::{
@@ -334,6 +518,7 @@ mod tests {
 					fetched.len(),
 					Duration::from_secs(0),
 					None,
+					0,
 				);
 				Box::pin(async move { Ok((fetched, unfetched, stats)) })
 			});
@@ -354,4 +539,127 @@ mod tests {
 		.await
 		.unwrap();
 	}
+
+	#[tokio::test]
+	async fn test_fetch_verified_adaptive_samples_additional_cells() {
+		let mut mock_network_client = network::MockClient::new();
+		let cfg = LightClientConfig::from(&RuntimeConfig::default());
+		let dimensions = Dimensions::new(16, 32).unwrap();
+		let round = Arc::new(AtomicUsize::new(0));
+		mock_network_client
+			.expect_fetch_verified()
+			.returning(move |_, _, _, _, positions| {
+				// Two cells are missing in the first round only
+				let missing = match round.fetch_add(1, Ordering::Relaxed) {
+					0 => 2,
+					_ => 0,
+				};
+				let (unfetched, fetched) = positions.split_at(missing);
+				let fetched = fetched
+					.iter()
+					.map(|&position| Cell {
+						position,
+						content: [0; 80],
+					})
+					.collect::<Vec<_>>();
+				let unfetched = unfetched.to_vec();
+				let stats = network::FetchStats::new(
+					positions.len(),
+					fetched.len(),
+					Duration::from_secs(0),
+					None,
+					0,
+				);
+				Box::pin(async move { Ok((fetched, unfetched, stats)) })
+			});
+
+		let (positions, fetched, unfetched, stats) = fetch_verified_adaptive(
+			&mock_network_client,
+			&*cfg.sampling_strategy,
+			cfg.max_sampling_rounds,
+			cfg.max_sampled_cells,
+			1,
+			H256::default(),
+			dimensions,
+			&[],
+			10,
+		)
+		.await
+		.unwrap();
+
+		assert_eq!(positions.len(), 12);
+		assert_eq!(positions.iter().collect::<HashSet<_>>().len(), 12);
+		assert_eq!(fetched.len(), 10);
+		assert_eq!(unfetched.len(), 2);
+		assert_eq!(stats.rounds, 2);
+		assert_eq!(stats.additional_cells, 2);
+	}
+
+	#[tokio::test]
+	async fn test_cells_with_invalid_proofs_are_replaced() {
+		let mut mock_network_client = network::MockClient::new();
+		let cfg = LightClientConfig::from(&RuntimeConfig::default());
+		let dimensions = Dimensions::new(16, 32).unwrap();
+		let round = Arc::new(AtomicUsize::new(0));
+		mock_network_client
+			.expect_fetch_verified()
+			.returning(move |_, _, _, _, positions| {
+				// Two cells fail proof verification in the first round only
+				let invalid = match round.fetch_add(1, Ordering::Relaxed) {
+					0 => 2,
+					_ => 0,
+				};
+				let (unfetched, fetched) = positions.split_at(invalid);
+				let fetched = fetched
+					.iter()
+					.map(|&position| Cell {
+						position,
+						content: [0; 80],
+					})
+					.collect::<Vec<_>>();
+				let unfetched = unfetched.to_vec();
+				let stats = network::FetchStats::new(
+					positions.len(),
+					fetched.len(),
+					Duration::from_secs(0),
+					None,
+					invalid,
+				);
+				Box::pin(async move { Ok((fetched, unfetched, stats)) })
+			});
+
+		let (positions, fetched, unfetched, stats) = fetch_verified_adaptive(
+			&mock_network_client,
+			&*cfg.sampling_strategy,
+			cfg.max_sampling_rounds,
+			cfg.max_sampled_cells,
+			1,
+			H256::default(),
+			dimensions,
+			&[],
+			10,
+		)
+		.await
+		.unwrap();
+
+		let result = SamplingResult {
+			requested: positions.len() as u32,
+			verified: fetched.len() as u32,
+			invalid_proofs: stats.invalid_proofs as u32,
+		};
+		// Each invalid cell is replaced by two cells, since it cancels one of the verified cells
+		assert_eq!(positions.len(), 14);
+		assert_eq!(fetched.len(), 12);
+		assert_eq!(unfetched.len(), 2);
+
+		let confidence = confidence::calculate(&*cfg.sampling_strategy, dimensions, result);
+		assert!(confidence.invalid_proof);
+		assert_eq!(confidence.result.invalid_proofs, 2);
+		let required = confidence::required(&*cfg.sampling_strategy, dimensions, cfg.confidence);
+		assert!(confidence.is_achieved(required));
+		assert_eq!(
+			confidence.value,
+			cfg.sampling_strategy.confidence(dimensions, 10)
+		);
+	}
 }
@@ -1,12 +1,12 @@
 use color_eyre::{eyre::WrapErr, Result};
 use std::sync::Arc;
-use tokio::sync::broadcast;
-use tracing::{debug, error, info};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{debug, error, info, warn};
 
 use crate::{
 	network::p2p::Client as P2pClient,
 	shutdown::Controller,
-	telemetry::{MetricValue, Metrics},
+	telemetry::{MetricCounter, MetricValue, Metrics},
 	types::BlockVerified,
 };
 
@@ -18,13 +18,31 @@ pub struct StaticConfigParams {
 	pub pruning_interval: u32,
 }
 
+/// Checks if pruning is due for the given block,
+/// including the blocks skipped since the last processed block.
+/// Pruning is not due for the blocks older than the last one, e.g. blocks which achieved confidence on retry.
+fn is_pruning_due(
+	last_block_number: Option<u32>,
+	block_number: u32,
+	pruning_interval: u32,
+) -> bool {
+	match last_block_number {
+		Some(last) if last < block_number => {
+			block_number / pruning_interval > last / pruning_interval
+		},
+		Some(_) => false,
+		None => block_number % pruning_interval == 0,
+	}
+}
+
 pub async fn process_block(
 	block_number: u32,
+	prune: bool,
 	p2p_client: &P2pClient,
 	static_config_params: StaticConfigParams,
 	metrics: &Arc<impl Metrics>,
 ) -> Result<()> {
-	if block_number % static_config_params.pruning_interval == 0 {
+	if prune {
 		info!(block_number, "Pruning...");
 		match p2p_client.prune_expired_records().await {
 			Ok(pruned) => info!(block_number, pruned, "Pruning finished"),
@@ -91,10 +109,33 @@ pub async fn run(
 ) {
 	info!("Starting maintenance...");
 
+	// Maintenance needs only block numbers, so skipped blocks are not recovered,
+	// pruning due in the skipped blocks is done on the next received block.
+	let mut last_block_number = None;
+
 	loop {
 		let result = match block_receiver.recv().await {
 			Ok(block) => {
-				process_block(block.block_num, &p2p_client, static_config_params, &metrics).await
+				let block_number = block.block_num;
+				let prune = is_pruning_due(
+					last_block_number,
+					block_number,
+					static_config_params.pruning_interval,
+				);
+				last_block_number = last_block_number.max(Some(block_number));
+				process_block(
+					block_number,
+					prune,
+					&p2p_client,
+					static_config_params,
+					&metrics,
+				)
+				.await
+			},
+			Err(RecvError::Lagged(skipped)) => {
+				warn!(skipped, "Maintenance lagged behind the verified blocks");
+				metrics.count(MetricCounter::ReceiverLagged).await;
+				continue;
 			},
 			Err(error) => Err(error.into()),
 		};
@@ -105,3 +146,20 @@ pub async fn run(
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::is_pruning_due;
+
+	#[test]
+	fn pruning_is_due_for_skipped_blocks() {
+		assert!(is_pruning_due(None, 20, 10));
+		assert!(!is_pruning_due(None, 21, 10));
+		assert!(!is_pruning_due(Some(20), 21, 10));
+		// Block 30 is skipped
+		assert!(is_pruning_due(Some(28), 31, 10));
+		assert!(!is_pruning_due(Some(31), 31, 10));
+		// Retried block older than the last one
+		assert!(!is_pruning_due(Some(31), 20, 10));
+	}
+}
@@ -6,13 +6,19 @@ use kate_recovery::{
 	data::Cell,
 	matrix::{Dimensions, Position},
 };
+use libp2p::PeerId;
 use mockall::automock;
 use sp_core::H256;
-use std::{sync::Arc, time::Duration};
-use tokio::time::Instant;
+use std::{collections::HashSet, sync::Arc, time::Duration};
+use tokio::{sync::broadcast, time::Instant};
 use tracing::{debug, info};
 
-use crate::proof;
+use crate::{
+	cell_cache,
+	data::Database,
+	invalid_proof::{CellSource, InvalidProof},
+	proof,
+};
 
 pub mod p2p;
 pub mod rpc;
@@ -36,6 +42,14 @@ pub struct FetchStats {
 	pub dht_fetch_duration: f64,
 	pub rpc_fetched: Option<f64>,
 	pub rpc_fetch_duration: Option<f64>,
+	/// Number of fetched cells which failed proof verification, from both DHT and RPC
+	pub invalid_proofs: usize,
+	/// Number of sampling rounds
+	pub rounds: u32,
+	/// Number of cells sampled in addition to the cells of the first round
+	pub additional_cells: usize,
+	/// Number of cells found in the local cell cache
+	pub cached: usize,
 }
 
 type RPCFetchStats = (usize, Duration);
@@ -46,6 +60,7 @@ impl FetchStats {
 		dht_fetched: usize,
 		dht_fetch_duration: Duration,
 		rpc_fetch_stats: Option<RPCFetchStats>,
+		invalid_proofs: usize,
 	) -> Self {
 		FetchStats {
 			dht_fetched: dht_fetched as f64,
@@ -53,33 +68,103 @@ impl FetchStats {
 			dht_fetch_duration: dht_fetch_duration.as_secs_f64(),
 			rpc_fetched: rpc_fetch_stats.map(|(rpc_fetched, _)| rpc_fetched as f64),
 			rpc_fetch_duration: rpc_fetch_stats.map(|(_, duration)| duration.as_secs_f64()),
+			invalid_proofs,
+			rounds: 1,
+			additional_cells: 0,
+			cached: 0,
 		}
 	}
+
+	/// Adds stats of the next sampling round, where `total` is the number of cells requested in all rounds
+	pub fn add_round(&mut self, round: FetchStats, total: usize, additional_cells: usize) {
+		self.dht_fetched += round.dht_fetched;
+		self.dht_fetched_percentage = self.dht_fetched / total as f64;
+		self.dht_fetch_duration += round.dht_fetch_duration;
+		self.rpc_fetched = match (self.rpc_fetched, round.rpc_fetched) {
+			(None, None) => None,
+			(fetched, round_fetched) => Some(fetched.unwrap_or(0.0) + round_fetched.unwrap_or(0.0)),
+		};
+		self.rpc_fetch_duration = match (self.rpc_fetch_duration, round.rpc_fetch_duration) {
+			(None, None) => None,
+			(duration, round_duration) => {
+				Some(duration.unwrap_or(0.0) + round_duration.unwrap_or(0.0))
+			},
+		};
+		self.invalid_proofs += round.invalid_proofs;
+		self.rounds += 1;
+		self.additional_cells += additional_cells;
+		self.cached += round.cached;
+	}
 }
 
-struct DHTWithRPCFallbackClient {
+struct DHTWithRPCFallbackClient<T: Database> {
 	p2p_client: p2p::Client,
 	rpc_client: rpc::Client,
 	pp: Arc<PublicParameters>,
 	disable_rpc: bool,
+	invalid_proof_sender: broadcast::Sender<InvalidProof>,
+	db: T,
+	cell_cache_blocks: u32,
 }
 
 type Commitments = [[u8; config::COMMITMENT_SIZE]];
 
-impl DHTWithRPCFallbackClient {
+/// Verified cells, unfetched positions, fetch duration and number of cells with invalid proofs
+type VerifiedCells = (Vec<Cell>, Vec<Position>, Duration, usize);
+
+impl<T: Database> DHTWithRPCFallbackClient<T> {
+	/// Sends invalid proof event for each fetched cell which failed proof verification
+	fn send_invalid_proofs<'a>(
+		&self,
+		block_number: u32,
+		cells: impl Iterator<Item = (&'a Cell, CellSource)>,
+	) {
+		for (cell, source) in cells {
+			let invalid_proof = InvalidProof::new(block_number, cell, source);
+			if let Err(error) = self.invalid_proof_sender.send(invalid_proof) {
+				debug!("Cannot send invalid proof: {error}");
+			}
+		}
+	}
+
+	/// Gets cached cells, returns cached cells and positions which are not cached
+	fn cached_cells(
+		&self,
+		block_number: u32,
+		positions: &[Position],
+	) -> (Vec<Cell>, Vec<Position>) {
+		if self.cell_cache_blocks == 0 {
+			return (vec![], positions.to_vec());
+		}
+		cell_cache::get(&self.db, block_number, positions).unwrap_or_else(|error| {
+			debug!(block_number, "Cannot get cached cells: {error:#}");
+			(vec![], positions.to_vec())
+		})
+	}
+
+	fn cache_cells(&self, block_number: u32, cells: &[Cell]) {
+		if let Err(error) = cell_cache::put(&self.db, block_number, cells, self.cell_cache_blocks) {
+			debug!(block_number, "Cannot cache cells: {error:#}");
+		}
+	}
+
 	async fn fetch_verified_from_dht(
 		&self,
 		block_number: u32,
 		dimensions: Dimensions,
 		commitments: &Commitments,
 		positions: &[Position],
-	) -> Result<(Vec<Cell>, Vec<Position>, Duration)> {
+	) -> Result<VerifiedCells> {
 		let begin = Instant::now();
 
-		let (mut dht_fetched, mut unfetched) = self
+		let (dht_fetched_with_peers, mut unfetched) = self
 			.p2p_client
-			.fetch_cells_from_dht(block_number, positions)
+			.fetch_cells_with_peers_from_dht(block_number, positions)
 			.await;
+		let mut dht_fetched = dht_fetched_with_peers
+			.iter()
+			.map(|(cell, _)| cell.clone())
+			.collect::<Vec<_>>();
 
 		let fetch_elapsed = begin.elapsed();
 
@@ -103,10 +188,36 @@ impl DHTWithRPCFallbackClient {
 			"Cells fetched from DHT"
 		);
 
+		let invalid = dht_fetched_with_peers
+			.iter()
+			.filter(|(cell, _)| unverified.contains(&cell.position))
+			.map(|(cell, peer_id)| {
+				let peer_id = peer_id.as_ref().map(ToString::to_string);
+				(cell, CellSource::Dht { peer_id })
+			});
+		self.send_invalid_proofs(block_number, invalid);
+
+		// Peers which served the cells are rewarded or penalized, depending on the proof verification
+		let (valid_sources, invalid_sources): (Vec<_>, Vec<_>) = dht_fetched_with_peers
+			.iter()
+			.filter_map(|(cell, peer_id)| peer_id.map(|peer_id| (cell.position, peer_id)))
+			.partition(|(position, _)| verified.contains(position));
+		let peer_ids = |sources: Vec<(Position, PeerId)>| -> HashSet<PeerId> {
+			sources.into_iter().map(|(_, id)| id).collect()
+		};
+		if let Err(error) = self.p2p_client.report_cell_sources(
+			block_number,
+			peer_ids(valid_sources),
+			peer_ids(invalid_sources),
+		) {
+			debug!("Cannot report DHT cell sources: {error}");
+		}
+
 		dht_fetched.retain(|cell| verified.contains(&cell.position));
+		let invalid_proofs = unverified.len();
 		unfetched.append(&mut unverified);
 
-		Ok((dht_fetched, unfetched, fetch_elapsed))
+		Ok((dht_fetched, unfetched, fetch_elapsed, invalid_proofs))
 	}
 
 	async fn fetch_verified_from_rpc(
@@ -116,7 +227,7 @@ impl DHTWithRPCFallbackClient {
 		dimensions: Dimensions,
 		commitments: &Commitments,
 		positions: &[Position],
-	) -> Result<(Vec<Cell>, Vec<Position>, Duration)> {
+	) -> Result<VerifiedCells> {
 		let begin = Instant::now();
 
 		let mut fetched = self
@@ -146,32 +257,61 @@ impl DHTWithRPCFallbackClient {
 			"Cells fetched from RPC"
 		);
 
+		let host = self.rpc_client.connected_host();
+		let invalid = fetched
+			.iter()
+			.filter(|cell| unverified.contains(&cell.position))
+			.map(|cell| (cell, CellSource::Rpc { host: host.clone() }));
+		self.send_invalid_proofs(block_number, invalid);
+
 		fetched.retain(|cell| verified.contains(&cell.position));
-		Ok((fetched, unverified, fetch_elapsed))
+		let invalid_proofs = unverified.len();
+		Ok((fetched, unverified, fetch_elapsed, invalid_proofs))
 	}
 }
 
 #[async_trait]
-impl Client for DHTWithRPCFallbackClient {
+impl<T: Database + Send + Sync> Client for DHTWithRPCFallbackClient<T> {
 	async fn fetch_verified(
 		&self,
 		block_number: u32,
 		block_hash: H256,
 		dimensions: Dimensions,
 		commitments: &Commitments,
-		positions: &[Position],
+		all_positions: &[Position],
 	) -> Result<(Vec<Cell>, Vec<Position>, FetchStats)> {
-		let (dht_fetched, unfetched, dht_fetch_duration) = self
-			.fetch_verified_from_dht(block_number, dimensions, commitments, positions)
+		// Cached cells are already verified, so only the remaining positions are fetched
+		let (cached, positions) = self.cached_cells(block_number, all_positions);
+		let cached_count = cached.len();
+
+		if positions.is_empty() {
+			debug!(block_number, cached = cached_count, "All cells are cached");
+			let mut stats = FetchStats::new(all_positions.len(), 0, Duration::ZERO, None, 0);
+			stats.cached = cached_count;
+			return Ok((cached, vec![], stats));
+		}
+
+		let (dht_fetched, unfetched, dht_fetch_duration, dht_invalid_proofs) = self
+			.fetch_verified_from_dht(block_number, dimensions, commitments, &positions)
 			.await?;
 
 		if self.disable_rpc {
-			let stats =
-				FetchStats::new(positions.len(), dht_fetched.len(), dht_fetch_duration, None);
-			return Ok((dht_fetched, unfetched, stats));
+			self.cache_cells(block_number, &dht_fetched);
+			let mut stats = FetchStats::new(
+				all_positions.len(),
+				dht_fetched.len(),
+				dht_fetch_duration,
+				None,
+				dht_invalid_proofs,
+			);
+			stats.cached = cached_count;
+			let mut fetched = cached;
+			fetched.extend(dht_fetched);
+			return Ok((fetched, unfetched, stats));
 		};
 
-		let (rpc_fetched, unfetched, rpc_fetch_duration) = self
+		// Cells with invalid proofs from DHT are fetched again from RPC
+		let (rpc_fetched, unfetched, rpc_fetch_duration, rpc_invalid_proofs) = self
 			.fetch_verified_from_rpc(
 				block_number,
 				block_hash,
@@ -189,14 +329,19 @@ impl Client for DHTWithRPCFallbackClient {
 			debug!("Error inserting cells into DHT: {error}");
 		}
 
-		let stats = FetchStats::new(
-			positions.len(),
+		let mut stats = FetchStats::new(
+			all_positions.len(),
 			dht_fetched.len(),
 			dht_fetch_duration,
 			Some((rpc_fetched.len(), rpc_fetch_duration)),
+			dht_invalid_proofs + rpc_invalid_proofs,
 		);
+		stats.cached = cached_count;
+
+		self.cache_cells(block_number, &dht_fetched);
+		self.cache_cells(block_number, &rpc_fetched);
 
-		let mut fetched = vec![];
+		let mut fetched = cached;
 		fetched.extend(dht_fetched);
 		fetched.extend(rpc_fetched);
 
@@ -209,11 +354,17 @@ pub fn new(
 	rpc_client: rpc::Client,
 	pp: Arc<PublicParameters>,
 	disable_rpc: bool,
+	invalid_proof_sender: broadcast::Sender<InvalidProof>,
+	db: impl Database + Send + Sync,
+	cell_cache_blocks: u32,
 ) -> impl Client {
 	DHTWithRPCFallbackClient {
 		p2p_client,
 		rpc_client,
 		pp,
 		disable_rpc,
+		invalid_proof_sender,
+		db,
+		cell_cache_blocks,
 	}
 }
@@ -17,16 +17,18 @@ use tracing::info;
 
 #[cfg(feature = "network-analysis")]
 pub mod analyzer;
+pub mod blocklist;
 mod client;
 mod event_loop;
 mod kad_mem_store;
+mod reputation;
 
 use crate::types::{LibP2PConfig, SecretKey};
-pub use client::Client;
+pub use client::{Client, DHTStats, LocalInfo, PeerInfo};
 pub use event_loop::EventLoop;
 pub use kad_mem_store::MemoryStoreConfig;
 
-use self::{client::BlockStat, kad_mem_store::MemoryStore};
+use self::{client::BlockStat, kad_mem_store::MemoryStore, reputation::PeerReputation};
 use libp2p_allow_block_list as allow_block_list;
 
 #[derive(Debug)]
@@ -42,6 +44,9 @@ pub struct EventLoopEntries<'a> {
 	pending_swarm_events: &'a mut HashMap<PeerId, oneshot::Sender<Result<()>>>,
 	/// <block_num, (total_cells, result_cell_counter, time_stat)>
 	active_blocks: &'a mut HashMap<u32, BlockStat>,
+	/// Reputation of the peers which served DHT records
+	reputation: &'a mut PeerReputation,
+	connected_peers: &'a mut HashMap<PeerId, PeerInfo>,
 }
 
 impl<'a> EventLoopEntries<'a> {
@@ -50,12 +55,16 @@ impl<'a> EventLoopEntries<'a> {
 		pending_kad_queries: &'a mut HashMap<QueryId, QueryChannel>,
 		pending_swarm_events: &'a mut HashMap<PeerId, oneshot::Sender<Result<()>>>,
 		active_blocks: &'a mut HashMap<u32, BlockStat>,
+		reputation: &'a mut PeerReputation,
+		connected_peers: &'a mut HashMap<PeerId, PeerInfo>,
 	) -> Self {
 		Self {
 			swarm,
 			pending_kad_queries,
 			pending_swarm_events,
 			active_blocks,
+			reputation,
+			connected_peers,
 		}
 	}
 
@@ -78,6 +87,14 @@ impl<'a> EventLoopEntries<'a> {
 	pub fn swarm(&mut self) -> &mut Swarm<Behaviour> {
 		self.swarm
 	}
+
+	pub fn reputation(&mut self) -> &mut PeerReputation {
+		self.reputation
+	}
+
+	pub fn connected_peers(&mut self) -> &mut HashMap<PeerId, PeerInfo> {
+		self.connected_peers
+	}
 }
 
 pub trait Command {
@@ -0,0 +1,92 @@
+//! Persisted list of manually blocked peers.
+//!
+//! Peers blocked by the operator via API are stored in the database, and blocked again on startup,
+//! along with the peers from the configuration. Peers blocked automatically, based on reputation, are not persisted.
+
+use color_eyre::{eyre::WrapErr, Result};
+use libp2p::PeerId;
+use std::{str::FromStr, sync::Mutex};
+
+use super::Client;
+use crate::data::{Database, Key};
+
+/// Gets stored blocked peers.
+pub fn list(db: &impl Database) -> Result<Vec<PeerId>> {
+	db.get::<Vec<String>>(Key::BlockedPeers)?
+		.unwrap_or_default()
+		.iter()
+		.map(|peer_id| PeerId::from_str(peer_id).wrap_err("Invalid stored peer ID"))
+		.collect()
+}
+
+fn store(db: &impl Database, peer_ids: &[PeerId]) -> Result<()> {
+	let peer_ids = peer_ids.iter().map(ToString::to_string).collect::<Vec<_>>();
+	db.put(Key::BlockedPeers, peer_ids)
+}
+
+/// Adds peer to the stored blocked peers.
+/// Updates sharing the lock are serialized, since the stored peers are read, modified and written back.
+pub fn add(db: &impl Database, update_lock: &Mutex<()>, peer_id: PeerId) -> Result<()> {
+	let _lock = update_lock.lock().expect("Lock should be acquired");
+	let mut peer_ids = list(db)?;
+	if !peer_ids.contains(&peer_id) {
+		peer_ids.push(peer_id);
+	}
+	store(db, &peer_ids)
+}
+
+/// Removes peer from the stored blocked peers.
+pub fn remove(db: &impl Database, update_lock: &Mutex<()>, peer_id: &PeerId) -> Result<()> {
+	let _lock = update_lock.lock().expect("Lock should be acquired");
+	let mut peer_ids = list(db)?;
+	peer_ids.retain(|stored| stored != peer_id);
+	store(db, &peer_ids)
+}
+
+/// Blocks configured and stored peers.
+/// Configured peers are not stored, so they are unblocked once removed from the configuration.
+pub async fn restore(client: &Client, db: &impl Database, configured: &[PeerId]) -> Result<()> {
+	let stored = list(db)?;
+	for &peer_id in configured.iter().chain(stored.iter()) {
+		client.block_peer(peer_id).await?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{add, list, remove};
+	use crate::data::mem_db::MemoryDB;
+	use libp2p::PeerId;
+	use std::sync::Mutex;
+
+	#[test]
+	fn blocked_peers_are_stored() {
+		let db = MemoryDB::default();
+		let lock = Mutex::default();
+		assert!(list(&db).unwrap().is_empty());
+
+		let (first, second) = (PeerId::random(), PeerId::random());
+		add(&db, &lock, first).unwrap();
+		add(&db, &lock, second).unwrap();
+		add(&db, &lock, first).unwrap();
+		assert_eq!(list(&db).unwrap(), vec![first, second]);
+
+		remove(&db, &lock, &first).unwrap();
+		assert_eq!(list(&db).unwrap(), vec![second]);
+	}
+
+	#[test]
+	fn concurrent_updates_are_not_lost() {
+		let db = MemoryDB::default();
+		let lock = Mutex::default();
+		let peer_ids = (0..8).map(|_| PeerId::random()).collect::<Vec<_>>();
+		std::thread::scope(|scope| {
+			for &peer_id in &peer_ids {
+				let (db, lock) = (db.clone(), &lock);
+				scope.spawn(move || add(&db, lock, peer_id).unwrap());
+			}
+		});
+		assert_eq!(list(&db).unwrap().len(), peer_ids.len());
+	}
+}
@@ -1,4 +1,5 @@
 use super::{Command, CommandSender, EventLoopEntries, QueryChannel, SendableCommand};
+use crate::types::KademliaMode;
 use color_eyre::{
 	eyre::{eyre, WrapErr},
 	Report, Result,
@@ -10,17 +11,18 @@ use kate_recovery::{
 	matrix::{Dimensions, Position, RowIndex},
 };
 use libp2p::{
+	autonat::NatStatus,
 	kad::{PeerRecord, Quorum, Record, RecordKey},
 	swarm::dial_opts::DialOpts,
 	Multiaddr, PeerId,
 };
 use std::str;
 use std::{
-	collections::HashMap,
+	collections::{HashMap, HashSet},
 	time::{Duration, Instant},
 };
 use tokio::sync::oneshot;
-use tracing::{debug, trace};
+use tracing::{debug, trace, warn};
 
 #[derive(Clone)]
 pub struct Client {
@@ -80,6 +82,40 @@ impl BlockStat {
 	}
 }
 
+/// Connected peer, with the data observed on established connections
+#[derive(Clone, Debug, Default)]
+pub struct PeerInfo {
+	/// Agent version received via identify protocol
+	pub agent_version: Option<String>,
+	/// Latest round-trip time measured via ping protocol
+	pub ping_rtt: Option<Duration>,
+	/// True if the connection is dialed by the local node
+	pub is_dialer: bool,
+}
+
+/// Local node info
+#[derive(Clone, Debug)]
+pub struct LocalInfo {
+	pub peer_id: PeerId,
+	pub listeners: Vec<Multiaddr>,
+	pub external_addresses: Vec<Multiaddr>,
+	pub nat_status: NatStatus,
+	pub kademlia_mode: KademliaMode,
+}
+
+/// DHT statistics
+#[derive(Clone, Debug)]
+pub struct DHTStats {
+	/// Number of records in the local store
+	pub records: usize,
+	/// Number of peers in the routing table
+	pub routing_table_peers: usize,
+	/// Number of non-empty buckets in the routing table
+	pub routing_table_buckets: usize,
+	/// Number of blocks with cells being put into the DHT
+	pub active_blocks: usize,
+}
+
 struct PruneExpiredRecords {
 	now: Instant,
 	response_sender: Option<oneshot::Sender<Result<usize>>>,
@@ -485,6 +521,223 @@ impl Command for AddAutonatServer {
 	}
 }
 
+struct GetLocalInfo {
+	response_sender: Option<oneshot::Sender<Result<LocalInfo>>>,
+}
+
+impl Command for GetLocalInfo {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		let swarm = entries.swarm();
+		let local_info = LocalInfo {
+			peer_id: *swarm.local_peer_id(),
+			listeners: swarm.listeners().cloned().collect(),
+			external_addresses: swarm.external_addresses().cloned().collect(),
+			nat_status: swarm.behaviour().auto_nat.nat_status(),
+			kademlia_mode: swarm.behaviour().kademlia.mode().into(),
+		};
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		self.response_sender
+			.take()
+			.unwrap()
+			.send(Ok(local_info))
+			.expect("GetLocalInfo receiver dropped");
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for GetLocalInfo command");
+	}
+}
+
+struct ListPeers {
+	response_sender: Option<oneshot::Sender<Result<Vec<(PeerId, PeerInfo)>>>>,
+}
+
+impl Command for ListPeers {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		let peers = entries
+			.connected_peers()
+			.iter()
+			.map(|(peer_id, info)| (*peer_id, info.clone()))
+			.collect::<Vec<_>>();
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		self.response_sender
+			.take()
+			.unwrap()
+			.send(Ok(peers))
+			.expect("ListPeers receiver dropped");
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for ListPeers command");
+	}
+}
+
+struct GetDHTStats {
+	response_sender: Option<oneshot::Sender<Result<DHTStats>>>,
+}
+
+impl Command for GetDHTStats {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		let active_blocks = entries.active_blocks.len();
+		let kademlia = &mut entries.behavior_mut().kademlia;
+		let records = kademlia.store_mut().records_len();
+		let (routing_table_peers, routing_table_buckets) = kademlia
+			.kbuckets()
+			.map(|bucket| bucket.num_entries())
+			.filter(|&count| count > 0)
+			.fold((0, 0), |(peers, buckets), count| {
+				(peers + count, buckets + 1)
+			});
+
+		let stats = DHTStats {
+			records,
+			routing_table_peers,
+			routing_table_buckets,
+			active_blocks,
+		};
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		self.response_sender
+			.take()
+			.unwrap()
+			.send(Ok(stats))
+			.expect("GetDHTStats receiver dropped");
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for GetDHTStats command");
+	}
+}
+
+struct ReportCellSources {
+	block_number: u32,
+	valid: HashSet<PeerId>,
+	invalid: HashSet<PeerId>,
+}
+
+impl Command for ReportCellSources {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		for &peer_id in &self.valid {
+			entries.reputation().reward(peer_id, self.block_number);
+		}
+
+		for &peer_id in &self.invalid {
+			if !entries.reputation().penalize(peer_id, self.block_number) {
+				continue;
+			}
+			let score = entries.reputation().score(&peer_id);
+			warn!(%peer_id, score, "Blocking peer which repeatedly served cells with invalid proofs");
+			entries.behavior_mut().kademlia.remove_peer(&peer_id);
+			entries.behavior_mut().blocked_peers.block_peer(peer_id);
+		}
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for ReportCellSources command");
+	}
+}
+
+struct ListBlockedPeers {
+	response_sender: Option<oneshot::Sender<Result<Vec<String>>>>,
+}
+
+impl Command for ListBlockedPeers {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		let blocked_peers = entries
+			.behavior_mut()
+			.blocked_peers
+			.blocked_peers()
+			.iter()
+			.map(|peer_id| peer_id.to_string())
+			.collect::<Vec<_>>();
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		self.response_sender
+			.take()
+			.unwrap()
+			.send(Ok(blocked_peers))
+			.expect("ListBlockedPeers receiver dropped");
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for ListBlockedPeers command");
+	}
+}
+
+struct BlockPeer {
+	peer_id: PeerId,
+	response_sender: Option<oneshot::Sender<Result<()>>>,
+}
+
+impl Command for BlockPeer {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		entries.behavior_mut().kademlia.remove_peer(&self.peer_id);
+		entries
+			.behavior_mut()
+			.blocked_peers
+			.block_peer(self.peer_id);
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		self.response_sender
+			.take()
+			.unwrap()
+			.send(Ok(()))
+			.expect("BlockPeer receiver dropped");
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for BlockPeer command");
+	}
+}
+
+struct UnblockPeer {
+	peer_id: PeerId,
+	response_sender: Option<oneshot::Sender<Result<()>>>,
+}
+
+impl Command for UnblockPeer {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		entries
+			.behavior_mut()
+			.blocked_peers
+			.unblock_peer(self.peer_id);
+		entries.reputation().reset(&self.peer_id);
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		self.response_sender
+			.take()
+			.unwrap()
+			.send(Ok(()))
+			.expect("UnblockPeer receiver dropped");
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for UnblockPeer command");
+	}
+}
+
 impl Client {
 	pub fn new(sender: CommandSender, dht_parallelization_limit: usize, ttl: u64) -> Self {
 		Self {
@@ -638,6 +891,82 @@ impl Client {
 		.await
 	}
 
+	pub async fn get_local_info(&self) -> Result<LocalInfo> {
+		self.execute_sync(|response_sender| {
+			Box::new(GetLocalInfo {
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	/// Lists connected peers, along with the data observed on established connections
+	pub async fn list_peers(&self) -> Result<Vec<(PeerId, PeerInfo)>> {
+		self.execute_sync(|response_sender| {
+			Box::new(ListPeers {
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	pub async fn get_dht_stats(&self) -> Result<DHTStats> {
+		self.execute_sync(|response_sender| {
+			Box::new(GetDHTStats {
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	/// Updates reputation of the peers which served DHT cells of the block, given the proof verification result.
+	/// Each peer is rewarded or penalized once per block, regardless of the number of served cells and reports.
+	/// Peers which served cells with invalid proofs in several blocks are blocked.
+	/// Report is applied in the event loop without waiting for the result.
+	pub fn report_cell_sources(
+		&self,
+		block_number: u32,
+		valid: HashSet<PeerId>,
+		invalid: HashSet<PeerId>,
+	) -> Result<()> {
+		self.command_sender
+			.send(Box::new(ReportCellSources {
+				block_number,
+				valid,
+				invalid,
+			}))
+			.context("receiver should not be dropped")
+	}
+
+	pub async fn list_blocked_peers(&self) -> Result<Vec<String>> {
+		self.execute_sync(|response_sender| {
+			Box::new(ListBlockedPeers {
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	pub async fn block_peer(&self, peer_id: PeerId) -> Result<()> {
+		self.execute_sync(|response_sender| {
+			Box::new(BlockPeer {
+				peer_id,
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	pub async fn unblock_peer(&self, peer_id: PeerId) -> Result<()> {
+		self.execute_sync(|response_sender| {
+			Box::new(UnblockPeer {
+				peer_id,
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
 	pub async fn prune_expired_records(&self) -> Result<usize> {
 		self.execute_sync(|response_sender| {
 			Box::new(PruneExpiredRecords {
@@ -650,7 +979,12 @@ impl Client {
 
 	// Since callers ignores DHT errors, debug logs are used to observe DHT behavior.
 	// Return type assumes that cell is not found in case when error is present.
-	async fn fetch_cell_from_dht(&self, block_number: u32, position: Position) -> Option<Cell> {
+	// Along with the cell, peer which provided the record is returned, if known.
+	async fn fetch_cell_from_dht(
+		&self,
+		block_number: u32,
+		position: Position,
+	) -> Option<(Cell, Option<PeerId>)> {
 		let reference = position.reference(block_number);
 		let record_key = RecordKey::from(reference.as_bytes().to_vec());
 
@@ -668,7 +1002,7 @@ impl Client {
 					return None;
 				};
 
-				Some(Cell { position, content })
+				Some((Cell { position, content }, peer_record.peer))
 			},
 			Err(error) => {
 				trace!("Cell {reference} not found in the DHT: {error}");
@@ -709,7 +1043,26 @@ impl Client {
 		block_number: u32,
 		positions: &[Position],
 	) -> (Vec<Cell>, Vec<Position>) {
-		let mut cells = Vec::<Option<Cell>>::with_capacity(positions.len());
+		let (fetched, unfetched) = self
+			.fetch_cells_with_peers_from_dht(block_number, positions)
+			.await;
+		let fetched = fetched.into_iter().map(|(cell, _)| cell).collect();
+		(fetched, unfetched)
+	}
+
+	/// Fetches cells from DHT, along with the peers which provided them, if known.
+	/// Returns fetched cells with peers and unfetched positions (so we can try RPC fetch).
+	///
+	/// # Arguments
+	///
+	/// * `block_number` - Block number
+	/// * `positions` - Cell positions to fetch
+	pub async fn fetch_cells_with_peers_from_dht(
+		&self,
+		block_number: u32,
+		positions: &[Position],
+	) -> (Vec<(Cell, Option<PeerId>)>, Vec<Position>) {
+		let mut cells = Vec::<Option<(Cell, Option<PeerId>)>>::with_capacity(positions.len());
 
 		for positions in positions.chunks(self.dht_parallelization_limit) {
 			let fetch = |&position| self.fetch_cell_from_dht(block_number, position);
@@ -33,8 +33,10 @@ use crate::{
 };
 
 use super::{
-	build_swarm, client::BlockStat, Behaviour, BehaviourEvent, CommandReceiver, EventLoopEntries,
-	QueryChannel, SendableCommand,
+	build_swarm,
+	client::{BlockStat, PeerInfo},
+	reputation::PeerReputation,
+	Behaviour, BehaviourEvent, CommandReceiver, EventLoopEntries, QueryChannel, SendableCommand,
 };
 
 // RelayState keeps track of all things relay related
@@ -93,6 +95,10 @@ pub struct EventLoop {
 	bootstrap: BootstrapState,
 	/// Blocks we monitor for PUT success rate
 	active_blocks: HashMap<u32, BlockStat>,
+	/// Reputation of the peers which served DHT records
+	reputation: PeerReputation,
+	/// Connected peers, with the data observed on established connections
+	connected_peers: HashMap<PeerId, PeerInfo>,
 	shutdown: Controller<String>,
 
 	event_loop_config: EventLoopConfig,
@@ -152,6 +158,8 @@ impl EventLoop {
 				timer: interval_at(Instant::now() + bootstrap_interval, bootstrap_interval),
 			},
 			active_blocks: Default::default(),
+			reputation: PeerReputation::new(cfg.peer_reputation_threshold),
+			connected_peers: Default::default(),
 			shutdown,
 			event_loop_config: EventLoopConfig {
 				identity_data: cfg.identify,
@@ -338,6 +346,9 @@ impl EventLoop {
 					trace!(
 						"Identity Received from: {peer_id:?} on listen address: {listen_addrs:?}"
 					);
+					if let Some(peer) = self.connected_peers.get_mut(&peer_id) {
+						peer.agent_version = Some(agent_version.clone());
+					}
 					let incoming_peer_agent_version = match AgentVersion::from_str(&agent_version) {
 						Ok(agent) => agent,
 						Err(e) => {
@@ -437,8 +448,11 @@ impl EventLoop {
 					trace!("Hole punching failed with: {remote_peer_id:#?}. Error: {err:#?}")
 				},
 			},
-			SwarmEvent::Behaviour(BehaviourEvent::Ping(ping::Event { result, .. })) => {
+			SwarmEvent::Behaviour(BehaviourEvent::Ping(ping::Event { peer, result, .. })) => {
 				if let Ok(rtt) = result {
+					if let Some(connected_peer) = self.connected_peers.get_mut(&peer) {
+						connected_peer.ping_rtt = Some(rtt);
+					}
 					let _ = metrics
 						.record(MetricValue::PingLatency(rtt.as_millis() as f64))
 						.await;
@@ -472,6 +486,10 @@ impl EventLoop {
 					} => {
 						trace!("Connection closed. PeerID: {peer_id:?}. Address: {:?}. Num established: {num_established:?}. Cause: {cause:?}", endpoint.get_remote_address());
 
+						if num_established == 0 {
+							self.connected_peers.remove(&peer_id);
+						}
+
 						if let Some(ConnectionError::IO(_)) = cause {
 							// remove peer with failed connection
 							self.swarm.behaviour_mut().kademlia.remove_peer(&peer_id);
@@ -489,8 +507,16 @@ impl EventLoop {
 							address.to_string()
 						);
 					},
-					SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+					SwarmEvent::ConnectionEstablished {
+						peer_id, endpoint, ..
+					} => {
 						metrics.count(MetricCounter::ConnectionEstablished).await;
+						self.connected_peers
+							.entry(peer_id)
+							.or_insert_with(|| PeerInfo {
+								is_dialer: endpoint.is_dialer(),
+								..Default::default()
+							});
 						// Notify the connections we're waiting on that we've connected successfully
 						if let Some(ch) = self.pending_swarm_events.remove(&peer_id) {
 							_ = ch.send(Ok(()));
@@ -540,6 +566,8 @@ impl EventLoop {
 			&mut self.pending_kad_queries,
 			&mut self.pending_swarm_events,
 			&mut self.active_blocks,
+			&mut self.reputation,
+			&mut self.connected_peers,
 		)) {
 			command.abort(eyre!(err));
 		}
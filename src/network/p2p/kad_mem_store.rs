@@ -108,6 +108,11 @@ impl MemoryStore {
 		self.records.iter()
 	}
 
+	/// Returns number of stored records
+	pub fn records_len(&self) -> usize {
+		self.records.len()
+	}
+
 	/// Shrinks the capacity of hashmap as much as possible
 	pub fn shrink_hashmap(&mut self) {
 		self.records.shrink_to_fit();
@@ -311,8 +316,10 @@ mod tests {
 		let mut store = MemoryStore::new(PeerId::random());
 		assert!(store.put(r.clone()).is_ok());
 		assert_eq!(Some(Cow::Borrowed(&r)), store.get(&r.key));
+		assert_eq!(store.records_len(), 1);
 		store.remove(&r.key);
 		assert!(store.get(&r.key).is_none());
+		assert_eq!(store.records_len(), 0);
 	}
 	}
 
@@ -0,0 +1,187 @@
+use libp2p::PeerId;
+use std::collections::{HashMap, VecDeque};
+
+/// Reputation score increase for the valid cells served by the peer in a block
+const VALID_CELL_REWARD: i32 = 1;
+/// Reputation score decrease for the cells with invalid proofs served by the peer in a block
+const INVALID_CELL_PENALTY: i32 = 10;
+/// Maximum reputation score, so peers cannot build up unlimited credit
+const MAX_REPUTATION: i32 = 100;
+/// Number of the latest blocks per peer, for which the rewards and penalties are tracked.
+/// Blocks are processed concurrently, so the reports can arrive out of order.
+const TRACKED_BLOCKS: usize = 16;
+
+/// Reputation score of the peer, with the latest blocks for which the score was changed
+#[derive(Default)]
+struct PeerScore {
+	score: i32,
+	rewarded_blocks: VecDeque<u32>,
+	penalized_blocks: VecDeque<u32>,
+}
+
+/// Records the block, keeping only the latest tracked blocks. Returns false if the block is already recorded.
+fn record(blocks: &mut VecDeque<u32>, block_number: u32) -> bool {
+	if blocks.contains(&block_number) {
+		return false;
+	}
+	if blocks.len() == TRACKED_BLOCKS {
+		blocks.pop_front();
+	}
+	blocks.push_back(block_number);
+	true
+}
+
+/// Reputation scores of the peers which served DHT records.
+/// Peers whose score drops to the threshold should be blocked.
+///
+/// Cell records are not signed, so the penalized peer is the one which served the record,
+/// which may only store the record replicated from its author. To avoid blocking such peers
+/// for a single faulty block, peer is penalized at most once per block, and rewarded at most once per block.
+/// Peers whose score is back to neutral are not tracked.
+pub struct PeerReputation {
+	threshold: i32,
+	peers: HashMap<PeerId, PeerScore>,
+}
+
+impl PeerReputation {
+	pub fn new(threshold: i32) -> Self {
+		Self {
+			threshold,
+			peers: Default::default(),
+		}
+	}
+
+	pub fn score(&self, peer_id: &PeerId) -> i32 {
+		self.peers.get(peer_id).map_or(0, |peer| peer.score)
+	}
+
+	/// Increases reputation score of the peer, unless it is already rewarded for the block.
+	pub fn reward(&mut self, peer_id: PeerId, block_number: u32) {
+		let peer = self.peers.entry(peer_id).or_default();
+		if record(&mut peer.rewarded_blocks, block_number) {
+			peer.score = (peer.score + VALID_CELL_REWARD).min(MAX_REPUTATION);
+		}
+		self.evict_neutral(&peer_id);
+	}
+
+	/// Decreases reputation score of the peer, unless it is already penalized for the block.
+	/// Returns true if the peer should be blocked.
+	pub fn penalize(&mut self, peer_id: PeerId, block_number: u32) -> bool {
+		let peer = self.peers.entry(peer_id).or_default();
+		if record(&mut peer.penalized_blocks, block_number) {
+			peer.score = peer.score.saturating_sub(INVALID_CELL_PENALTY);
+		}
+		let blocked = peer.score <= self.threshold;
+		self.evict_neutral(&peer_id);
+		blocked
+	}
+
+	/// Resets reputation score of the peer (e.g. when peer is unblocked).
+	pub fn reset(&mut self, peer_id: &PeerId) {
+		self.peers.remove(peer_id);
+	}
+
+	fn evict_neutral(&mut self, peer_id: &PeerId) {
+		if self.score(peer_id) == 0 {
+			self.peers.remove(peer_id);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{PeerReputation, MAX_REPUTATION, TRACKED_BLOCKS};
+	use libp2p::PeerId;
+
+	#[test]
+	fn peer_is_blocked_after_repeated_invalid_cells() {
+		let mut reputation = PeerReputation::new(-30);
+		let peer_id = PeerId::random();
+
+		assert!(!reputation.penalize(peer_id, 1));
+		assert!(!reputation.penalize(peer_id, 2));
+		assert!(reputation.penalize(peer_id, 3));
+		assert_eq!(reputation.score(&peer_id), -30);
+
+		reputation.reset(&peer_id);
+		assert_eq!(reputation.score(&peer_id), 0);
+	}
+
+	#[test]
+	fn valid_cells_offset_invalid_cells() {
+		let mut reputation = PeerReputation::new(-30);
+		let peer_id = PeerId::random();
+
+		for block_number in 0..1000 {
+			reputation.reward(peer_id, block_number);
+		}
+		assert_eq!(reputation.score(&peer_id), MAX_REPUTATION);
+
+		for block_number in 0..12 {
+			assert!(!reputation.penalize(peer_id, block_number));
+		}
+		assert!(reputation.penalize(peer_id, 12));
+	}
+
+	#[test]
+	fn peer_is_penalized_once_per_block() {
+		let mut reputation = PeerReputation::new(-30);
+		let peer_id = PeerId::random();
+
+		for _ in 0..10 {
+			assert!(!reputation.penalize(peer_id, 1));
+		}
+		assert_eq!(reputation.score(&peer_id), -10);
+		assert!(!reputation.penalize(peer_id, 2));
+		assert_eq!(reputation.score(&peer_id), -20);
+
+		// Reports of the concurrently processed blocks arrive out of order
+		assert!(!reputation.penalize(peer_id, 1));
+		assert_eq!(reputation.score(&peer_id), -20);
+	}
+
+	#[test]
+	fn peer_is_rewarded_once_per_block() {
+		let mut reputation = PeerReputation::new(-30);
+		let peer_id = PeerId::random();
+
+		for _ in 0..10 {
+			reputation.reward(peer_id, 1);
+		}
+		assert_eq!(reputation.score(&peer_id), 1);
+		reputation.reward(peer_id, 3);
+		reputation.reward(peer_id, 2);
+		reputation.reward(peer_id, 1);
+		assert_eq!(reputation.score(&peer_id), 3);
+	}
+
+	#[test]
+	fn only_latest_blocks_are_tracked() {
+		let mut reputation = PeerReputation::new(-30);
+		let peer_id = PeerId::random();
+
+		for block_number in 0..100 {
+			reputation.reward(peer_id, block_number);
+		}
+		let peer = &reputation.peers[&peer_id];
+		assert_eq!(peer.rewarded_blocks.len(), TRACKED_BLOCKS);
+		assert_eq!(peer.rewarded_blocks.front(), Some(&84));
+	}
+
+	#[test]
+	fn neutral_peers_are_not_tracked() {
+		let mut reputation = PeerReputation::new(-30);
+		let peer_id = PeerId::random();
+
+		reputation.penalize(peer_id, 1);
+		for block_number in 1..10 {
+			reputation.reward(peer_id, block_number);
+		}
+		assert_eq!(reputation.score(&peer_id), -1);
+		assert!(reputation.peers.contains_key(&peer_id));
+
+		reputation.reward(peer_id, 10);
+		assert_eq!(reputation.score(&peer_id), 0);
+		assert!(reputation.peers.is_empty());
+	}
+}
@@ -9,6 +9,7 @@ use sp_core::bytes::from_hex;
 use std::{
 	collections::HashSet,
 	fmt::Display,
+	ops::RangeInclusive,
 	sync::{Arc, Mutex},
 };
 use tokio::{
@@ -20,10 +21,11 @@ use tracing::{debug, info};
 use crate::{
 	data::Database,
 	network::rpc,
-	types::{GrandpaJustification, RetryConfig, State},
+	types::{GrandpaJustification, RetryConfig, RpcHealthConfig, State},
 };
 
 mod client;
+mod receiver;
 mod subscriptions;
 
 use subscriptions::SubscriptionLoop;
@@ -32,7 +34,8 @@ const PROOF_SIZE: usize = 48;
 pub const CELL_WITH_PROOF_SIZE: usize = CELL_SIZE + PROOF_SIZE;
 pub use subscriptions::Event;
 
-pub use client::Client;
+pub use client::{Client, NodeHealth};
+pub use receiver::{BlockMessage, HeaderSource, RecoveringReceiver};
 
 pub enum Subscription {
 	Header(Header),
@@ -207,9 +210,18 @@ pub async fn init<T: Database>(
 	nodes: &[String],
 	genesis_hash: &str,
 	retry_config: RetryConfig,
+	health_config: RpcHealthConfig,
+	spec_versions: RangeInclusive<u32>,
 ) -> Result<(Client, broadcast::Sender<Event>, SubscriptionLoop<T>)> {
-	let rpc_client =
-		Client::new(state.clone(), Nodes::new(nodes), genesis_hash, retry_config).await?;
+	let rpc_client = Client::new(
+		state.clone(),
+		Nodes::new(nodes),
+		genesis_hash,
+		retry_config,
+		health_config,
+		spec_versions,
+	)
+	.await?;
 	// create output channel for RPC Subscription Events
 	let (event_sender, _) = broadcast::channel(1000);
 	let subscriptions =
@@ -220,6 +232,12 @@ pub async fn init<T: Database>(
 
 /// Generates random cell positions for sampling
 pub fn generate_random_cells(dimensions: Dimensions, cell_count: u32) -> Vec<Position> {
+	random_cells(&mut thread_rng(), dimensions, cell_count)
+}
+
+/// Generates random cell positions using given random generator.
+/// Positions are returned in order of generation, so the seeded generator gives reproducible results.
+pub fn random_cells(rng: &mut impl Rng, dimensions: Dimensions, cell_count: u32) -> Vec<Position> {
 	let max_cells = dimensions.extended_size();
 	let count = if max_cells < cell_count {
 		debug!("Max cells count {max_cells} is lesser than cell_count {cell_count}");
@@ -227,15 +245,18 @@ pub fn generate_random_cells(dimensions: Dimensions, cell_count: u32) -> Vec<Pos
 	} else {
 		cell_count
 	};
-	let mut rng = thread_rng();
 	let mut indices = HashSet::new();
-	while (indices.len() as u16) < count as u16 {
+	let mut positions = vec![];
+	while (positions.len() as u16) < count as u16 {
 		let col = rng.gen_range(0..dimensions.cols().into());
 		let row = rng.gen_range(0..dimensions.extended_rows());
-		indices.insert(Position { row, col });
+		let position = Position { row, col };
+		if indices.insert(position) {
+			positions.push(position);
+		}
 	}
 
-	indices.into_iter().collect::<Vec<_>>()
+	positions
 }
 
 /* @note: fn to take the number of cells needs to get equal to or greater than
@@ -7,13 +7,18 @@ use avail_subxt::{
 	AvailConfig,
 };
 use color_eyre::{eyre::eyre, Report, Result};
-use futures::{Stream, TryFutureExt, TryStreamExt};
+use futures::{future::join_all, Stream, TryFutureExt, TryStreamExt};
 use kate_recovery::{data::Cell, matrix::Position};
 use sp_core::{
 	bytes::from_hex,
 	ed25519::{self, Public},
 };
-use std::sync::{Arc, Mutex};
+use std::{
+	collections::HashMap,
+	ops::RangeInclusive,
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
 use subxt::{
 	rpc::{types::BlockNumber, RpcParams},
 	rpc_params,
@@ -21,17 +26,159 @@ use subxt::{
 	tx::{PairSigner, SubmittableExtrinsic},
 	utils::AccountId32,
 };
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock};
 use tokio_retry::Retry;
 use tokio_stream::StreamExt;
-use tracing::{info, warn};
+use tracing::{debug, error, info, warn};
 
 use super::{Node, Nodes, Subscription, WrappedProof, CELL_WITH_PROOF_SIZE};
 use crate::{
 	consts::ExpectedNodeVariant,
-	types::{RetryConfig, RuntimeVersion, State, DEV_FLAG_GENHASH},
+	telemetry::{MetricCounter, MetricValue, Metrics},
+	types::{RetryConfig, RpcHealthConfig, RuntimeVersion, State, DEV_FLAG_GENHASH},
 };
 
+/// Weight of the latest sample in the moving averages of the node latency and error rate
+const SAMPLE_WEIGHT: f64 = 0.2;
+/// Error rate above which the node is considered unhealthy
+const MAX_ERROR_RATE: f64 = 0.5;
+
+/// Health of the RPC node, tracked on RPC calls and periodic health checks
+#[derive(Clone, Debug, Default)]
+pub struct NodeHealth {
+	pub host: String,
+	/// Moving average of the successful RPC call latency
+	pub latency: Option<Duration>,
+	pub requests: u64,
+	pub errors: u64,
+	/// Moving average of the RPC call error rate
+	pub error_rate: f64,
+	/// Latest finalized block number reported by the node
+	pub finalized_head: Option<u32>,
+	/// Number of blocks by which the finalized head lags behind the most advanced node
+	pub head_lag: u32,
+	/// Reason why the node cannot be used, like genesis hash or version mismatch
+	pub incompatibility: Option<String>,
+}
+
+impl NodeHealth {
+	fn new(host: &str) -> Self {
+		NodeHealth {
+			host: host.to_string(),
+			..Default::default()
+		}
+	}
+
+	fn record_call(&mut self, latency: Duration, is_error: bool) {
+		self.requests += 1;
+		self.error_rate *= 1.0 - SAMPLE_WEIGHT;
+		if is_error {
+			self.errors += 1;
+			self.error_rate += SAMPLE_WEIGHT;
+			return;
+		}
+		self.latency = Some(self.latency.map_or(latency, |average| {
+			average.mul_f64(1.0 - SAMPLE_WEIGHT) + latency.mul_f64(SAMPLE_WEIGHT)
+		}));
+	}
+
+	/// Node is healthy if it is compatible, its finalized head is known,
+	/// and its head lag, latency and error rate are within the limits.
+	pub fn is_healthy(&self, cfg: &RpcHealthConfig) -> bool {
+		self.incompatibility.is_none()
+			&& self.finalized_head.is_some()
+			&& self.head_lag <= cfg.max_head_lag
+			&& self
+				.latency
+				.map_or(true, |latency| latency <= cfg.max_latency)
+			&& self.error_rate <= MAX_ERROR_RATE
+	}
+
+	/// Key by which nodes are ordered for connecting, lower is better
+	fn rank(&self, cfg: &RpcHealthConfig) -> (bool, bool, u32, Duration) {
+		(
+			self.incompatibility.is_some(),
+			!self.is_healthy(cfg),
+			self.head_lag,
+			self.latency.unwrap_or(cfg.max_latency),
+		)
+	}
+}
+
+/// Result of the node health check
+enum HealthCheck {
+	/// Finalized head number, and latency of the finalized head query
+	Head(u32, Duration),
+	Incompatible(String),
+}
+
+/// Sets head lag of each compatible node, relative to the most advanced compatible node
+fn update_head_lags(health: &mut [NodeHealth]) {
+	let best = health
+		.iter()
+		.filter(|node| node.incompatibility.is_none())
+		.filter_map(|node| node.finalized_head)
+		.max();
+	for node in health.iter_mut() {
+		node.head_lag = best
+			.zip(node.finalized_head)
+			.map_or(0, |(best, head)| best.saturating_sub(head));
+	}
+}
+
+/// Checks that the node genesis hash matches the configured one, unless it is configured for development
+fn check_genesis_hash(host: &str, genesis_hash: H256, expected_genesis_hash: &str) -> Result<()> {
+	if expected_genesis_hash.starts_with(DEV_FLAG_GENHASH) {
+		return Ok(());
+	}
+	let Some(cfg_genhash) = from_hex(expected_genesis_hash)
+		.ok()
+		.and_then(|e| TryInto::<[u8; 32]>::try_into(e).ok().map(H256::from))
+	else {
+		return Err(eyre!(
+			"Genesis hash invalid, badly configured or missing (\"{}\").",
+			expected_genesis_hash
+		));
+	};
+	if !genesis_hash.eq(&cfg_genhash) {
+		return Err(eyre!(
+			"Genesis hash doesn't match the configured one! Change the config or the node url ({}).",
+			host
+		));
+	}
+	Ok(())
+}
+
+fn check_version(
+	expected_node: &ExpectedNodeVariant,
+	system_version: &str,
+	runtime_version: &RuntimeVersion,
+) -> Result<()> {
+	if !expected_node.matches(system_version, &runtime_version.spec_name) {
+		return Err(eyre!(
+			"Expected Node system version:{:?}/{}, found: {}/{}. Skipping to another node.",
+			expected_node.system_version,
+			expected_node.spec_name,
+			system_version,
+			runtime_version.spec_name,
+		));
+	}
+	Ok(())
+}
+
+/// Returns version of the latest header extension, e.g. `V3`.
+/// Header is fetched as JSON, so the unsupported extension version can be reported instead of failing to decode.
+async fn header_extension_version(client: &avail::Client) -> Result<String> {
+	let header: serde_json::Value = client
+		.rpc()
+		.request("chain_getHeader", RpcParams::new())
+		.await?;
+	(header.get("extension"))
+		.and_then(|extension| extension.as_object())
+		.and_then(|extension| extension.keys().next().cloned())
+		.ok_or_else(|| eyre!("Header extension is missing"))
+}
+
 #[derive(Clone)]
 pub struct Client {
 	subxt_client: Arc<RwLock<avail::Client>>,
@@ -39,6 +186,14 @@ pub struct Client {
 	nodes: Nodes,
 	retry_config: RetryConfig,
 	expected_genesis_hash: String,
+	expected_node: ExpectedNodeVariant,
+	health: Arc<Mutex<Vec<NodeHealth>>>,
+	health_config: RpcHealthConfig,
+	/// Incremented on each switch to another node, so the subscriptions are moved to the new node
+	switches: Arc<watch::Sender<u64>>,
+	/// Clients of the nodes which are not connected, reused by the health checks.
+	/// Health checks don't depend on the runtime metadata, so the clients are not refreshed on runtime upgrade.
+	health_clients: Arc<Mutex<HashMap<String, avail::Client>>>,
 }
 
 impl Client {
@@ -47,13 +202,16 @@ impl Client {
 		nodes: Nodes,
 		expected_genesis_hash: &str,
 		retry_config: RetryConfig,
+		health_config: RpcHealthConfig,
+		spec_versions: RangeInclusive<u32>,
 	) -> Result<Self> {
+		let expected_node = ExpectedNodeVariant::new(spec_versions);
 		// try and connect appropriate Node from the provided list
 		// will do retries with the provided Retry Config
 		let (client, node, _) = Retry::spawn(retry_config.clone(), || async {
 			Self::try_connect_and_execute(
 				nodes.shuffle(Default::default()),
-				ExpectedNodeVariant::new(),
+				expected_node.clone(),
 				expected_genesis_hash,
 				|_| futures::future::ok(()),
 			)
@@ -64,13 +222,22 @@ impl Client {
 		// update application wide State with the newly connected Node
 		state.lock().unwrap().connected_node = node;
 
-		Ok(Self {
+		let health = nodes.iter().map(|node| NodeHealth::new(&node.host));
+
+		let client = Self {
 			subxt_client: Arc::new(RwLock::new(client)),
 			state,
+			health: Arc::new(Mutex::new(health.collect())),
 			nodes,
 			retry_config,
 			expected_genesis_hash: expected_genesis_hash.to_string(),
-		})
+			expected_node,
+			health_config,
+			switches: Arc::new(watch::Sender::new(0)),
+			health_clients: Default::default(),
+		};
+		client.update_runtime_compatibility().await;
+		Ok(client)
 	}
 
 	async fn create_subxt_client(
@@ -83,23 +250,10 @@ impl Client {
 		// check genesis hash
 		let genesis_hash = client.genesis_hash();
 		info!("Genesis hash: {:?}", genesis_hash);
-		if let Some(cfg_genhash) = from_hex(expected_genesis_hash)
-			.ok()
-			.and_then(|e| TryInto::<[u8; 32]>::try_into(e).ok().map(H256::from))
-		{
-			if !genesis_hash.eq(&cfg_genhash) {
-				Err(eyre!(
-					"Genesis hash doesn't match the configured one! Change the config or the node url ({}).", host
-				))?
-			}
-		} else if expected_genesis_hash.starts_with(DEV_FLAG_GENHASH) {
+		if expected_genesis_hash.starts_with(DEV_FLAG_GENHASH) {
 			warn!("Genesis hash configured for development ({}), skipping the genesis hash check entirely.", expected_genesis_hash);
-		} else {
-			Err(eyre!(
-				"Genesis hash invalid, badly configured or missing (\"{}\").",
-				expected_genesis_hash
-			))?
-		};
+		}
+		check_genesis_hash(host, genesis_hash, expected_genesis_hash)?;
 
 		// check system and runtime versions
 		let system_version = client.rpc().system_version().await?;
@@ -108,15 +262,7 @@ impl Client {
 			.request("state_getRuntimeVersion", RpcParams::new())
 			.await?;
 
-		if !expected_node.matches(&system_version, &runtime_version.spec_name) {
-			return Err(eyre!(
-				"Expected Node system version:{:?}/{}, found: {}/{}. Skipping to another node.",
-				expected_node.system_version,
-				expected_node.spec_name,
-				system_version,
-				runtime_version.spec_name,
-			));
-		}
+		check_version(&expected_node, &system_version, &runtime_version)?;
 
 		let variant = Node::new(
 			host.to_string(),
@@ -158,6 +304,266 @@ impl Client {
 		Err(eyre!("Failed to connect any appropriate working node"))
 	}
 
+	/// Returns host of the currently connected node
+	pub fn connected_host(&self) -> String {
+		self.state.lock().unwrap().connected_node.host.clone()
+	}
+
+	fn record_call(&self, host: &str, latency: Duration, is_error: bool) {
+		let mut health = self.health.lock().expect("Lock should be acquired");
+		if let Some(node) = health.iter_mut().find(|node| node.host == host) {
+			node.record_call(latency, is_error);
+		}
+	}
+
+	/// Returns health of the nodes, in the configured order
+	pub fn nodes_health(&self) -> Vec<NodeHealth> {
+		self.health.lock().expect("Lock should be acquired").clone()
+	}
+
+	/// Returns nodes, excluding the current host, ordered from the healthiest one.
+	/// Nodes with the same rank are shuffled.
+	fn ranked_nodes(&self, current_host: String) -> Vec<Node> {
+		let mut nodes = self.nodes.shuffle(current_host);
+		let health = self.health.lock().expect("Lock should be acquired");
+		nodes.sort_by_key(|node| {
+			(health.iter())
+				.find(|health| health.host == node.host)
+				.map(|health| health.rank(&self.health_config))
+		});
+		nodes
+	}
+
+	async fn set_connected(&self, client: avail::Client, node: Node) {
+		*self.subxt_client.write().await = client;
+		self.state.lock().unwrap().connected_node = node;
+		self.switches.send_modify(|switches| *switches += 1);
+		self.update_runtime_compatibility().await;
+	}
+
+	/// Checks if the runtime of the connected node is supported, and updates the degraded mode in the state.
+	/// Light client runs in degraded mode on unsupported runtime, since the blocks might not be decoded properly.
+	/// Degraded mode is only reported, blocks are still processed as long as their headers are decoded.
+	/// Compatibility is checked again on connection, runtime upgrade and subscription error,
+	/// so the light client leaves degraded mode once the connected node runs the supported runtime.
+	async fn update_runtime_compatibility(&self) {
+		let client = self.current_client().await;
+		let versions = async {
+			let runtime_version: RuntimeVersion = client
+				.rpc()
+				.request("state_getRuntimeVersion", RpcParams::new())
+				.await?;
+			let extension_version = header_extension_version(&client).await?;
+			Ok::<_, Report>((runtime_version.spec_version, extension_version))
+		};
+		let (spec_version, extension_version) = match versions.await {
+			Ok(versions) => versions,
+			Err(error) => {
+				warn!("Cannot check runtime compatibility: {error:#}");
+				return;
+			},
+		};
+
+		let incompatibility = self
+			.expected_node
+			.runtime_incompatibility(spec_version, &extension_version);
+		let mut state = self.state.lock().unwrap();
+		state.connected_node.spec_version = spec_version;
+		match (&state.degraded, &incompatibility) {
+			(None, Some(reason)) => {
+				error!(spec_version, "Running in degraded mode: {reason}")
+			},
+			(Some(_), None) => info!(spec_version, "Runtime is supported, leaving degraded mode"),
+			_ => (),
+		}
+		state.degraded = incompatibility;
+	}
+
+	/// Reconnects to the connected node after the runtime upgrade, so the runtime metadata is refreshed,
+	/// and checks if the upgraded runtime is supported.
+	/// Subscriptions are moved to the new connection, the same way as on the switch to another node.
+	pub async fn handle_runtime_upgrade(&self) {
+		let host = self.connected_host();
+		info!(
+			host,
+			"Runtime upgrade detected, refreshing the node connection"
+		);
+		match build_client(&host, false).await {
+			Ok((client, _)) => {
+				*self.subxt_client.write().await = client;
+				self.switches.send_modify(|switches| *switches += 1);
+			},
+			Err(error) => warn!(host, "Cannot reconnect after the runtime upgrade: {error}"),
+		}
+		self.update_runtime_compatibility().await;
+	}
+
+	/// Checks the node using the connected client, or the cached client of the node.
+	/// Client is cached once the check succeeds, and dropped once it fails, so it is rebuilt on the next check.
+	async fn check_node(&self, host: &str, is_connected: bool) -> Result<HealthCheck> {
+		if is_connected {
+			return self.check_client(host, &self.current_client().await).await;
+		}
+
+		let cached = (self.health_clients.lock().expect("Lock should be acquired"))
+			.get(host)
+			.cloned();
+		let client = match cached {
+			Some(client) => client,
+			None => build_client(host, false).await.map_err(|e| eyre!(e))?.0,
+		};
+
+		let result = self.check_client(host, &client).await;
+		let mut health_clients = self.health_clients.lock().expect("Lock should be acquired");
+		if result.is_ok() {
+			health_clients.insert(host.to_string(), client);
+		} else {
+			health_clients.remove(host);
+		}
+		result
+	}
+
+	async fn check_client(&self, host: &str, client: &avail::Client) -> Result<HealthCheck> {
+		let genesis_hash = client.genesis_hash();
+		if let Err(error) = check_genesis_hash(host, genesis_hash, &self.expected_genesis_hash) {
+			return Ok(HealthCheck::Incompatible(error.to_string()));
+		}
+
+		let system_version = client.rpc().system_version().await?;
+		let runtime_version: RuntimeVersion = client
+			.rpc()
+			.request("state_getRuntimeVersion", RpcParams::new())
+			.await?;
+		if let Err(error) = check_version(&self.expected_node, &system_version, &runtime_version) {
+			return Ok(HealthCheck::Incompatible(error.to_string()));
+		}
+
+		let started = Instant::now();
+		let finalized_hash = client.rpc().finalized_head().await?;
+		let header = client
+			.rpc()
+			.header(Some(finalized_hash))
+			.await?
+			.ok_or_else(|| eyre!("Finalized header not found"))?;
+		Ok(HealthCheck::Head(header.number, started.elapsed()))
+	}
+
+	/// Checks health of all nodes, and updates their head lag relative to the most advanced node
+	pub async fn check_health(&self) {
+		let connected_host = &self.connected_host();
+		let checks = self.nodes.iter().map(|Node { host, .. }| async move {
+			(host, self.check_node(host, host == connected_host).await)
+		});
+		let results = join_all(checks).await;
+
+		let mut health = self.health.lock().expect("Lock should be acquired");
+		for (host, result) in results {
+			let Some(node) = health.iter_mut().find(|node| &node.host == host) else {
+				continue;
+			};
+			match result {
+				Ok(HealthCheck::Head(number, latency)) => {
+					node.incompatibility = None;
+					node.finalized_head = Some(number);
+					node.record_call(latency, false);
+				},
+				Ok(HealthCheck::Incompatible(reason)) => {
+					debug!(host, "Node is incompatible: {reason}");
+					node.incompatibility = Some(reason);
+				},
+				Err(error) => {
+					debug!(host, "Node health check failed: {error:#}");
+					node.record_call(Duration::ZERO, true);
+				},
+			}
+		}
+		update_head_lags(&mut health);
+	}
+
+	/// Switches to the healthiest node if the connected node is unhealthy.
+	/// Returns true if the node is switched.
+	async fn switch_unhealthy_node(&self) -> Result<bool> {
+		let connected_host = self.connected_host();
+		let is_connected_healthy = {
+			let health = self.health.lock().expect("Lock should be acquired");
+			(health.iter())
+				.find(|node| node.host == connected_host)
+				.map_or(true, |node| node.is_healthy(&self.health_config))
+		};
+		if is_connected_healthy {
+			return Ok(false);
+		}
+
+		let mut healthy_nodes = self.ranked_nodes(connected_host.clone());
+		{
+			let health = self.health.lock().expect("Lock should be acquired");
+			healthy_nodes.retain(|node| {
+				(health.iter())
+					.find(|health| health.host == node.host)
+					.map_or(false, |health| health.is_healthy(&self.health_config))
+			});
+		}
+		if healthy_nodes.is_empty() {
+			warn!(
+				host = connected_host,
+				"Connected node is unhealthy, and there is no healthy node to switch to"
+			);
+			return Ok(false);
+		}
+
+		let (client, node, _) = Self::try_connect_and_execute(
+			healthy_nodes,
+			self.expected_node.clone(),
+			&self.expected_genesis_hash,
+			|_| futures::future::ok(()),
+		)
+		.await?;
+		info!(
+			from = connected_host,
+			to = node.host,
+			"Switching from unhealthy RPC node"
+		);
+		self.set_connected(client, node).await;
+		Ok(true)
+	}
+
+	/// Periodically checks health of the nodes, switches away from the unhealthy connected node and records metrics.
+	/// Health checks are not run if the check interval is not configured.
+	pub async fn monitor_health(self, metrics: Arc<impl Metrics>) {
+		let Some(interval) = self.health_config.check_interval else {
+			return;
+		};
+		loop {
+			tokio::time::sleep(interval).await;
+			self.check_health().await;
+
+			match self.switch_unhealthy_node().await {
+				Ok(true) => metrics.count(MetricCounter::RPCNodeSwitch).await,
+				Ok(false) => (),
+				Err(error) => warn!("Cannot switch from unhealthy RPC node: {error:#}"),
+			}
+
+			let connected_host = self.connected_host();
+			let health = self.nodes_health();
+			let healthy = health
+				.iter()
+				.filter(|node| node.is_healthy(&self.health_config))
+				.count();
+			let mut values = vec![MetricValue::RPCHealthyNodes(healthy)];
+			if let Some(node) = health.iter().find(|node| node.host == connected_host) {
+				values.push(MetricValue::RPCNodeHeadLag(node.head_lag));
+				if let Some(latency) = node.latency {
+					values.push(MetricValue::RPCNodeLatency(latency.as_secs_f64() * 1000.0));
+				}
+			}
+			for value in values {
+				if let Err(error) = metrics.record(value).await {
+					warn!("Cannot record RPC node metric: {error:#}");
+				}
+			}
+		}
+	}
+
 	async fn with_retries<F, Fut, T>(&self, mut f: F) -> Result<T>
 	where
 		F: FnMut(avail::Client) -> Fut + Copy,
@@ -165,7 +571,11 @@ impl Client {
 	{
 		// try and execute the passed function, use the Retry strategy if needed
 		if let Ok(result) = Retry::spawn(self.retry_config.clone(), move || async move {
-			f(self.current_client().await).await
+			let host = self.connected_host();
+			let started = Instant::now();
+			let result = f(self.current_client().await).await;
+			self.record_call(&host, started.elapsed(), result.is_err());
+			result
 		})
 		.await
 		{
@@ -178,15 +588,15 @@ impl Client {
 			"Executing RPC call with host: {} failed. Trying to create a new RPC connection.",
 			connected_node.host
 		);
-		// shuffle nodes, if possible
-		let nodes = self.nodes.shuffle(connected_node.host);
+		// try the healthiest nodes first
+		let nodes = self.ranked_nodes(connected_node.host);
 		// go through available Nodes, try to connect, Retry connecting if needed
 		let (client, node, result) = Retry::spawn(self.retry_config.clone(), move || {
 			let nodes = nodes.clone();
 			async move {
 				Self::try_connect_and_execute(
 					nodes,
-					ExpectedNodeVariant::new(),
+					self.expected_node.clone(),
 					&self.expected_genesis_hash,
 					move |client| f(client).map_err(Report::from),
 				)
@@ -196,8 +606,7 @@ impl Client {
 		.await?;
 
 		// retries gave results, update currently connected Node and created Client
-		*self.subxt_client.write().await = client;
-		self.state.lock().unwrap().connected_node = node;
+		self.set_connected(client, node).await;
 
 		Ok(result)
 	}
@@ -226,8 +635,11 @@ impl Client {
 	}
 
 	pub async fn subscription_stream(self) -> impl Stream<Item = Result<Subscription>> {
+		let mut switched = self.switches.subscribe();
 		async_stream::stream! {
 			'outer: loop{
+				// switch made while the stream is created is not missed, at the cost of creating the stream once more
+				switched.borrow_and_update();
 				let mut stream = match self.with_retries(|client| async move{
 					Self::create_subxt_subscriptions(client).await
 				}).await {
@@ -239,8 +651,16 @@ impl Client {
 				};
 
 				loop {
+					let next = tokio::select! {
+						next = stream.next() => next,
+						// connected node is switched, so the subscriptions are moved to the new node
+						_ = switched.changed() => {
+							info!("Connected node is switched. Creating a new Subscriptions Stream.");
+							continue 'outer
+						},
+					};
 					// no more subscriptions left on stream, we have to try and create a new stream
-					let Some(result) = stream.next().await else {
+					let Some(result) = next else {
 						warn!("No more items on Subscriptions Stream. Trying to create a new one.");
 						continue 'outer
 					};
@@ -249,6 +669,8 @@ impl Client {
 						// if Error was received, we need to switch to another RPC Client
 						Err(err)=> {
 							warn!(%err, "Received Error on stream. Trying to create a new one.");
+							// headers of the unsupported runtime are failing to decode
+							self.update_runtime_compatibility().await;
 							continue 'outer
 						}
 					}
@@ -541,3 +963,66 @@ impl Client {
 		Ok(gen_hash)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::{update_head_lags, NodeHealth};
+	use crate::types::RpcHealthConfig;
+	use std::time::Duration;
+
+	fn node(host: &str, finalized_head: u32, latency: u64) -> NodeHealth {
+		let mut node = NodeHealth::new(host);
+		node.finalized_head = Some(finalized_head);
+		node.record_call(Duration::from_millis(latency), false);
+		node
+	}
+
+	#[test]
+	fn unhealthy_nodes_are_ranked_last() {
+		let cfg = RpcHealthConfig {
+			check_interval: None,
+			max_head_lag: 3,
+			max_latency: Duration::from_millis(500),
+		};
+
+		let mut incompatible = node("ws://incompatible", 110, 10);
+		incompatible.incompatibility = Some("Genesis hash doesn't match".to_string());
+		let mut failing = node("ws://failing", 100, 10);
+		for _ in 0..4 {
+			failing.record_call(Duration::ZERO, true);
+		}
+		let mut nodes = vec![
+			incompatible,
+			node("ws://lagging", 90, 10),
+			node("ws://slow", 100, 1000),
+			failing,
+			node("ws://healthy", 99, 100),
+			node("ws://fastest", 100, 10),
+		];
+		update_head_lags(&mut nodes);
+
+		let healthy = (nodes.iter())
+			.filter(|node| node.is_healthy(&cfg))
+			.map(|node| node.host.as_str())
+			.collect::<Vec<_>>();
+		assert_eq!(healthy, vec!["ws://healthy", "ws://fastest"]);
+		assert_eq!(nodes[1].head_lag, 10);
+		assert_eq!((nodes[3].requests, nodes[3].errors), (5, 4));
+
+		nodes.sort_by_key(|node| node.rank(&cfg));
+		let ranked = (nodes.iter())
+			.map(|node| node.host.as_str())
+			.collect::<Vec<_>>();
+		assert_eq!(
+			ranked,
+			vec![
+				"ws://fastest",
+				"ws://healthy",
+				"ws://failing",
+				"ws://slow",
+				"ws://lagging",
+				"ws://incompatible"
+			]
+		);
+	}
+}
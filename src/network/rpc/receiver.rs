@@ -0,0 +1,255 @@
+//! Broadcast receiver which recovers from the lag.
+//!
+//! When receiver falls behind the sender, broadcast channel drops the oldest messages.
+//! Instead of failing, skipped messages are recreated from the headers fetched via RPC.
+
+use async_trait::async_trait;
+use avail_subxt::primitives::Header;
+use color_eyre::Result;
+use std::{collections::VecDeque, ops::Range, sync::Arc};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::warn;
+
+use super::{Client, Event};
+use crate::telemetry::{MetricCounter, Metrics};
+
+/// Message which is sent for each finalized block
+pub trait BlockMessage: Sized {
+	fn block_number(&self) -> u32;
+
+	/// Creates message for the skipped block from the header fetched via RPC,
+	/// and the first message received after the lag.
+	fn recover(header: Header, next: &Self) -> Result<Self>;
+}
+
+impl BlockMessage for Event {
+	fn block_number(&self) -> u32 {
+		match self {
+			Event::HeaderUpdate { header, .. } => header.number,
+		}
+	}
+
+	fn recover(header: Header, next: &Self) -> Result<Self> {
+		let Event::HeaderUpdate { received_at, .. } = next;
+		Ok(Event::HeaderUpdate {
+			header,
+			received_at: *received_at,
+		})
+	}
+}
+
+/// Source of the block headers, fetched by block number
+#[async_trait]
+pub trait HeaderSource: Send + Sync {
+	async fn get_header(&self, block_number: u32) -> Result<Header>;
+}
+
+#[async_trait]
+impl HeaderSource for Client {
+	async fn get_header(&self, block_number: u32) -> Result<Header> {
+		let (header, _) = self.get_header_by_block_number(block_number).await?;
+		Ok(header)
+	}
+}
+
+/// Returns blocks skipped before the next received block.
+/// If no block was received before the lag, number of skipped messages is used.
+fn skipped_blocks(last: Option<u32>, next: u32, skipped: u64) -> Range<u32> {
+	let first = match last {
+		Some(last) => last.saturating_add(1),
+		None => next.saturating_sub(skipped.try_into().unwrap_or(u32::MAX)),
+	};
+	first..next
+}
+
+pub struct RecoveringReceiver<T, S, M> {
+	receiver: broadcast::Receiver<T>,
+	header_source: S,
+	metrics: Arc<M>,
+	last_block_number: Option<u32>,
+	/// Number of messages skipped since the last received message
+	skipped: u64,
+	/// Skipped blocks which are not recovered yet
+	recovering: VecDeque<u32>,
+	/// First message received after the lag, returned once skipped blocks are recovered
+	next: Option<T>,
+}
+
+impl<T: BlockMessage + Clone, S: HeaderSource, M: Metrics> RecoveringReceiver<T, S, M> {
+	pub fn new(receiver: broadcast::Receiver<T>, header_source: S, metrics: Arc<M>) -> Self {
+		RecoveringReceiver {
+			receiver,
+			header_source,
+			metrics,
+			last_block_number: None,
+			skipped: 0,
+			recovering: VecDeque::new(),
+			next: None,
+		}
+	}
+
+	/// Returns number of messages which are not received yet
+	pub fn len(&self) -> usize {
+		self.receiver.len() + self.recovering.len() + usize::from(self.next.is_some())
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	fn received(&mut self, message: T) -> T {
+		self.last_block_number = Some(message.block_number());
+		message
+	}
+
+	async fn recover(&self, block_number: u32, next: &T) -> Result<T> {
+		let header = self.header_source.get_header(block_number).await?;
+		T::recover(header, next)
+	}
+
+	/// Receives the next message, in order of block numbers.
+	/// Returns an error only if the channel is closed.
+	pub async fn recv(&mut self) -> Result<T, RecvError> {
+		loop {
+			if let Some(next) = self.next.take() {
+				let Some(block_number) = self.recovering.pop_front() else {
+					return Ok(self.received(next));
+				};
+				let result = self.recover(block_number, &next).await;
+				self.next = Some(next);
+				match result {
+					Ok(message) => return Ok(self.received(message)),
+					Err(error) => warn!(block_number, "Cannot recover skipped block: {error:#}"),
+				}
+				continue;
+			}
+
+			match self.receiver.recv().await {
+				Ok(message) if self.skipped > 0 => {
+					let skipped = skipped_blocks(
+						self.last_block_number,
+						message.block_number(),
+						std::mem::take(&mut self.skipped),
+					);
+					warn!(?skipped, "Recovering blocks skipped due to the lag");
+					self.recovering.extend(skipped);
+					self.next = Some(message);
+				},
+				Ok(message) => return Ok(self.received(message)),
+				Err(RecvError::Lagged(skipped)) => {
+					warn!(skipped, "Receiver lagged behind the sender");
+					self.metrics.count(MetricCounter::ReceiverLagged).await;
+					self.skipped += skipped;
+				},
+				Err(error @ RecvError::Closed) => return Err(error),
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{skipped_blocks, HeaderSource, RecoveringReceiver};
+	use crate::{network::rpc::Event, telemetry::MockMetrics, test_utils::header};
+	use async_trait::async_trait;
+	use avail_subxt::primitives::Header;
+	use color_eyre::{eyre::eyre, Result};
+	use sp_core::H256;
+	use std::{sync::Arc, time::Instant};
+	use tokio::sync::broadcast;
+
+	fn event(number: u32) -> Event {
+		Event::HeaderUpdate {
+			header: header(number, H256::default()),
+			received_at: Instant::now(),
+		}
+	}
+
+	/// Returns headers of all blocks, except the unavailable one
+	struct Headers {
+		unavailable: Option<u32>,
+	}
+
+	#[async_trait]
+	impl HeaderSource for Headers {
+		async fn get_header(&self, block_number: u32) -> Result<Header> {
+			match self.unavailable == Some(block_number) {
+				true => Err(eyre!("Header {block_number} is not available")),
+				false => Ok(header(block_number, H256::default())),
+			}
+		}
+	}
+
+	fn lagging_metrics() -> Arc<MockMetrics> {
+		let mut metrics = MockMetrics::new();
+		metrics.expect_count().times(1).returning(|_| ());
+		Arc::new(metrics)
+	}
+
+	async fn receive(
+		receiver: &mut RecoveringReceiver<Event, Headers, MockMetrics>,
+		count: usize,
+	) -> Vec<u32> {
+		let mut block_numbers = vec![];
+		for _ in 0..count {
+			let Event::HeaderUpdate { header, .. } = receiver.recv().await.unwrap();
+			block_numbers.push(header.number);
+		}
+		block_numbers
+	}
+
+	#[tokio::test]
+	async fn skipped_blocks_are_replayed_in_order() {
+		let (sender, receiver) = broadcast::channel(2);
+		let headers = Headers { unavailable: None };
+		let mut receiver = RecoveringReceiver::new(receiver, headers, lagging_metrics());
+
+		sender.send(event(1)).unwrap();
+		assert_eq!(receive(&mut receiver, 1).await, vec![1]);
+
+		// Blocks 2, 3 and 4 are dropped from the channel
+		for block_number in 2..=6 {
+			sender.send(event(block_number)).unwrap();
+		}
+		assert_eq!(receiver.len(), 2);
+		assert_eq!(receive(&mut receiver, 5).await, vec![2, 3, 4, 5, 6]);
+		assert!(receiver.is_empty());
+	}
+
+	#[tokio::test]
+	async fn skipped_blocks_are_replayed_before_first_received() {
+		let (sender, receiver) = broadcast::channel(2);
+		let headers = Headers { unavailable: None };
+		let mut receiver = RecoveringReceiver::new(receiver, headers, lagging_metrics());
+
+		for block_number in 10..=14 {
+			sender.send(event(block_number)).unwrap();
+		}
+		assert_eq!(receive(&mut receiver, 5).await, vec![10, 11, 12, 13, 14]);
+	}
+
+	#[tokio::test]
+	async fn unrecoverable_block_is_skipped() {
+		let (sender, receiver) = broadcast::channel(2);
+		let headers = Headers {
+			unavailable: Some(3),
+		};
+		let mut receiver = RecoveringReceiver::new(receiver, headers, lagging_metrics());
+
+		sender.send(event(1)).unwrap();
+		assert_eq!(receive(&mut receiver, 1).await, vec![1]);
+
+		for block_number in 2..=6 {
+			sender.send(event(block_number)).unwrap();
+		}
+		assert_eq!(receive(&mut receiver, 4).await, vec![2, 4, 5, 6]);
+	}
+
+	#[test]
+	fn skipped_blocks_range() {
+		assert_eq!(skipped_blocks(Some(10), 15, 2), 11..15);
+		assert_eq!(skipped_blocks(Some(10), 11, 1), 11..11);
+		assert_eq!(skipped_blocks(None, 15, 2), 13..15);
+		assert_eq!(skipped_blocks(None, 1, 5), 0..1);
+	}
+}
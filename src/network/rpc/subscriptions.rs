@@ -19,7 +19,7 @@ use crate::{
 	data::{FinalitySyncCheckpoint, Key},
 	finality::{check_finality, ValidatorSet},
 	types::{GrandpaJustification, OptionBlockRange, State},
-	utils::filter_auth_set_changes,
+	utils::{filter_auth_set_changes, is_runtime_upgraded},
 };
 
 #[derive(Clone, Debug)]
@@ -113,6 +113,10 @@ impl<T: Database> SubscriptionLoop<T> {
 				self.state.lock().unwrap().latest = header.clone().number;
 				info!("Header no.: {}", header.number);
 
+				if is_runtime_upgraded(&header) {
+					self.rpc_client.handle_runtime_upgrade().await;
+				}
+
 				// if new validator set becomes active, replace the current one
 				if self.block_data.next_valset.is_some() {
 					self.block_data.current_valset = self.block_data.next_valset.take().unwrap();
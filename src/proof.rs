@@ -1,9 +1,15 @@
 //! Parallelized proof verification
 
-use color_eyre::eyre;
-use dusk_plonk::commitment_scheme::kzg10::PublicParameters;
+use color_eyre::eyre::{self, eyre};
+use dusk_bytes::Serializable;
+use dusk_plonk::{
+	commitment_scheme::kzg10::{CommitKey, PublicParameters},
+	fft::{EvaluationDomain, Evaluations},
+	prelude::BlsScalar,
+};
 use itertools::{Either, Itertools};
 use kate_recovery::{
+	config::{self, CHUNK_SIZE},
 	data::Cell,
 	matrix::{Dimensions, Position},
 	proof,
@@ -61,3 +67,107 @@ pub async fn verify(
 			false => Either::Right(position),
 		}))
 }
+
+/// Computes commitment of the row data, returns `None` if data is not a valid row
+fn row_commitment(
+	commit_key: &CommitKey,
+	cols: usize,
+	data: &[u8],
+) -> Option<[u8; config::COMMITMENT_SIZE]> {
+	if data.len() != cols * CHUNK_SIZE {
+		return None;
+	}
+	let scalars = data
+		.chunks_exact(CHUNK_SIZE)
+		.map(|chunk| BlsScalar::from_bytes(chunk.try_into().ok()?).ok())
+		.collect::<Option<Vec<_>>>()?;
+	let domain = EvaluationDomain::new(cols).ok()?;
+	let polynomial = Evaluations::from_vec_and_domain(scalars, domain).interpolate();
+	let commitment = commit_key.commit(&polynomial).ok()?;
+	Some(commitment.to_bytes())
+}
+
+/// Verifies rows for given block, by comparing commitments of the row data with given commitments.
+/// Returns verified and unverified row indexes.
+pub fn verify_rows(
+	dimensions: Dimensions,
+	rows: &[(u32, Vec<u8>)],
+	commitments: &[[u8; config::COMMITMENT_SIZE]],
+	public_parameters: &PublicParameters,
+) -> eyre::Result<(Vec<u32>, Vec<u32>)> {
+	if rows.is_empty() {
+		return Ok((Vec::new(), Vec::new()));
+	};
+
+	let cols = dimensions.width();
+	let (commit_key, _) = public_parameters
+		.trim(cols)
+		.map_err(|error| eyre!("Cannot trim public parameters: {error:?}"))?;
+
+	Ok(rows.iter().partition_map(|(row, data)| {
+		let commitment = row_commitment(&commit_key, cols, data);
+		match commitment.is_some() && commitment.as_ref() == commitments.get(*row as usize) {
+			true => Either::Left(*row),
+			false => Either::Right(*row),
+		}
+	}))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{row_commitment, verify_rows};
+	use avail_core::{AppId, DataLookup};
+	use hex_literal::hex;
+	use kate_recovery::{commitments, config::CHUNK_SIZE, matrix::Dimensions, testnet};
+
+	#[test]
+	fn rows_are_verified_against_header_commitments() {
+		// Row and commitment of the block 288, the same as used in the app client tests
+		let pp = testnet::public_params(1024);
+		let dimensions = Dimensions::new(1, 16).unwrap();
+		let row = hex!("042c280403000ba3fa0ab887018000000000000000000000000000000000000004d904d1048400d43593c715fdd31c61141abd04a99fd6822c8558854ccde3009a5684e7a56da27d01a8cf58e1e9c735f93ebc7a94086aa27cfd77db173aac00803895886b8a4f49e85c68f469d570f0ed992750bf95329bb90ef56b45abcd009fedef0d9cbdd61c05a181d4013800041d0121033036343265356430346236003632353966363635666431353361613136646637343066323533373237386600613139316565393630343862663839393733343961303137353865346237610032643539663534353338393865626231643233626634353965363637613633003462313663663432326663393335336434623862623630386235393230653400353733663335663037303764333238616661343832316663656631363439660039643532653762353732356533303935643865656561356436633235333830006434658000000000000000000000000000000000000000000000000000000000346080be83f48ad1748c4ad339abdcb803368efdd1f65689619ff8c208755d0084eefcf837b61c479b3332059bc8e89b490a9d502baecaed448433d4e161710000a71cbb1a0387598e509d9fcab511022f437b0caf13591315c3f1bbf04f18009d83f014806210da6ee1d2f80cf0f9c08f1d132be042769015f6174fd2b24c00").to_vec();
+		let commitment = [
+			165, 227, 207, 130, 59, 77, 78, 242, 184, 232, 114, 218, 145, 167, 149, 53, 89, 7, 230,
+			49, 85, 113, 218, 116, 43, 195, 144, 203, 149, 114, 106, 89, 73, 164, 17, 163, 3, 145,
+			173, 6, 119, 222, 17, 60, 251, 215, 40, 192,
+		];
+		let commitments = [commitment, commitment];
+
+		let lookup =
+			DataLookup::from_id_and_len_iter([(0u32, 1usize), (1, 11)].into_iter()).unwrap();
+		let rows = [Some(row.clone()), None];
+		let (expected, _) =
+			commitments::verify_equality(&pp, &commitments, &rows, &lookup, dimensions, AppId(1))
+				.unwrap();
+		assert_eq!(expected, vec![0]);
+
+		let mut tampered = row.clone();
+		tampered[1] ^= 1;
+		let rows = [(0, row), (1, tampered)];
+		let (verified, unverified) = verify_rows(dimensions, &rows, &commitments, &pp).unwrap();
+		assert_eq!(verified, expected);
+		assert_eq!(unverified, vec![1]);
+	}
+
+	#[test]
+	fn rows_are_verified_against_commitments() {
+		let pp = testnet::public_params(1024);
+		let dimensions = Dimensions::new(1, 4).unwrap();
+		let (commit_key, _) = pp.trim(dimensions.width()).unwrap();
+
+		let row = vec![1u8; dimensions.width() * CHUNK_SIZE]
+			.chunks_exact(CHUNK_SIZE)
+			.flat_map(|chunk| [&chunk[..CHUNK_SIZE - 1], &[0]].concat())
+			.collect::<Vec<_>>();
+		let commitment = row_commitment(&commit_key, dimensions.width(), &row).unwrap();
+		let commitments = [commitment, commitment];
+
+		let mut tampered = row.clone();
+		tampered[0] = 2;
+		let rows = [(0, row.clone()), (1, tampered), (2, row), (3, vec![0; 4])];
+
+		let (verified, unverified) = verify_rows(dimensions, &rows, &commitments, &pp).unwrap();
+		assert_eq!(verified, vec![0]);
+		assert_eq!(unverified, vec![1, 2, 3]);
+	}
+}
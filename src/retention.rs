@@ -0,0 +1,411 @@
+use color_eyre::{eyre::WrapErr, Result};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{debug, error, info};
+
+use crate::{
+	data::{Database, Key, KeyRange},
+	shutdown::Controller,
+	types::{BlockVerified, OptionBlockRange, RetentionConfig, State, SyncCursor},
+};
+
+/// Returns first block to keep, if only last `retention_blocks` blocks are kept.
+fn retention_cutoff(latest: u32, retention_blocks: Option<u32>) -> u32 {
+	retention_blocks.map_or(0, |blocks| latest.saturating_add(1).saturating_sub(blocks))
+}
+
+/// Returns first block to keep, so the stored data fits into the disk budget.
+/// Assumes that every stored block takes roughly the same amount of disk space.
+fn disk_budget_cutoff(size: u64, max_disk_size: u64, first_block: u32, latest: u32) -> u32 {
+	if size <= max_disk_size || first_block >= latest {
+		return 0;
+	}
+
+	let stored_blocks = (latest - first_block + 1) as u128;
+	let excess_blocks = (stored_blocks * (size - max_disk_size) as u128).div_ceil(size as u128);
+	// Latest block is never pruned
+	first_block + excess_blocks.min(stored_blocks - 1) as u32
+}
+
+/// Returns first block stored in the database, based on the current state.
+fn first_stored_block(state: &State) -> u32 {
+	let first_block = [
+		state.sync_header_verified.first(),
+		state.header_verified.first(),
+	]
+	.into_iter()
+	.flatten()
+	.min()
+	.unwrap_or(state.latest);
+	first_block.max(state.oldest_available.unwrap_or(0))
+}
+
+/// Pruning progress, kept between the pruning intervals.
+#[derive(Default)]
+pub struct Pruner {
+	header_cutoff: u32,
+	confidence_cutoff: u32,
+	app_data_cutoff: u32,
+	budget_cutoff: u32,
+	/// Database size measured before the last pruning which advanced the disk budget cutoff
+	budget_pruned_size: Option<u64>,
+}
+
+/// Deletes the range and reclaims its space, so the deletion is reflected in the database size.
+fn delete_range(db: &impl Database, range: KeyRange) -> Result<()> {
+	db.delete_range(range.clone())
+		.and_then(|_| db.compact_range(range.clone()))
+		.wrap_err_with(|| format!("Failed to prune {range:?}"))
+}
+
+/// Removes pruned blocks from the sync cursor, so the cursor doesn't grow with every synced range.
+fn prune_sync_cursor(db: &impl Database, oldest_available: u32) -> Result<()> {
+	let Some(last_pruned) = oldest_available.checked_sub(1) else {
+		return Ok(());
+	};
+	let Some(mut cursor) = db
+		.get::<SyncCursor>(Key::SyncCursor)
+		.wrap_err("Failed to get sync cursor")?
+	else {
+		return Ok(());
+	};
+	if cursor
+		.processed
+		.first()
+		.map_or(true, |first| first > last_pruned)
+	{
+		return Ok(());
+	}
+	cursor.processed.remove_range(0, last_pruned);
+	db.put(Key::SyncCursor, cursor)
+		.wrap_err("Failed to store pruned sync cursor")
+}
+
+impl Pruner {
+	/// Returns first block to keep, so the stored data fits into the disk budget.
+	/// Disk budget cutoff is not advanced again until the previous pruning is reflected in the database size,
+	/// otherwise the same excess would be pruned again from the smaller block range.
+	fn budget_cutoff(
+		&mut self,
+		db: &impl Database,
+		max_disk_size: u64,
+		first_block: u32,
+		latest: u32,
+	) -> Result<u32> {
+		let size = db.size().wrap_err("Failed to get database size")?;
+		if self
+			.budget_pruned_size
+			.map_or(false, |pruned_size| size >= pruned_size)
+		{
+			return Ok(self.budget_cutoff);
+		}
+
+		let cutoff = disk_budget_cutoff(size, max_disk_size, first_block, latest);
+		self.budget_pruned_size = (cutoff > self.budget_cutoff).then_some(size);
+		self.budget_cutoff = self.budget_cutoff.max(cutoff);
+		Ok(self.budget_cutoff)
+	}
+
+	/// Prunes blocks outside of the configured retention from the database.
+	/// Only the blocks between the previous and the new cutoff are deleted.
+	/// Returns the oldest block which is still fully available.
+	pub fn prune(
+		&mut self,
+		db: &impl Database,
+		cfg: &RetentionConfig,
+		latest: u32,
+		first_block: u32,
+	) -> Result<u32> {
+		let budget_cutoff = match cfg.max_disk_size {
+			Some(max_disk_size) => self.budget_cutoff(db, max_disk_size, first_block, latest)?,
+			None => 0,
+		};
+
+		let header_cutoff = retention_cutoff(latest, cfg.header_retention_blocks)
+			.max(budget_cutoff)
+			.max(self.header_cutoff);
+		let headers = self.header_cutoff..header_cutoff;
+		delete_range(db, KeyRange::BlockHeader(headers.clone()))?;
+		delete_range(db, KeyRange::TrustedHeader(headers))?;
+		self.header_cutoff = header_cutoff;
+
+		let confidence_cutoff = retention_cutoff(latest, cfg.confidence_retention_blocks)
+			.max(budget_cutoff)
+			.max(self.confidence_cutoff);
+		let blocks = self.confidence_cutoff..confidence_cutoff;
+		delete_range(db, KeyRange::VerifiedCellCount(blocks.clone()))?;
+		delete_range(db, KeyRange::BlockConfidence(blocks.clone()))?;
+		// Sampling related data is pruned together with the confidence
+		delete_range(db, KeyRange::RetryBlock(blocks.clone()))?;
+		delete_range(db, KeyRange::InvalidProofs(blocks.clone()))?;
+		delete_range(db, KeyRange::Cells(blocks))?;
+		self.confidence_cutoff = confidence_cutoff;
+
+		let mut oldest_available = header_cutoff.max(confidence_cutoff);
+
+		if let Some(app_id) = cfg.app_id {
+			let app_data_cutoff = retention_cutoff(latest, cfg.app_data_retention_blocks)
+				.max(budget_cutoff)
+				.max(self.app_data_cutoff);
+			delete_range(
+				db,
+				KeyRange::AppData(app_id, self.app_data_cutoff..app_data_cutoff),
+			)?;
+			self.app_data_cutoff = app_data_cutoff;
+			oldest_available = oldest_available.max(app_data_cutoff);
+		}
+
+		prune_sync_cursor(db, oldest_available)?;
+		Ok(oldest_available)
+	}
+}
+
+pub async fn run(
+	db: impl Database,
+	state: Arc<Mutex<State>>,
+	cfg: RetentionConfig,
+	mut block_receiver: broadcast::Receiver<BlockVerified>,
+	shutdown: Controller<String>,
+) {
+	info!("Starting retention pruning...");
+
+	let mut pruner = Pruner::default();
+	loop {
+		let block_number = match block_receiver.recv().await {
+			Ok(block) => block.block_num,
+			// Skipped blocks are pruned on the next pruning interval
+			Err(RecvError::Lagged(skipped)) => {
+				debug!(skipped, "Block receiver lagged");
+				continue;
+			},
+			Err(error) => {
+				let _ = shutdown.trigger_shutdown(format!("{error:#}"));
+				break;
+			},
+		};
+
+		// Retried blocks, received out of order, can trigger pruning more often,
+		// which only prunes the blocks outside of the retention of the latest block
+		if block_number % cfg.pruning_interval != 0 {
+			continue;
+		}
+
+		let (latest, first_block) = {
+			let state = state.lock().expect("Lock should be acquired");
+			(state.latest, first_stored_block(&state))
+		};
+
+		info!(block_number, latest, "Pruning database...");
+		match pruner.prune(&db, &cfg, latest, first_block) {
+			Ok(0) => debug!(block_number, "Nothing to prune"),
+			Ok(oldest_available) => {
+				let mut state = state.lock().expect("Lock should be acquired");
+				state.prune(oldest_available);
+				info!(block_number, oldest_available, "Database pruning finished");
+			},
+			Err(error) => error!(block_number, "Database pruning failed: {error:#}"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{disk_budget_cutoff, retention_cutoff, Pruner};
+	use crate::{
+		data::{mem_db::MemoryDB, Database, Key, KeyRange},
+		types::{
+			BlockRange, BlockSet, OptionBlockRange, RetentionConfig, RuntimeConfig, State,
+			StorageBackend, SyncCursor,
+		},
+	};
+	use codec::{Decode, Encode};
+	use color_eyre::Result;
+	use serde::{Deserialize, Serialize};
+
+	#[test]
+	fn retention_cutoff_keeps_last_blocks() {
+		assert_eq!(retention_cutoff(100, None), 0);
+		assert_eq!(retention_cutoff(100, Some(10)), 91);
+		assert_eq!(retention_cutoff(5, Some(10)), 0);
+		assert_eq!(retention_cutoff(100, Some(0)), 101);
+	}
+
+	#[test]
+	fn disk_budget_cutoff_prunes_excess_blocks() {
+		assert_eq!(disk_budget_cutoff(100, 100, 0, 99), 0);
+		assert_eq!(disk_budget_cutoff(200, 100, 0, 99), 50);
+		assert_eq!(disk_budget_cutoff(200, 100, 50, 99), 75);
+		assert_eq!(disk_budget_cutoff(200, 0, 0, 99), 99);
+		assert_eq!(disk_budget_cutoff(200, 100, 99, 99), 0);
+	}
+
+	#[test]
+	fn prune_deletes_blocks_outside_retention() {
+		let db = MemoryDB::default();
+		for block_number in 0..10 {
+			db.put(Key::BlockHeader(block_number), block_number)
+				.unwrap();
+			db.put(Key::VerifiedCellCount(block_number), block_number)
+				.unwrap();
+			db.put(Key::AppData(1, block_number), block_number).unwrap();
+			db.put(Key::RetryBlock(block_number), block_number).unwrap();
+			db.put(Key::Cell(block_number, 0, 0), block_number).unwrap();
+		}
+		let mut processed = BlockSet::default();
+		processed.insert_range(0, 9);
+		db.put(Key::SyncCursor, SyncCursor { processed }).unwrap();
+
+		let cfg = RetentionConfig {
+			app_id: Some(1),
+			header_retention_blocks: Some(5),
+			app_data_retention_blocks: Some(3),
+			confidence_retention_blocks: None,
+			max_disk_size: None,
+			pruning_interval: 1,
+		};
+
+		let mut pruner = Pruner::default();
+		assert_eq!(pruner.prune(&db, &cfg, 9, 0).unwrap(), 7);
+		assert!(db.get::<u32>(Key::BlockHeader(4)).unwrap().is_none());
+		assert!(db.get::<u32>(Key::BlockHeader(5)).unwrap().is_some());
+		assert!(db.get::<u32>(Key::AppData(1, 6)).unwrap().is_none());
+		assert!(db.get::<u32>(Key::AppData(1, 7)).unwrap().is_some());
+		assert!(db.get::<u32>(Key::VerifiedCellCount(0)).unwrap().is_some());
+
+		let cursor = db.get::<SyncCursor>(Key::SyncCursor).unwrap().unwrap();
+		assert_eq!(cursor.processed.first(), Some(7));
+		assert_eq!(cursor.processed.last(), Some(9));
+
+		let cfg = RetentionConfig {
+			confidence_retention_blocks: Some(5),
+			..cfg
+		};
+		pruner.prune(&db, &cfg, 9, 0).unwrap();
+		assert!(db.get::<u32>(Key::RetryBlock(4)).unwrap().is_none());
+		assert!(db.get::<u32>(Key::Cell(4, 0, 0)).unwrap().is_none());
+		assert!(db.get::<u32>(Key::Cell(5, 0, 0)).unwrap().is_some());
+	}
+
+	/// Database which reports the same size, like the storage which doesn't reclaim the space of deleted values
+	struct FixedSize(MemoryDB);
+
+	impl Database for FixedSize {
+		type Key = <MemoryDB as Database>::Key;
+
+		fn put<T>(&self, key: Key, value: T) -> Result<()>
+		where
+			T: Serialize + Encode,
+		{
+			self.0.put(key, value)
+		}
+
+		fn get<T>(&self, key: Key) -> Result<Option<T>>
+		where
+			for<'a> T: Deserialize<'a> + Decode,
+		{
+			self.0.get(key)
+		}
+
+		fn delete(&self, key: Key) -> Result<()> {
+			self.0.delete(key)
+		}
+
+		fn get_range<T>(&self, range: KeyRange) -> Result<Vec<(Key, T)>>
+		where
+			for<'a> T: Deserialize<'a> + Decode,
+		{
+			self.0.get_range(range)
+		}
+
+		fn delete_range(&self, range: KeyRange) -> Result<()> {
+			self.0.delete_range(range)
+		}
+
+		fn compact_range(&self, range: KeyRange) -> Result<()> {
+			self.0.compact_range(range)
+		}
+
+		fn size(&self) -> Result<u64> {
+			Ok(200)
+		}
+	}
+
+	#[test]
+	fn budget_cutoff_waits_for_reflected_size() {
+		let db = FixedSize(MemoryDB::default());
+		for block_number in 0..100 {
+			db.put(Key::BlockHeader(block_number), block_number)
+				.unwrap();
+		}
+		let cfg = RetentionConfig {
+			app_id: None,
+			header_retention_blocks: None,
+			app_data_retention_blocks: None,
+			confidence_retention_blocks: None,
+			max_disk_size: Some(100),
+			pruning_interval: 1,
+		};
+
+		let mut pruner = Pruner::default();
+		assert_eq!(pruner.prune(&db, &cfg, 99, 0).unwrap(), 50);
+		// Size didn't change, so the same excess is not pruned again
+		assert_eq!(pruner.prune(&db, &cfg, 99, 50).unwrap(), 50);
+		assert!(db.get::<u32>(Key::BlockHeader(49)).unwrap().is_none());
+		assert!(db.get::<u32>(Key::BlockHeader(50)).unwrap().is_some());
+	}
+
+	#[test]
+	fn memory_storage_is_bounded_by_retention() {
+		let cfg = RuntimeConfig {
+			storage_backend: StorageBackend::Memory,
+			memory_db_max_size: 1000,
+			..Default::default()
+		};
+		let retention = RetentionConfig::from(&cfg);
+		assert!(retention.is_enabled());
+		assert_eq!(retention.max_disk_size, Some(1000));
+
+		let cfg = RuntimeConfig {
+			max_disk_size: Some(100),
+			..cfg
+		};
+		assert_eq!(RetentionConfig::from(&cfg).max_disk_size, Some(100));
+
+		let cfg = RuntimeConfig {
+			storage_backend: StorageBackend::RocksDB,
+			max_disk_size: None,
+			..cfg
+		};
+		assert!(!RetentionConfig::from(&cfg).is_enabled());
+	}
+
+	#[test]
+	fn pruned_blocks_are_removed_from_state() {
+		let mut state = State {
+			latest: 20,
+			header_verified: BlockRange::from_set(&blocks(10, 20)),
+			confidence_achieved: BlockRange::from_set(&blocks(10, 19)),
+			sync_header_verified: BlockRange::from_set(&blocks(0, 9)),
+			confidence_failed: blocks(3, 12),
+			..Default::default()
+		};
+
+		state.prune(12);
+		assert_eq!(state.oldest_available, Some(12));
+		assert_eq!(state.header_verified.first(), Some(12));
+		assert_eq!(state.confidence_achieved.first(), Some(12));
+		assert!(state.sync_header_verified.is_none());
+		assert!(!state.confidence_failed.contains(11));
+		assert!(state.confidence_failed.contains(12));
+
+		// Oldest available block is never moved back
+		state.prune(5);
+		assert_eq!(state.oldest_available, Some(12));
+	}
+
+	fn blocks(first: u32, last: u32) -> BlockSet {
+		let mut blocks = BlockSet::default();
+		blocks.insert_range(first, last);
+		blocks
+	}
+}
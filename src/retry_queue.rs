@@ -0,0 +1,370 @@
+//! Persistent queue of blocks which failed sampling.
+//!
+//! Blocks which failed sampling in light or sync client are queued in the database,
+//! and re-sampled with backoff given by the retry configuration,
+//! until the confidence is achieved, retries are exhausted or the deadline is reached.
+//! Block which achieves confidence on retry is recorded in the state and sent to the consumers,
+//! the same way as on the first successful sampling.
+
+use avail_subxt::primitives::Header;
+use codec::{Decode, Encode};
+use color_eyre::{
+	eyre::{eyre, WrapErr},
+	Result,
+};
+use kate_recovery::{commitments, matrix::Dimensions};
+use serde::{Deserialize, Serialize};
+use std::{
+	sync::{Arc, Mutex},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+use crate::{
+	confidence::{self, Confidence, SamplingResult},
+	data::{Database, Key, KeyRange},
+	header_chain, light_client,
+	network::{self, rpc},
+	telemetry::{MetricCounter, MetricValue, Metrics},
+	types::{BlockVerified, OptionBlockRange, RetryQueueConfig, State},
+	utils::extract_kate,
+};
+
+/// Interval in which the queue is checked for blocks due for re-sampling
+const QUEUE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Block queued for re-sampling
+#[derive(Clone, Debug, PartialEq, Decode, Encode, Serialize, Deserialize)]
+pub struct RetryEntry {
+	/// Number of re-sampling attempts
+	pub attempts: u32,
+	/// Time when block failed sampling, in milliseconds since UNIX epoch
+	pub failed_at: u64,
+	/// Time of the next re-sampling attempt, in milliseconds since UNIX epoch
+	pub next_attempt_at: u64,
+}
+
+fn now() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|duration| duration.as_millis() as u64)
+		.unwrap_or(0)
+}
+
+/// Queues block for re-sampling, if it is not already queued.
+pub fn enqueue(db: &impl Database, block_number: u32) -> Result<()> {
+	if db
+		.get::<RetryEntry>(Key::RetryBlock(block_number))?
+		.is_some()
+	{
+		return Ok(());
+	}
+	let now = now();
+	let entry = RetryEntry {
+		attempts: 0,
+		failed_at: now,
+		next_attempt_at: now,
+	};
+	db.put(Key::RetryBlock(block_number), entry)
+}
+
+/// Gets all queued blocks, ordered by block number.
+pub fn list(db: &impl Database) -> Result<Vec<(u32, RetryEntry)>> {
+	let entries = db.get_range::<RetryEntry>(KeyRange::RetryBlock(0..u32::MAX))?;
+	Ok(entries
+		.into_iter()
+		.filter_map(|(key, entry)| match key {
+			Key::RetryBlock(block_number) => Some((block_number, entry)),
+			_ => None,
+		})
+		.collect())
+}
+
+/// Returns next entry state after failed attempt, or `None` if retries are exhausted.
+fn next_entry(cfg: &RetryQueueConfig, entry: &RetryEntry, now: u64) -> Option<RetryEntry> {
+	let deadline = entry.failed_at + cfg.deadline.as_millis() as u64;
+	let delay = cfg
+		.retry_config
+		.clone()
+		.into_iter()
+		.nth(entry.attempts as usize)?;
+	let next_attempt_at = now + delay.as_millis() as u64;
+	(next_attempt_at <= deadline).then_some(RetryEntry {
+		attempts: entry.attempts + 1,
+		failed_at: entry.failed_at,
+		next_attempt_at,
+	})
+}
+
+/// Re-samples the block and stores confidence if achieved.
+/// Returns block header and confidence, or `None` if confidence is not achieved.
+async fn resample(
+	db: &impl Database,
+	network_client: &impl network::Client,
+	rpc_client: &rpc::Client,
+	cfg: &RetryQueueConfig,
+	latest: u32,
+	block_number: u32,
+) -> Result<Option<(Header, Confidence)>> {
+	// Fetched header is stored only if it links to the trusted headers, up to the latest block.
+	// Blocks are re-sampled one at a time, so the linking lock is not shared.
+	let linking = tokio::sync::Mutex::default();
+	let (header, header_hash) =
+		header_chain::get_linked_header(db, rpc_client, &linking, None, latest, block_number)
+			.await
+			.wrap_err("Failed to get block header")?;
+
+	let (rows, cols, _, commitment) = extract_kate(&header.extension);
+	let dimensions = Dimensions::new(rows, cols).ok_or_else(|| eyre!("Invalid dimensions"))?;
+	let commitments = commitments::from_slice(&commitment)?;
+
+	// Blocks are re-sampled adaptively, the same way as on the first attempt
+	let required = (cfg.sampling_strategy).cell_count(dimensions, cfg.confidence);
+	let (positions, fetched, unfetched, fetch_stats) = light_client::fetch_verified_adaptive(
+		network_client,
+		&*cfg.sampling_strategy,
+		cfg.max_sampling_rounds,
+		cfg.max_sampled_cells,
+		block_number,
+		header_hash,
+		dimensions,
+		&commitments,
+		required,
+	)
+	.await?;
+
+	let sampling_result = SamplingResult {
+		requested: positions.len() as u32,
+		verified: fetched.len() as u32,
+		invalid_proofs: fetch_stats.invalid_proofs as u32,
+	};
+	let confidence = confidence::calculate(&*cfg.sampling_strategy, dimensions, sampling_result);
+
+	let required_confidence =
+		confidence::required(&*cfg.sampling_strategy, dimensions, cfg.confidence);
+	if !confidence.is_achieved(required_confidence) {
+		warn!(
+			block_number,
+			invalid_proofs = sampling_result.invalid_proofs,
+			"Failed to fetch {} cells",
+			unfetched.len()
+		);
+		return Ok(None);
+	}
+
+	db.put(Key::BlockConfidence(block_number), confidence.clone())
+		.wrap_err("Failed to store Block Confidence")?;
+	db.put(
+		Key::VerifiedCellCount(block_number),
+		sampling_result.verified,
+	)
+	.wrap_err("Failed to store Confidence Factor")?;
+
+	Ok(Some((header, confidence)))
+}
+
+/// Records that the header is verified and confidence is achieved for the block.
+/// Block is inserted into the ranges without covering other blocks, since they might not be verified.
+fn set_confidence_achieved(state: &mut State, block_number: u32) {
+	state.header_failed.remove(block_number);
+	state.confidence_failed.remove(block_number);
+	let is_sync = state
+		.header_verified
+		.first()
+		.map_or(false, |first| block_number < first);
+	let (header_verified, confidence_achieved) = match is_sync {
+		true => (
+			&mut state.sync_header_verified,
+			&mut state.sync_confidence_achieved,
+		),
+		false => (&mut state.header_verified, &mut state.confidence_achieved),
+	};
+	header_verified.insert(block_number);
+	confidence_achieved.insert(block_number);
+}
+
+/// Re-samples queued blocks which are due.
+/// Block which achieves confidence is recorded in the state first, the same way as in the light client,
+/// and then sent to the consumers. Since the block is older than the latest blocks already sent,
+/// consumers of the verified blocks must not expect them in the block order.
+async fn process_queue(
+	db: &impl Database,
+	network_client: &impl network::Client,
+	rpc_client: &rpc::Client,
+	cfg: &RetryQueueConfig,
+	metrics: &Arc<impl Metrics>,
+	state: &Mutex<State>,
+	block_sender: &broadcast::Sender<BlockVerified>,
+) -> Result<()> {
+	let entries = list(db)?;
+	metrics
+		.record(MetricValue::RetryQueueLength(entries.len()))
+		.await?;
+
+	for (block_number, entry) in entries {
+		if entry.next_attempt_at > now() {
+			continue;
+		}
+
+		info!(
+			block_number,
+			attempt = entry.attempts + 1,
+			"Re-sampling block"
+		);
+		let latest = state.lock().expect("Lock should be acquired").latest;
+		let result = resample(db, network_client, rpc_client, cfg, latest, block_number).await;
+
+		if let Ok(Some((header, confidence))) = result {
+			db.delete(Key::RetryBlock(block_number))?;
+			set_confidence_achieved(
+				&mut state.lock().expect("Lock should be acquired"),
+				block_number,
+			);
+			metrics.count(MetricCounter::BlockRetrySucceeded).await;
+			info!(
+				block_number,
+				count = confidence.result.verified,
+				"Block re-sampling succeeded"
+			);
+			match BlockVerified::try_from((header, Some(confidence))) {
+				Ok(block) => {
+					if let Err(error) = block_sender.send(block) {
+						error!("Cannot send block verified message: {error}");
+					}
+				},
+				Err(error) => error!(block_number, "Cannot create message from header: {error:#}"),
+			}
+			continue;
+		}
+
+		if let Err(error) = result {
+			warn!(block_number, "Block re-sampling failed: {error:#}");
+		}
+
+		match next_entry(cfg, &entry, now()) {
+			Some(next_entry) => db.put(Key::RetryBlock(block_number), next_entry)?,
+			None => {
+				db.delete(Key::RetryBlock(block_number))?;
+				metrics.count(MetricCounter::BlockRetryGivenUp).await;
+				warn!(block_number, "Block re-sampling given up");
+			},
+		}
+	}
+	Ok(())
+}
+
+/// Runs retry queue, re-sampling blocks which failed sampling.
+pub async fn run(
+	db: impl Database,
+	network_client: impl network::Client,
+	rpc_client: rpc::Client,
+	cfg: RetryQueueConfig,
+	metrics: Arc<impl Metrics>,
+	state: Arc<Mutex<State>>,
+	block_sender: broadcast::Sender<BlockVerified>,
+) {
+	info!("Starting retry queue...");
+
+	let mut interval = tokio::time::interval(QUEUE_CHECK_INTERVAL);
+	loop {
+		interval.tick().await;
+		let result = process_queue(
+			&db,
+			&network_client,
+			&rpc_client,
+			&cfg,
+			&metrics,
+			&state,
+			&block_sender,
+		)
+		.await;
+		if let Err(error) = result {
+			error!("Cannot process retry queue: {error:#}");
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{enqueue, list, next_entry, set_confidence_achieved, RetryEntry};
+	use crate::{
+		data::mem_db::MemoryDB,
+		sampling,
+		types::{
+			BlockRange, FibonacciConfig, OptionBlockRange, RetryConfig, RetryQueueConfig,
+			SamplingStrategyConfig, State,
+		},
+	};
+	use std::time::Duration;
+
+	#[test]
+	fn enqueue_keeps_existing_entry() {
+		let db = MemoryDB::default();
+		enqueue(&db, 10).unwrap();
+		enqueue(&db, 5).unwrap();
+		let entries = list(&db).unwrap();
+		assert_eq!(entries.len(), 2);
+		assert_eq!(entries[0].0, 5);
+		assert_eq!(entries[1].0, 10);
+
+		enqueue(&db, 10).unwrap();
+		assert_eq!(list(&db).unwrap()[1], entries[1]);
+	}
+
+	#[test]
+	fn next_entry_respects_retries_and_deadline() {
+		let cfg = RetryQueueConfig {
+			confidence: 99.9,
+			sampling_strategy: sampling::new(SamplingStrategyConfig::Uniform),
+			max_sampling_rounds: 1,
+			max_sampled_cells: 0,
+			retry_config: RetryConfig::Fibonacci(FibonacciConfig {
+				base: 1,
+				max_delay: 10,
+				retries: 2,
+			}),
+			deadline: Duration::from_secs(60),
+		};
+		let entry = RetryEntry {
+			attempts: 0,
+			failed_at: 0,
+			next_attempt_at: 0,
+		};
+
+		let entry = next_entry(&cfg, &entry, 0).unwrap();
+		assert_eq!(entry.attempts, 1);
+		let entry = next_entry(&cfg, &entry, 0).unwrap();
+		assert_eq!(entry.attempts, 2);
+		assert!(next_entry(&cfg, &entry, 0).is_none());
+
+		let entry = RetryEntry {
+			attempts: 0,
+			failed_at: 0,
+			next_attempt_at: 0,
+		};
+		assert!(next_entry(&cfg, &entry, 60_000).is_none());
+	}
+
+	#[test]
+	fn retried_block_is_verified_without_covering_other_blocks() {
+		let mut state = State {
+			header_verified: Some(BlockRange::init(10)),
+			confidence_achieved: Some(BlockRange::init(10)),
+			..Default::default()
+		};
+		state.header_failed.insert(5);
+		state.confidence_failed.insert(13);
+
+		set_confidence_achieved(&mut state, 13);
+		assert!(state.confidence_achieved.contains(13));
+		assert!(!state.confidence_achieved.contains(12));
+		assert!(!state.confidence_failed.contains(13));
+
+		// Block before the first verified header is synced block
+		set_confidence_achieved(&mut state, 5);
+		assert!(!state.header_failed.contains(5));
+		assert!(state.sync_header_verified.contains(5));
+		assert!(state.sync_confidence_achieved.contains(5));
+	}
+}
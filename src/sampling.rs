@@ -0,0 +1,420 @@
+//! Cell selection strategies for data availability sampling.
+//!
+//! Each strategy selects positions of the cells to sample, and calculates confidence
+//! achieved by verifying the selected cells, so the confidence matches the strategy used.
+//!
+//! # Strategies
+//!
+//! * Uniform - cells are selected uniformly at random from the extended matrix
+//! * Row stratified - cells are spread evenly over the extended rows
+//! * Column stratified - cells are spread evenly over the columns
+//! * Seeded - cells are selected uniformly, using random generator seeded with configured seed and block number
+
+use avail_subxt::primitives::Header;
+use color_eyre::{eyre::WrapErr, Result};
+use kate_recovery::matrix::{Dimensions, Position};
+use rand::{seq::index, thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+use std::{collections::HashSet, sync::Arc};
+
+use crate::{
+	confidence::Confidence,
+	data::{Database, Key},
+	network::rpc,
+	types::SamplingStrategyConfig,
+	utils::{calculate_confidence, extract_kate},
+};
+
+pub trait SamplingStrategy: Send + Sync {
+	/// Returns name of the strategy
+	fn name(&self) -> &'static str;
+
+	/// Returns number of cells required to achieve given confidence
+	fn cell_count(&self, dimensions: Dimensions, confidence: f64) -> u32;
+
+	/// Selects positions of the cells to sample
+	fn select_cells(&self, block_number: u32, dimensions: Dimensions, count: u32) -> Vec<Position>;
+
+	/// Calculates confidence achieved by verifying given number of cells
+	fn confidence(&self, dimensions: Dimensions, count: u32) -> f64;
+
+	/// Selects positions of additional cells uniformly at random from the cells which are not already selected.
+	/// Returns less than `count` positions if there are not enough cells left to select.
+	fn select_additional_cells(
+		&self,
+		_block_number: u32,
+		dimensions: Dimensions,
+		selected: &[Position],
+		count: u32,
+	) -> Vec<Position> {
+		untried_cells(&mut thread_rng(), dimensions, selected, count)
+	}
+
+	/// Selects positions of the cells required to achieve given confidence
+	fn sample(&self, block_number: u32, dimensions: Dimensions, confidence: f64) -> Vec<Position> {
+		let count = self.cell_count(dimensions, confidence);
+		self.select_cells(block_number, dimensions, count)
+	}
+}
+
+/// Selects cells without replacement from the cells of the extended matrix which are not already selected
+fn untried_cells(
+	rng: &mut impl Rng,
+	dimensions: Dimensions,
+	selected: &[Position],
+	count: u32,
+) -> Vec<Position> {
+	let cols: u16 = dimensions.cols().get();
+	let untried = (0..dimensions.extended_rows())
+		.flat_map(|row| (0..cols).map(move |col| Position { row, col }))
+		.filter(|position| !selected.contains(position))
+		.collect::<Vec<_>>();
+	index::sample(rng, untried.len(), (count as usize).min(untried.len()))
+		.into_iter()
+		.map(|index| untried[index])
+		.collect()
+}
+
+/// Creates sampling strategy from the configuration
+pub fn new(config: SamplingStrategyConfig) -> Arc<dyn SamplingStrategy> {
+	match config {
+		SamplingStrategyConfig::Uniform => Arc::new(Uniform),
+		SamplingStrategyConfig::RowStratified => Arc::new(RowStratified),
+		SamplingStrategyConfig::ColumnStratified => Arc::new(ColumnStratified),
+		SamplingStrategyConfig::Seeded { seed } => Arc::new(Seeded { seed }),
+	}
+}
+
+/// Returns number of cells required to achieve given confidence, up to the number of cells in the extended matrix
+fn required_cells(dimensions: Dimensions, confidence: f64) -> u32 {
+	rpc::cell_count_for_confidence(confidence).min(dimensions.extended_size())
+}
+
+/// Confidence achieved by verifying given number of distinct cells, as for the independent samples.
+/// Block is available if all cells of the extended matrix are verified.
+fn sampled_confidence(dimensions: Dimensions, count: u32) -> f64 {
+	if count >= dimensions.extended_size() {
+		return 100f64;
+	}
+	calculate_confidence(count)
+}
+
+/// Selects cells spread evenly over the strata, e.g. rows or columns, without replacement.
+/// Each round selects cells from the distinct strata, so the stratum is sampled again only after all strata are sampled.
+/// Cell is created from the stratum and the offset within the stratum.
+fn stratified_cells(
+	strata: u32,
+	stratum_size: u32,
+	count: u32,
+	cell: impl Fn(u32, u32) -> Position,
+) -> Vec<Position> {
+	let mut rng = thread_rng();
+	let count = count.min(strata * stratum_size) as usize;
+	let mut selected = HashSet::new();
+	let mut cells = Vec::with_capacity(count);
+	while cells.len() < count {
+		let round = (count - cells.len()).min(strata as usize);
+		for stratum in index::sample(&mut rng, strata as usize, round) {
+			// Stratum is sampled at most once per round, so it has cells which are not selected
+			loop {
+				let position = cell(stratum as u32, rng.gen_range(0..stratum_size));
+				if selected.insert(position) {
+					cells.push(position);
+					break;
+				}
+			}
+		}
+	}
+	cells
+}
+
+/// Cells are selected uniformly at random from the extended matrix, without replacement.
+/// Adversary can make the block unavailable by withholding less than a half of the extended matrix,
+/// so confidence is not derived from the matrix size, and it is calculated as for the independent samples,
+/// unless all cells of the extended matrix are verified.
+pub struct Uniform;
+
+impl SamplingStrategy for Uniform {
+	fn name(&self) -> &'static str {
+		"uniform"
+	}
+
+	fn cell_count(&self, dimensions: Dimensions, confidence: f64) -> u32 {
+		required_cells(dimensions, confidence)
+	}
+
+	fn select_cells(&self, _: u32, dimensions: Dimensions, count: u32) -> Vec<Position> {
+		rpc::generate_random_cells(dimensions, count)
+	}
+
+	fn confidence(&self, dimensions: Dimensions, count: u32) -> f64 {
+		sampled_confidence(dimensions, count)
+	}
+}
+
+/// Cells are spread evenly over the extended rows, in random columns.
+/// Since rows are extended column-wise, adversary can make the block unavailable by withholding
+/// half of the columns in all rows. Columns in different rows are selected independently, so each verified cell
+/// only halves the probability of missing withheld data, regardless of the rows being distinct.
+/// If more cells are required than there are extended rows, rows are sampled again in the following rounds.
+pub struct RowStratified;
+
+impl SamplingStrategy for RowStratified {
+	fn name(&self) -> &'static str {
+		"row-stratified"
+	}
+
+	fn cell_count(&self, dimensions: Dimensions, confidence: f64) -> u32 {
+		required_cells(dimensions, confidence)
+	}
+
+	fn select_cells(&self, _: u32, dimensions: Dimensions, count: u32) -> Vec<Position> {
+		let cols = dimensions.cols().get().into();
+		stratified_cells(dimensions.extended_rows(), cols, count, |row, col| {
+			Position {
+				row,
+				col: col as u16,
+			}
+		})
+	}
+
+	fn confidence(&self, dimensions: Dimensions, count: u32) -> f64 {
+		sampled_confidence(dimensions, count)
+	}
+}
+
+/// Cells are spread evenly over the columns, in random extended rows.
+/// Since columns are not extended, withheld data can be spread over all columns,
+/// so each verified cell halves the probability that the block is unavailable, as for the independent samples.
+/// If more cells are required than there are columns, columns are sampled again in the following rounds.
+pub struct ColumnStratified;
+
+impl SamplingStrategy for ColumnStratified {
+	fn name(&self) -> &'static str {
+		"column-stratified"
+	}
+
+	fn cell_count(&self, dimensions: Dimensions, confidence: f64) -> u32 {
+		required_cells(dimensions, confidence)
+	}
+
+	fn select_cells(&self, _: u32, dimensions: Dimensions, count: u32) -> Vec<Position> {
+		let cols = dimensions.cols().get().into();
+		stratified_cells(cols, dimensions.extended_rows(), count, |col, row| {
+			Position {
+				row,
+				col: col as u16,
+			}
+		})
+	}
+
+	fn confidence(&self, dimensions: Dimensions, count: u32) -> f64 {
+		sampled_confidence(dimensions, count)
+	}
+}
+
+/// Cells are selected uniformly, using random generator seeded with configured seed and block number,
+/// so the same cells are selected for the same block in each run. Intended for reproducible test runs.
+pub struct Seeded {
+	pub seed: u64,
+}
+
+impl SamplingStrategy for Seeded {
+	fn name(&self) -> &'static str {
+		"seeded"
+	}
+
+	fn cell_count(&self, dimensions: Dimensions, confidence: f64) -> u32 {
+		required_cells(dimensions, confidence)
+	}
+
+	fn select_cells(&self, block_number: u32, dimensions: Dimensions, count: u32) -> Vec<Position> {
+		let mut rng = ChaChaRng::seed_from_u64(self.seed ^ u64::from(block_number));
+		rpc::random_cells(&mut rng, dimensions, count)
+	}
+
+	fn select_additional_cells(
+		&self,
+		block_number: u32,
+		dimensions: Dimensions,
+		selected: &[Position],
+		count: u32,
+	) -> Vec<Position> {
+		// Number of selected cells is mixed into the seed, so each round selects different cells
+		let seed = self.seed ^ u64::from(block_number) ^ ((selected.len() as u64) << 32);
+		untried_cells(
+			&mut ChaChaRng::seed_from_u64(seed),
+			dimensions,
+			selected,
+			count,
+		)
+	}
+
+	fn confidence(&self, dimensions: Dimensions, count: u32) -> f64 {
+		Uniform.confidence(dimensions, count)
+	}
+}
+
+/// Gets confidence of the stored block, or calculates it from the number of verified cells
+/// if confidence is not stored. If block header is not stored either, confidence is calculated
+/// as for the independent samples, which is the lower bound for all strategies.
+pub fn stored_confidence(
+	db: &impl Database,
+	strategy: &dyn SamplingStrategy,
+	block_number: u32,
+	count: u32,
+) -> Result<f64> {
+	let confidence = db
+		.get::<Confidence>(Key::BlockConfidence(block_number))
+		.wrap_err("Failed to get block confidence")?;
+
+	if let Some(confidence) = confidence {
+		return Ok(confidence.value);
+	}
+
+	let header = db
+		.get::<Header>(Key::BlockHeader(block_number))
+		.wrap_err("Failed to get block header")?;
+
+	let dimensions = header.and_then(|header| {
+		let (rows, cols, _, _) = extract_kate(&header.extension);
+		Dimensions::new(rows, cols)
+	});
+
+	Ok(match dimensions {
+		Some(dimensions) => strategy.confidence(dimensions, count),
+		None => calculate_confidence(count),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ColumnStratified, RowStratified, SamplingStrategy, Seeded, Uniform};
+	use crate::utils::calculate_confidence;
+	use kate_recovery::matrix::Dimensions;
+	use std::collections::HashSet;
+
+	fn dimensions() -> Dimensions {
+		Dimensions::new(16, 32).unwrap()
+	}
+
+	#[test]
+	fn row_stratified_cells_are_in_distinct_rows() {
+		let cells = RowStratified.select_cells(1, dimensions(), 10);
+		let rows = cells.iter().map(|cell| cell.row).collect::<HashSet<_>>();
+		assert_eq!(cells.len(), 10);
+		assert_eq!(rows.len(), 10);
+		assert!(rows.iter().all(|&row| row < dimensions().extended_rows()));
+
+		// Rows are sampled again, once all rows are sampled
+		let cells = RowStratified.select_cells(1, dimensions(), 100);
+		assert_eq!(cells.len(), 100);
+		assert_eq!(cells.iter().collect::<HashSet<_>>().len(), 100);
+		for row in 0..dimensions().extended_rows() {
+			let count = cells.iter().filter(|cell| cell.row == row).count();
+			assert!((3..=4).contains(&count));
+		}
+	}
+
+	#[test]
+	fn column_stratified_cells_are_in_distinct_columns() {
+		let cells = ColumnStratified.select_cells(1, dimensions(), 10);
+		let cols = cells.iter().map(|cell| cell.col).collect::<HashSet<_>>();
+		assert_eq!(cells.len(), 10);
+		assert_eq!(cols.len(), 10);
+
+		// Extended matrix of 2 rows and 4 columns is sampled entirely
+		let dimensions = Dimensions::new(1, 4).unwrap();
+		let cells = ColumnStratified.select_cells(1, dimensions, 10);
+		assert_eq!(cells.len(), 8);
+		assert_eq!(cells.iter().collect::<HashSet<_>>().len(), 8);
+	}
+
+	#[test]
+	fn seeded_cells_are_reproducible() {
+		let strategy = Seeded { seed: 42 };
+		let cells = strategy.select_cells(1, dimensions(), 10);
+		assert_eq!(cells.len(), 10);
+		assert_eq!(cells, strategy.select_cells(1, dimensions(), 10));
+		assert_ne!(cells, strategy.select_cells(2, dimensions(), 10));
+	}
+
+	#[test]
+	fn additional_cells_are_not_selected() {
+		let strategy = Seeded { seed: 42 };
+		let selected = strategy.select_cells(1, dimensions(), 10);
+		let additional = strategy.select_additional_cells(1, dimensions(), &selected, 5);
+		assert_eq!(additional.len(), 5);
+		assert!(additional.iter().all(|cell| !selected.contains(cell)));
+		assert_eq!(
+			additional,
+			strategy.select_additional_cells(1, dimensions(), &selected, 5)
+		);
+	}
+
+	#[test]
+	fn additional_cells_are_sampled_without_replacement() {
+		// Extended matrix of 2 rows and 4 columns
+		let dimensions = Dimensions::new(1, 4).unwrap();
+		let selected = Uniform.select_cells(1, dimensions, 3);
+
+		// Only 5 cells are left to select
+		let additional = Uniform.select_additional_cells(1, dimensions, &selected, 8);
+		assert_eq!(additional.len(), 5);
+		let distinct = additional.iter().collect::<HashSet<_>>();
+		assert_eq!(distinct.len(), additional.len());
+		assert!(additional.iter().all(|cell| !selected.contains(cell)));
+	}
+
+	#[test]
+	fn uniform_confidence_is_independent_of_dimensions() {
+		// Extended matrix of 2 rows and 4 columns
+		let small = Dimensions::new(1, 4).unwrap();
+		for count in 1..8 {
+			let confidence = Uniform.confidence(small, count);
+			assert_eq!(confidence, calculate_confidence(count));
+			assert_eq!(confidence, Uniform.confidence(dimensions(), count));
+		}
+		assert!(Uniform.confidence(dimensions(), 100) < 100f64);
+
+		// Block is available if all cells are verified
+		assert_eq!(Uniform.cell_count(small, 99.9), 8);
+		assert_eq!(Uniform.confidence(small, 8), 100f64);
+	}
+
+	#[test]
+	fn stratified_cell_count_achieves_confidence() {
+		let strategies: [&dyn SamplingStrategy; 2] = [&RowStratified, &ColumnStratified];
+		for strategy in strategies {
+			for dimensions in [dimensions(), Dimensions::new(1, 4).unwrap()] {
+				for confidence in [50f64, 90f64, 99.9f64, 99.99f64] {
+					let count = strategy.cell_count(dimensions, confidence);
+					assert!(strategy.confidence(dimensions, count) >= confidence);
+					let cells = strategy.select_cells(1, dimensions, count);
+					assert_eq!(cells.len(), count as usize);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn row_stratified_confidence_is_bounded_by_column_withholding() {
+		for count in 1..=10 {
+			let stratified = RowStratified.confidence(dimensions(), count);
+			assert_eq!(stratified, calculate_confidence(count));
+		}
+
+		let count = RowStratified.cell_count(dimensions(), 99.9);
+		assert_eq!(count, Uniform.cell_count(dimensions(), 99.9));
+		assert!(RowStratified.confidence(dimensions(), count) >= 99.9);
+
+		// All extended rows are sampled, but the block can still be unavailable
+		let dimensions = Dimensions::new(1, 4).unwrap();
+		assert_eq!(RowStratified.confidence(dimensions, 2), 75f64);
+		assert!(RowStratified.confidence(Dimensions::new(256, 4).unwrap(), 512) < 100f64);
+
+		// More cells than extended rows are required
+		assert_eq!(RowStratified.cell_count(dimensions, 99.9), 8);
+		assert_eq!(RowStratified.confidence(dimensions, 8), 100f64);
+	}
+}
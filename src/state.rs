@@ -0,0 +1,121 @@
+use color_eyre::{eyre::WrapErr, Result};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+	data::{Database, Key},
+	shutdown::Controller,
+	types::{BlockVerified, State, StateCheckpoint},
+};
+
+/// Restores state from the checkpoint stored in the database, if any.
+/// Checkpoint which cannot be decoded is ignored, and the state is rebuilt from scratch.
+pub fn restore(db: &impl Database, state: &Mutex<State>) -> Result<()> {
+	let checkpoint = match db.get::<StateCheckpoint>(Key::StateCheckpoint) {
+		Ok(Some(checkpoint)) => checkpoint,
+		Ok(None) => return Ok(()),
+		Err(error) => {
+			warn!("Cannot get state checkpoint, starting with empty state: {error:#}");
+			return Ok(());
+		},
+	};
+
+	info!(
+		latest = checkpoint.latest,
+		"Restoring state from checkpoint"
+	);
+	state
+		.lock()
+		.expect("Lock should be acquired")
+		.restore(checkpoint);
+	Ok(())
+}
+
+/// Stores current state checkpoint into the database.
+pub fn store(db: &impl Database, state: &Mutex<State>) -> Result<()> {
+	let checkpoint: StateCheckpoint = (&*state.lock().expect("Lock should be acquired")).into();
+	db.put(Key::StateCheckpoint, checkpoint)
+		.wrap_err("Failed to store state checkpoint")
+}
+
+/// Stores state checkpoint on every verified block.
+pub async fn run(
+	db: impl Database,
+	state: Arc<Mutex<State>>,
+	mut block_receiver: broadcast::Receiver<BlockVerified>,
+	shutdown: Controller<String>,
+) {
+	info!("Starting state checkpointing...");
+
+	loop {
+		let block_number = match block_receiver.recv().await {
+			Ok(block) => block.block_num,
+			// Checkpoint is stored on the next received block
+			Err(RecvError::Lagged(skipped)) => {
+				debug!(skipped, "Block receiver lagged");
+				continue;
+			},
+			Err(error) => {
+				let _ = shutdown.trigger_shutdown(format!("{error:#}"));
+				break;
+			},
+		};
+
+		if let Err(error) = store(&db, &state) {
+			error!(block_number, "{error:#}");
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{restore, store};
+	use crate::{
+		data::{mem_db::MemoryDB, Database, Key},
+		types::{OptionBlockRange, State},
+	};
+	use std::sync::Mutex;
+
+	#[test]
+	fn restored_state_records_gaps() {
+		let db = MemoryDB::default();
+
+		let state = Mutex::new(State::default());
+		{
+			let mut state = state.lock().unwrap();
+			state.latest = 10;
+			state.confidence_achieved.set(5);
+			state.confidence_achieved.set(10);
+		}
+		store(&db, &state).unwrap();
+
+		let state = Mutex::new(State::default());
+		restore(&db, &state).unwrap();
+
+		let mut state = state.lock().unwrap();
+		assert_eq!(state.latest, 10);
+		assert!(state.confidence_achieved.contains(10));
+
+		state.confidence_achieved.set(15);
+		assert!(state.confidence_achieved.contains(5));
+		assert!(state.confidence_achieved.contains(10));
+		assert!(!state.confidence_achieved.contains(11));
+		assert!(!state.confidence_achieved.contains(14));
+		assert!(state.confidence_achieved.contains(15));
+
+		state.confidence_achieved.set(16);
+		assert!(state.confidence_achieved.contains(16));
+		assert!(!state.confidence_achieved.contains(12));
+	}
+
+	#[test]
+	fn undecodable_checkpoint_is_ignored() {
+		let db = MemoryDB::default();
+		db.put(Key::StateCheckpoint, vec![1u8]).unwrap();
+
+		let state = Mutex::new(State::default());
+		restore(&db, &state).unwrap();
+		assert_eq!(state.lock().unwrap().latest, 0);
+	}
+}
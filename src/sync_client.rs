@@ -4,37 +4,44 @@
 //!
 //! # Flow
 //!
-//! * For each block, fetches block header from RPC and stores it into database
+//! * Plans blocks to sync, skipping blocks processed before the restart, in configured order
+//! * For each block, fetches block header from RPC and stores it into database,
+//!   if it is linked by parent hash to the finalized head
 //! * Generate random cells for random data sampling
 //! * Retrieve cell proofs from a) DHT and/or b) via RPC call from the node, in that order
 //! * Verify proof using the received cells
 //! * Calculate block confidence and store it in RocksDB
 //! * Insert cells to to DHT for remote fetch
+//! * Stores verified block into sync cursor, and updates sync progress
 //!
 //! # Notes
 //!
 //! In case RPC is disabled, RPC calls will be skipped.
+//! Blocks which failed are queued for retry, and synced again after the restart.
+//! Blocks are processed by the configured number of parallel workers.
 
 use crate::{
+	confidence::{self, Confidence, SamplingResult},
 	data::{Database, Key},
-	network::{
-		self,
-		rpc::{self, Client as RpcClient},
+	header_chain,
+	network::{self, rpc::Client as RpcClient},
+	retry_queue,
+	types::{
+		BlockRange, BlockSet, BlockVerified, OptionBlockRange, State, SyncClientConfig, SyncCursor,
+		SyncDirection, SyncProgress,
 	},
-	types::{BlockVerified, OptionBlockRange, State, SyncClientConfig},
-	utils::{calculate_confidence, extract_app_lookup, extract_kate},
+	utils::{extract_app_lookup, extract_kate},
 };
 
 use async_trait::async_trait;
 use avail_subxt::{primitives::Header as DaHeader, utils::H256};
-use codec::Encode;
 use color_eyre::{
 	eyre::{eyre, WrapErr},
 	Result,
 };
+use futures::stream::{self, StreamExt};
 use kate_recovery::{commitments, matrix::Dimensions};
 use mockall::automock;
-use sp_core::blake2_256;
 use std::{
 	ops::Range,
 	sync::{Arc, Mutex},
@@ -48,51 +55,46 @@ use tracing::{error, info, warn};
 pub trait Client {
 	async fn get_header_by_block_number(&self, block_number: u32) -> Result<(DaHeader, H256)>;
 	fn is_confidence_stored(&self, block_number: u32) -> Result<bool>;
-	fn store_confidence(&self, count: u32, block_number: u32) -> Result<()>;
+	fn store_confidence(&self, confidence: &Confidence, block_number: u32) -> Result<()>;
+	fn enqueue_retry(&self, block_number: u32) -> Result<()>;
+	fn get_sync_cursor(&self) -> Result<Option<SyncCursor>>;
+	fn store_sync_cursor(&self, cursor: &SyncCursor) -> Result<()>;
 }
 
 #[derive(Clone)]
 pub struct SyncClient<T: Database + Sync> {
 	db: T,
 	rpc_client: RpcClient,
+	/// Finality verified header which the synced headers are linked to
+	finalized_head: DaHeader,
+	linking: Arc<tokio::sync::Mutex<()>>,
 }
 
 impl<T: Database + Sync> SyncClient<T> {
-	pub fn new(db: T, rpc_client: RpcClient) -> Self {
-		SyncClient { db, rpc_client }
+	pub fn new(db: T, rpc_client: RpcClient, finalized_head: DaHeader) -> Self {
+		SyncClient {
+			db,
+			rpc_client,
+			finalized_head,
+			linking: Default::default(),
+		}
 	}
 }
 
 #[async_trait]
 impl<T: Database + Sync> Client for SyncClient<T> {
 	async fn get_header_by_block_number(&self, block_number: u32) -> Result<(DaHeader, H256)> {
-		if let Some(header) = self
-			.db
-			.get(Key::BlockHeader(block_number))
-			.wrap_err("Sync Client failed to get Block Header from the storage")?
-		{
-			let hash: H256 = Encode::using_encoded(&header, blake2_256).into();
-			return Ok((header, hash));
-		}
-
-		let (header, hash) = match self
-			.rpc_client
-			.get_header_by_block_number(block_number)
-			.await
-			.wrap_err_with(|| {
-				format!(
-					"Sync Client failed to get Block {block_number:#?} by Block Number from storage",
-				)
-			}) {
-			Ok(value) => value,
-			Err(error) => return Err(error),
-		};
-
-		self.db
-			.put(Key::BlockHeader(block_number), header.clone())
-			.wrap_err("Sync Client failed to store Block Header")?;
-
-		Ok((header, hash))
+		// Linking lock is shared, so concurrent workers don't walk the same part of the chain
+		header_chain::get_linked_header(
+			&self.db,
+			&self.rpc_client,
+			&self.linking,
+			Some(&self.finalized_head),
+			self.finalized_head.number,
+			block_number,
+		)
+		.await
+		.wrap_err("Sync Client failed to get linked Block Header")
 	}
 
 	fn is_confidence_stored(&self, block_number: u32) -> Result<bool> {
@@ -102,11 +104,34 @@ impl<T: Database + Sync> Client for SyncClient<T> {
 			.map(|c: Option<u32>| c.is_some())
 	}
 
-	fn store_confidence(&self, count: u32, block_number: u32) -> Result<()> {
+	fn store_confidence(&self, confidence: &Confidence, block_number: u32) -> Result<()> {
+		self.db
+			.put(Key::BlockConfidence(block_number), confidence.clone())
+			.wrap_err("Sync Client failed to store Block Confidence")?;
 		self.db
-			.put(Key::VerifiedCellCount(block_number), count)
+			.put(
+				Key::VerifiedCellCount(block_number),
+				confidence.result.verified,
+			)
 			.wrap_err("Sync Client failed to store Confidence Factor")
 	}
+
+	fn enqueue_retry(&self, block_number: u32) -> Result<()> {
+		retry_queue::enqueue(&self.db, block_number)
+			.wrap_err("Sync Client failed to queue block for retry")
+	}
+
+	fn get_sync_cursor(&self) -> Result<Option<SyncCursor>> {
+		self.db
+			.get(Key::SyncCursor)
+			.wrap_err("Sync Client failed to get Sync Cursor")
+	}
+
+	fn store_sync_cursor(&self, cursor: &SyncCursor) -> Result<()> {
+		self.db
+			.put(Key::SyncCursor, cursor.clone())
+			.wrap_err("Sync Client failed to store Sync Cursor")
+	}
 }
 
 async fn process_block(
@@ -116,7 +141,7 @@ async fn process_block(
 	header_hash: H256,
 	cfg: &SyncClientConfig,
 	block_verified_sender: broadcast::Sender<BlockVerified>,
-) -> Result<()> {
+) -> Result<Option<Confidence>> {
 	let block_number = header.number;
 	let begin = Instant::now();
 
@@ -130,11 +155,9 @@ async fn process_block(
 
 	let commitments = commitments::from_slice(&commitment)?;
 
-	// now this is in `u64`
-	let cell_count = rpc::cell_count_for_confidence(cfg.confidence);
-	let positions = rpc::generate_random_cells(dimensions, cell_count);
+	let positions = (cfg.sampling_strategy).sample(block_number, dimensions, cfg.confidence);
 
-	let (fetched, unfetched, _fetch_stats) = network_client
+	let (fetched, unfetched, fetch_stats) = network_client
 		.fetch_verified(
 			block_number,
 			header_hash,
@@ -144,33 +167,276 @@ async fn process_block(
 		)
 		.await?;
 
-	if positions.len() > fetched.len() {
+	let sampling_result = SamplingResult {
+		requested: positions.len().try_into()?,
+		verified: fetched.len().try_into()?,
+		invalid_proofs: fetch_stats.invalid_proofs.try_into()?,
+	};
+	let confidence = confidence::calculate(&*cfg.sampling_strategy, dimensions, sampling_result);
+
+	if confidence.invalid_proof {
+		warn!(
+			block_number,
+			invalid_proofs = sampling_result.invalid_proofs,
+			"Fetched cells with invalid proofs"
+		);
+	}
+
+	let required_confidence =
+		confidence::required(&*cfg.sampling_strategy, dimensions, cfg.confidence);
+	if !confidence.is_achieved(required_confidence) {
 		error!(block_number, "Failed to fetch {} cells", unfetched.len());
-		return Ok(());
+		return Ok(None);
 	}
 
 	// write confidence factor into on-disk database
-	client.store_confidence(fetched.len().try_into()?, block_number)?;
+	client.store_confidence(&confidence, block_number)?;
 
-	let confidence = Some(calculate_confidence(fetched.len() as u32));
-	let client_msg =
-		BlockVerified::try_from((header, confidence)).wrap_err("converting to message failed")?;
+	let confidence = Some(confidence);
+	let client_msg = BlockVerified::try_from((header, confidence.clone()))
+		.wrap_err("converting to message failed")?;
 
 	if let Err(error) = block_verified_sender.send(client_msg) {
 		error!("Cannot send block verified message: {error}");
 	}
 
-	Ok(())
+	Ok(confidence)
+}
+
+fn enqueue_retry(client: &impl Client, block_number: u32) {
+	if let Err(error) = client.enqueue_retry(block_number) {
+		error!(block_number, "{error:#}");
+	}
+}
+
+/// Plans the sync of the blocks in the sync range, skipping blocks verified before the restart.
+struct SyncPlanner {
+	sync_range: Range<u32>,
+	cursor: SyncCursor,
+	started_at: Instant,
+	/// Number of blocks processed since the planner is created
+	processed: u32,
+	/// Number of verified blocks within the sync range
+	verified: u32,
+}
+
+impl SyncPlanner {
+	fn new(sync_range: Range<u32>, cursor: SyncCursor) -> Self {
+		let mut planner = SyncPlanner {
+			sync_range,
+			cursor,
+			started_at: Instant::now(),
+			processed: 0,
+			verified: 0,
+		};
+		planner.verified = (planner.processed_blocks().intervals().iter())
+			.map(|(first, last)| last - first + 1)
+			.sum();
+		planner
+	}
+
+	/// Returns blocks in the sync range which are not verified yet, in the sync order
+	fn pending_blocks(&self, direction: SyncDirection) -> Vec<u32> {
+		let blocks =
+			(self.sync_range.clone()).filter(|&block| !self.cursor.processed.contains(block));
+		match direction {
+			SyncDirection::Forward => blocks.collect(),
+			SyncDirection::Backward => blocks.rev().collect(),
+		}
+	}
+
+	/// Returns verified blocks within the sync range
+	fn processed_blocks(&self) -> BlockSet {
+		let mut blocks = BlockSet::default();
+		let Some(last) = self.sync_range.end.checked_sub(1) else {
+			return blocks;
+		};
+		let intervals = self
+			.cursor
+			.processed
+			.intervals_within(self.sync_range.start, last);
+		for (first, last) in intervals {
+			blocks.insert_range(first, last);
+		}
+		blocks
+	}
+
+	/// Marks block as verified, and removes blocks pruned by the retention from the cursor
+	fn complete(&mut self, block_number: u32, oldest_available: u32) {
+		if !self.cursor.processed.contains(block_number) {
+			self.cursor.processed.insert(block_number);
+			self.processed += 1;
+			if self.sync_range.contains(&block_number) {
+				self.verified += 1;
+			}
+		}
+		if let Some(last_pruned) = oldest_available.checked_sub(1) {
+			self.cursor.processed.remove_range(0, last_pruned);
+		}
+	}
+
+	/// Returns sync progress, estimating remaining time based on blocks processed since the start
+	fn progress(&self) -> SyncProgress {
+		let total = self.sync_range.len() as u32;
+		let processed = self.verified;
+		let remaining = total.saturating_sub(processed);
+		let eta =
+			(self.processed > 0).then(|| self.started_at.elapsed() / self.processed * remaining);
+		SyncProgress {
+			total,
+			processed,
+			eta,
+		}
+	}
+}
+
+/// Merges blocks into the range, skipping the pruned blocks
+fn merge(
+	range: &Option<BlockRange>,
+	blocks: &BlockSet,
+	oldest_available: u32,
+) -> Option<BlockRange> {
+	let mut blocks = blocks.clone();
+	// Range can contain blocks verified by the retry queue
+	if let Some(range) = range {
+		for &(first, last) in range.blocks().intervals() {
+			blocks.insert_range(first, last);
+		}
+	}
+	BlockRange::from_set(&blocks).and_then(|range| range.prune(oldest_available))
+}
+
+/// Initializes the state with the blocks verified before the restart
+fn init_state(state: &mut State, planner: &SyncPlanner) {
+	let processed_blocks = planner.processed_blocks();
+	let oldest_available = state.oldest_available.unwrap_or(0);
+	state.sync_header_verified = merge(
+		&state.sync_header_verified,
+		&processed_blocks,
+		oldest_available,
+	);
+	state.sync_confidence_achieved = merge(
+		&state.sync_confidence_achieved,
+		&processed_blocks,
+		oldest_available,
+	);
+	state.sync_progress = Some(planner.progress());
+}
+
+/// Inserts the block into the range, unless the block is pruned already
+fn insert_available(range: &mut Option<BlockRange>, block_number: u32, oldest_available: u32) {
+	if block_number >= oldest_available {
+		range.insert(block_number);
+	}
+}
+
+/// Marks block as verified, so it is not synced again after the restart
+fn complete_block(
+	client: &impl Client,
+	block_number: u32,
+	state: &Mutex<State>,
+	planner: &Mutex<SyncPlanner>,
+) {
+	let oldest_available = state.lock().unwrap().oldest_available.unwrap_or(0);
+	let progress = {
+		let mut planner = planner.lock().unwrap();
+		planner.complete(block_number, oldest_available);
+		if let Err(error) = client.store_sync_cursor(&planner.cursor) {
+			error!(block_number, "{error:#}");
+		}
+		planner.progress()
+	};
+
+	let mut state = state.lock().unwrap();
+	let oldest_available = state.oldest_available.unwrap_or(0);
+	insert_available(
+		&mut state.sync_header_verified,
+		block_number,
+		oldest_available,
+	);
+	insert_available(
+		&mut state.sync_confidence_achieved,
+		block_number,
+		oldest_available,
+	);
+	state.sync_progress = Some(progress);
+}
+
+async fn sync_block(
+	client: &impl Client,
+	network_client: &impl network::Client,
+	cfg: &SyncClientConfig,
+	block_number: u32,
+	block_verified_sender: &broadcast::Sender<BlockVerified>,
+	state: &Mutex<State>,
+	planner: &Mutex<SyncPlanner>,
+) {
+	// Blocks processed before the sync cursor was introduced are skipped
+	match client.is_confidence_stored(block_number) {
+		Ok(false) => (),
+		Ok(true) => return complete_block(client, block_number, state, planner),
+		Err(error) => {
+			// TODO: Is it valid to have skipped block?
+			error!(block_number, "Cannot process block: {error:#}");
+			return;
+		},
+	};
+
+	let (header, header_hash) = match client.get_header_by_block_number(block_number).await {
+		Ok(value) => value,
+		Err(error) => {
+			error!(block_number, "Cannot process block: {error:#}");
+			state.lock().unwrap().header_failed.insert(block_number);
+			enqueue_retry(client, block_number);
+			return;
+		},
+	};
+
+	{
+		let mut state = state.lock().unwrap();
+		state.sync_latest = state.sync_latest.max(Some(block_number));
+		state.header_failed.remove(block_number);
+		let oldest_available = state.oldest_available.unwrap_or(0);
+		insert_available(
+			&mut state.sync_header_verified,
+			block_number,
+			oldest_available,
+		);
+	}
+
+	let process_block_result = process_block(
+		client,
+		network_client,
+		header,
+		header_hash,
+		cfg,
+		block_verified_sender.clone(),
+	)
+	.await;
+
+	if let Err(error) = &process_block_result {
+		error!(block_number, "Cannot process block: {error:#}");
+	}
+
+	if let Ok(Some(_)) = process_block_result {
+		state.lock().unwrap().confidence_failed.remove(block_number);
+		complete_block(client, block_number, state, planner);
+	} else {
+		state.lock().unwrap().confidence_failed.insert(block_number);
+		enqueue_retry(client, block_number);
+	}
 }
 
 /// Runs sync client.
 ///
 /// # Arguments
 ///
+/// * `client` - Sync client implementation
+/// * `network_client` - Network client used to fetch cells
 /// * `cfg` - Sync client configuration
-/// * `start_block` - Sync start block
-/// * `end_block` - Sync end block
-/// * `block_verified_sender` - Optional channel to send verified blocks
+/// * `sync_range` - Range of blocks to sync
+/// * `block_verified_sender` - Channel to send verified blocks
+/// * `state` - Processed blocks state
 pub async fn run(
 	client: impl Client,
 	network_client: impl network::Client,
@@ -188,53 +454,43 @@ pub async fn run(
 		warn!("In order to process {sync_blocks_depth} blocks behind latest block, connected nodes needs to be archive nodes!");
 	}
 
-	info!("Syncing block headers for {sync_range:?}");
-	for block_number in sync_range {
-		// TODO: This is still an ambiguous check since data fetch can fail.
-		// We should write block status in DB explicitly.
-		match client.is_confidence_stored(block_number) {
-			Ok(false) => (),
-			Ok(true) => continue,
-			Err(error) => {
-				// TODO: Is it valid to have skipped block?
-				error!(block_number, "Cannot process block: {error:#}");
-				continue;
-			},
-		};
-
-		let (header, header_hash) = match client.get_header_by_block_number(block_number).await {
-			Ok(value) => value,
-			Err(error) => {
-				error!(block_number, "Cannot process block: {error:#}");
-				continue;
-			},
-		};
-
-		{
-			let mut state = state.lock().unwrap();
-			state.sync_latest.replace(block_number);
-			// TODO: Add proper header verification on sync
-			state.sync_header_verified.set(block_number);
-		}
+	let cursor = match client.get_sync_cursor() {
+		Ok(cursor) => cursor.unwrap_or_default(),
+		Err(error) => {
+			error!("Cannot get sync cursor, syncing the entire range: {error:#}");
+			SyncCursor::default()
+		},
+	};
 
-		// TODO: Should we handle unprocessed blocks differently?
-		let block_verified_sender = block_verified_sender.clone();
-		if let Err(error) = process_block(
-			&client,
-			&network_client,
-			header,
-			header_hash,
-			&cfg,
-			block_verified_sender,
-		)
-		.await
-		{
-			error!(block_number, "Cannot process block: {error:#}");
-		} else {
-			let mut state = state.lock().unwrap();
-			state.sync_confidence_achieved.set(block_number);
-		}
-	}
+	// Blocks pruned by the retention are not synced again
+	let oldest_available = state.lock().unwrap().oldest_available.unwrap_or(0);
+	let sync_range = sync_range.start.max(oldest_available)..sync_range.end;
+
+	let planner = SyncPlanner::new(sync_range.clone(), cursor);
+	let pending_blocks = planner.pending_blocks(cfg.direction);
+	init_state(&mut state.lock().unwrap(), &planner);
+
+	info!(
+		direction = ?cfg.direction,
+		workers = cfg.workers,
+		pending = pending_blocks.len(),
+		"Syncing block headers for {sync_range:?}"
+	);
+
+	let planner = Mutex::new(planner);
+	stream::iter(pending_blocks)
+		.for_each_concurrent(cfg.workers, |block_number| {
+			sync_block(
+				&client,
+				&network_client,
+				&cfg,
+				block_number,
+				&block_verified_sender,
+				&state,
+				&planner,
+			)
+		})
+		.await;
 
 	if cfg.is_last_step {
 		state.lock().unwrap().synced.replace(true);
@@ -256,9 +512,11 @@ mod tests {
 		},
 		config::substrate::Digest,
 	};
+	use codec::Encode;
 	use hex_literal::hex;
 	use kate_recovery::{data::Cell, matrix::Position};
 	use mockall::predicate::eq;
+	use sp_core::blake2_256;
 
 	fn default_header() -> DaHeader {
 		DaHeader {
@@ -363,6 +621,7 @@ mod tests {
 					fetched.len(),
 					Duration::from_secs(0),
 					None,
+					0,
 				);
 				Box::pin(async move { Ok((fetched, unfetched, stats)) })
 			});
@@ -451,6 +710,7 @@ mod tests {
 					dht_fetched.len(),
 					Duration::from_secs(0),
 					Some((rpc_fetched.len(), Duration::from_secs(1))),
+					0,
 				);
 				let fetched = [&dht_fetched[..], &rpc_fetched[..]].concat();
 				Box::pin(async move { Ok((fetched, unfetched, stats)) })
@@ -471,4 +731,81 @@ mod tests {
 		.await
 		.unwrap();
 	}
+
+	#[test]
+	fn sync_planner_skips_processed_blocks() {
+		let mut processed = BlockSet::default();
+		processed.insert_range(3, 5);
+		processed.insert(8);
+		let planner = SyncPlanner::new(1..10, SyncCursor { processed });
+
+		let forward = planner.pending_blocks(SyncDirection::Forward);
+		assert_eq!(forward, vec![1, 2, 6, 7, 9]);
+		let backward = planner.pending_blocks(SyncDirection::Backward);
+		assert_eq!(backward, vec![9, 7, 6, 2, 1]);
+	}
+
+	#[test]
+	fn sync_planner_progress() {
+		let mut processed = BlockSet::default();
+		processed.insert_range(0, 4);
+		let mut planner = SyncPlanner::new(3..13, SyncCursor { processed });
+
+		let progress = planner.progress();
+		assert_eq!(progress.total, 10);
+		assert_eq!(progress.processed, 2);
+		assert!(progress.eta.is_none());
+
+		planner.complete(12, 0);
+		// Completed block is counted once
+		planner.complete(12, 0);
+		let progress = planner.progress();
+		assert_eq!(progress.processed, 3);
+		assert!(progress.eta.is_some());
+
+		let range = BlockRange::from_set(&planner.processed_blocks()).unwrap();
+		assert_eq!((range.first, range.last), (3, 12));
+		assert!(range.contains(4));
+		assert!(!range.contains(5));
+		assert!(!range.contains(11));
+		assert!(range.contains(12));
+
+		// Blocks pruned by the retention are removed from the cursor
+		planner.complete(11, 4);
+		assert!(!planner.cursor.processed.contains(3));
+		assert!(planner.cursor.processed.contains(4));
+		assert!(planner.cursor.processed.contains(11));
+		assert_eq!(planner.progress().processed, 4);
+	}
+
+	#[test]
+	fn only_verified_blocks_are_in_state_ranges() {
+		let mut processed = BlockSet::default();
+		processed.insert_range(3, 5);
+		let planner = SyncPlanner::new(0..10, SyncCursor { processed });
+
+		// Block verified by the retry queue
+		let mut state = State {
+			sync_confidence_achieved: Some(BlockRange::init(9)),
+			..Default::default()
+		};
+		init_state(&mut state, &planner);
+		insert_available(&mut state.sync_header_verified, 7, 0);
+		// Pruned block is not inserted
+		insert_available(&mut state.sync_header_verified, 1, 2);
+
+		let header_verified = state.sync_header_verified.unwrap();
+		assert_eq!((header_verified.first, header_verified.last), (3, 7));
+		assert!(header_verified.contains(5));
+		assert!(!header_verified.contains(6));
+		assert!(header_verified.contains(7));
+
+		let confidence_achieved = state.sync_confidence_achieved.unwrap();
+		assert_eq!(
+			(confidence_achieved.first, confidence_achieved.last),
+			(3, 9)
+		);
+		assert!(!confidence_achieved.contains(7));
+		assert!(confidence_achieved.contains(9));
+	}
 }
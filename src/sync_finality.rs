@@ -21,6 +21,7 @@ use tracing::{error, info, trace};
 use crate::{
 	data::{Database, FinalitySyncCheckpoint, Key},
 	finality::{check_finality, ValidatorSet},
+	header_chain,
 	network::rpc::{self, WrappedProof},
 	shutdown::Controller,
 	types::State,
@@ -133,9 +134,8 @@ impl<T: Database + Sync> Client for SyncFinality<T> {
 			.wrap_err("Finality Sync Client failed to request Finality Proof")
 	}
 
-	fn store_block_header(&self, block_number: u32, header: Header) -> Result<()> {
-		self.db
-			.put(Key::BlockHeader(block_number), header)
+	fn store_block_header(&self, _block_number: u32, header: Header) -> Result<()> {
+		header_chain::store_trusted(&self.db, &header)
 			.wrap_err("Finality Sync Client failed to store Block Header")
 	}
 
@@ -0,0 +1,66 @@
+//! Metrics backend which fans out metrics to several backends (e.g. OpenTelemetry Collector and Prometheus).
+
+use async_trait::async_trait;
+use color_eyre::Result;
+
+pub struct Metrics {
+	backends: Vec<Box<dyn super::Metrics + Send + Sync>>,
+}
+
+impl Metrics {
+	pub fn new(backends: Vec<Box<dyn super::Metrics + Send + Sync>>) -> Self {
+		Metrics { backends }
+	}
+}
+
+#[async_trait]
+impl super::Metrics for Metrics {
+	async fn count(&self, counter: super::MetricCounter) {
+		for backend in &self.backends {
+			backend.count(counter).await;
+		}
+	}
+
+	/// Records value in all backends, and returns the first error if any of the backends fails.
+	async fn record(&self, value: super::MetricValue) -> Result<()> {
+		let mut first_error = None;
+		for backend in &self.backends {
+			if let Err(error) = backend.record(value).await {
+				first_error.get_or_insert(error);
+			}
+		}
+		first_error.map_or(Ok(()), Err)
+	}
+
+	async fn set_multiaddress(&self, multiaddr: String) {
+		for backend in &self.backends {
+			backend.set_multiaddress(multiaddr.clone()).await;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Metrics;
+	use crate::telemetry::{self, MetricCounter, MetricValue, MockMetrics};
+	use color_eyre::eyre::eyre;
+
+	#[tokio::test]
+	async fn metrics_are_sent_to_all_backends() {
+		let mut failing = MockMetrics::new();
+		failing.expect_count().times(1).returning(|_| ());
+		failing
+			.expect_record()
+			.times(1)
+			.returning(|_| Err(eyre!("Cannot record")));
+
+		let mut healthy = MockMetrics::new();
+		healthy.expect_count().times(1).returning(|_| ());
+		healthy.expect_record().times(1).returning(|_| Ok(()));
+
+		let metrics = Metrics::new(vec![Box::new(failing), Box::new(healthy)]);
+		telemetry::Metrics::count(&metrics, MetricCounter::SessionBlock).await;
+		let result = telemetry::Metrics::record(&metrics, MetricValue::BlockLag(1)).await;
+		assert_eq!(result.unwrap_err().to_string(), "Cannot record");
+	}
+}
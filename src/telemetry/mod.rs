@@ -7,9 +7,44 @@ use async_trait::async_trait;
 use color_eyre::Result;
 use mockall::automock;
 use opentelemetry_api::metrics::{Counter, Meter};
+use tokio::sync::RwLock;
 
+pub mod composite;
+pub mod noop;
 pub mod otlp;
+pub mod prometheus;
 
+const ATTRIBUTE_NUMBER: usize = 8;
+
+#[derive(Debug)]
+pub struct MetricAttributes {
+	pub role: String,
+	pub peer_id: String,
+	pub ip: RwLock<String>,
+	pub multiaddress: RwLock<String>,
+	pub origin: String,
+	pub avail_address: String,
+	pub operating_mode: String,
+	pub partition_size: String,
+}
+
+impl MetricAttributes {
+	/// Returns attribute names and values which are attached to every metric
+	async fn values(&self) -> [(&'static str, String); ATTRIBUTE_NUMBER] {
+		[
+			("version", clap::crate_version!().to_string()),
+			("role", self.role.clone()),
+			("origin", self.origin.clone()),
+			("peerID", self.peer_id.clone()),
+			("multiaddress", self.multiaddress.read().await.clone()),
+			("avail_address", self.avail_address.clone()),
+			("partition_size", self.partition_size.clone()),
+			("operating_mode", self.operating_mode.clone()),
+		]
+	}
+}
+
+#[derive(Clone, Copy)]
 pub enum MetricCounter {
 	SessionBlock,
 	OutgoingConnectionError,
@@ -18,6 +53,11 @@ pub enum MetricCounter {
 	ConnectionEstablished,
 	IncomingPutRecord,
 	IncomingGetRecord,
+	BlockRetrySucceeded,
+	BlockRetryGivenUp,
+	ReceiverLagged,
+	InvalidProof,
+	RPCNodeSwitch,
 }
 
 impl Display for MetricCounter {
@@ -30,22 +70,34 @@ impl Display for MetricCounter {
 			MetricCounter::ConnectionEstablished => write!(f, "established_connections"),
 			MetricCounter::IncomingPutRecord => write!(f, "incoming_put_record_counter"),
 			MetricCounter::IncomingGetRecord => write!(f, "incoming_get_record_counter"),
+			MetricCounter::BlockRetrySucceeded => write!(f, "block_retry_succeeded_counter"),
+			MetricCounter::BlockRetryGivenUp => write!(f, "block_retry_given_up_counter"),
+			MetricCounter::ReceiverLagged => write!(f, "receiver_lagged_counter"),
+			MetricCounter::InvalidProof => write!(f, "invalid_proof_counter"),
+			MetricCounter::RPCNodeSwitch => write!(f, "rpc_node_switch_counter"),
 		}
 	}
 }
 
 impl MetricCounter {
+	const ALL: [MetricCounter; 12] = [
+		MetricCounter::SessionBlock,
+		MetricCounter::OutgoingConnectionError,
+		MetricCounter::IncomingConnectionError,
+		MetricCounter::IncomingConnection,
+		MetricCounter::ConnectionEstablished,
+		MetricCounter::IncomingPutRecord,
+		MetricCounter::IncomingGetRecord,
+		MetricCounter::BlockRetrySucceeded,
+		MetricCounter::BlockRetryGivenUp,
+		MetricCounter::ReceiverLagged,
+		MetricCounter::InvalidProof,
+		MetricCounter::RPCNodeSwitch,
+	];
+
 	fn init_counters(meter: Meter) -> HashMap<String, Counter<u64>> {
 		let mut counter_map: HashMap<String, Counter<u64>> = Default::default();
-		for counter in [
-			MetricCounter::SessionBlock,
-			MetricCounter::OutgoingConnectionError,
-			MetricCounter::IncomingConnectionError,
-			MetricCounter::IncomingConnection,
-			MetricCounter::ConnectionEstablished,
-			MetricCounter::IncomingPutRecord,
-			MetricCounter::IncomingGetRecord,
-		] {
+		for counter in MetricCounter::ALL {
 			counter_map.insert(
 				counter.to_string(),
 				meter.u64_counter(counter.to_string()).init(),
@@ -55,6 +107,7 @@ impl MetricCounter {
 	}
 }
 
+#[derive(Clone, Copy)]
 pub enum MetricValue {
 	TotalBlockNumber(u32),
 	DHTFetched(f64),
@@ -73,6 +126,14 @@ pub enum MetricValue {
 	PingLatency(f64),
 	ReplicationFactor(u16),
 	QueryTimeout(u32),
+	RetryQueueLength(usize),
+	BlockQueueDepth(usize),
+	BlockLag(u32),
+	SamplingRounds(u32),
+	SamplingAdditionalCells(usize),
+	RPCNodeLatency(f64),
+	RPCNodeHeadLag(u32),
+	RPCHealthyNodes(usize),
 	#[cfg(feature = "crawl")]
 	CrawlCellsSuccessRate(f64),
 	#[cfg(feature = "crawl")]
@@ -81,6 +142,47 @@ pub enum MetricValue {
 	CrawlBlockDelay(f64),
 }
 
+impl MetricValue {
+	/// Returns metric name and value, as exported by the metrics backends
+	fn name_and_value(&self) -> (&'static str, f64) {
+		match self {
+			MetricValue::TotalBlockNumber(number) => ("total_block_number", *number as f64),
+			MetricValue::DHTFetched(number) => ("dht_fetched", *number),
+			MetricValue::DHTFetchedPercentage(number) => ("dht_fetched_percentage", *number),
+			MetricValue::DHTFetchDuration(number) => ("dht_fetch_duration", *number),
+			MetricValue::NodeRPCFetched(number) => ("node_rpc_fetched", *number),
+			MetricValue::NodeRPCFetchDuration(number) => ("node_rpc_fetch_duration", *number),
+			MetricValue::BlockConfidence(number) => ("block_confidence", *number),
+			MetricValue::BlockConfidenceTreshold(number) => ("block_confidence_treshold", *number),
+			MetricValue::RPCCallDuration(number) => ("rpc_call_duration", *number),
+			MetricValue::DHTPutDuration(number) => ("dht_put_duration", *number),
+			MetricValue::DHTPutSuccess(number) => ("dht_put_success", *number),
+			MetricValue::ConnectedPeersNum(number) => ("connected_peers_num", *number as f64),
+			MetricValue::HealthCheck() => ("up", 1.0),
+			MetricValue::BlockProcessingDelay(number) => ("block_processing_delay", *number),
+			MetricValue::PingLatency(number) => ("ping_latency", *number),
+			MetricValue::ReplicationFactor(number) => ("replication_factor", *number as f64),
+			MetricValue::QueryTimeout(number) => ("query_timeout", *number as f64),
+			MetricValue::RetryQueueLength(number) => ("retry_queue_length", *number as f64),
+			MetricValue::BlockQueueDepth(number) => ("block_queue_depth", *number as f64),
+			MetricValue::BlockLag(number) => ("block_lag", *number as f64),
+			MetricValue::SamplingRounds(number) => ("sampling_rounds", *number as f64),
+			MetricValue::SamplingAdditionalCells(number) => {
+				("sampling_additional_cells", *number as f64)
+			},
+			MetricValue::RPCNodeLatency(number) => ("rpc_node_latency", *number),
+			MetricValue::RPCNodeHeadLag(number) => ("rpc_node_head_lag", *number as f64),
+			MetricValue::RPCHealthyNodes(number) => ("rpc_healthy_nodes", *number as f64),
+			#[cfg(feature = "crawl")]
+			MetricValue::CrawlCellsSuccessRate(number) => ("crawl_cells_success_rate", *number),
+			#[cfg(feature = "crawl")]
+			MetricValue::CrawlRowsSuccessRate(number) => ("crawl_rows_success_rate", *number),
+			#[cfg(feature = "crawl")]
+			MetricValue::CrawlBlockDelay(number) => ("crawl_block_delay", *number),
+		}
+	}
+}
+
 #[automock]
 #[async_trait]
 pub trait Metrics {
@@ -88,3 +190,19 @@ pub trait Metrics {
 	async fn record(&self, value: MetricValue) -> Result<()>;
 	async fn set_multiaddress(&self, multiaddr: String);
 }
+
+/// Metrics backend selected at runtime, e.g. based on the configuration
+#[async_trait]
+impl Metrics for Box<dyn Metrics + Send + Sync> {
+	async fn count(&self, counter: MetricCounter) {
+		(**self).count(counter).await
+	}
+
+	async fn record(&self, value: MetricValue) -> Result<()> {
+		(**self).record(value).await
+	}
+
+	async fn set_multiaddress(&self, multiaddr: String) {
+		(**self).set_multiaddress(multiaddr).await
+	}
+}
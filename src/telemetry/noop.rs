@@ -0,0 +1,17 @@
+//! Metrics backend which discards all metrics, used when metrics export is disabled.
+
+use async_trait::async_trait;
+use color_eyre::Result;
+
+pub struct Metrics;
+
+#[async_trait]
+impl super::Metrics for Metrics {
+	async fn count(&self, _: super::MetricCounter) {}
+
+	async fn record(&self, _: super::MetricValue) -> Result<()> {
+		Ok(())
+	}
+
+	async fn set_multiaddress(&self, _: String) {}
+}
@@ -7,11 +7,8 @@ use opentelemetry_api::{
 };
 use opentelemetry_otlp::{ExportConfig, Protocol, WithExportConfig};
 use std::{collections::HashMap, time::Duration};
-use tokio::sync::RwLock;
 
-use super::MetricCounter;
-
-const ATTRIBUTE_NUMBER: usize = 8;
+use super::{MetricAttributes, MetricCounter, ATTRIBUTE_NUMBER};
 
 #[derive(Debug)]
 pub struct Metrics {
@@ -20,33 +17,12 @@ pub struct Metrics {
 	attributes: MetricAttributes,
 }
 
-#[derive(Debug)]
-pub struct MetricAttributes {
-	pub role: String,
-	pub peer_id: String,
-	pub ip: RwLock<String>,
-	pub multiaddress: RwLock<String>,
-	pub origin: String,
-	pub avail_address: String,
-	pub operating_mode: String,
-	pub partition_size: String,
-}
-
 impl Metrics {
 	async fn attributes(&self) -> [KeyValue; ATTRIBUTE_NUMBER] {
-		[
-			KeyValue::new("version", clap::crate_version!()),
-			KeyValue::new("role", self.attributes.role.clone()),
-			KeyValue::new("origin", self.attributes.origin.clone()),
-			KeyValue::new("peerID", self.attributes.peer_id.clone()),
-			KeyValue::new(
-				"multiaddress",
-				self.attributes.multiaddress.read().await.clone(),
-			),
-			KeyValue::new("avail_address", self.attributes.avail_address.clone()),
-			KeyValue::new("partition_size", self.attributes.partition_size.clone()),
-			KeyValue::new("operating_mode", self.attributes.operating_mode.clone()),
-		]
+		self.attributes
+			.values()
+			.await
+			.map(|(key, value)| KeyValue::new(key, value))
 	}
 
 	async fn record_u64(&self, name: &'static str, value: u64) -> Result<()> {
@@ -135,6 +111,31 @@ impl super::Metrics for Metrics {
 			super::MetricValue::PingLatency(number) => {
 				self.record_f64("ping_latency", number).await?;
 			},
+			super::MetricValue::RetryQueueLength(number) => {
+				self.record_u64("retry_queue_length", number as u64).await?;
+			},
+			super::MetricValue::BlockQueueDepth(number) => {
+				self.record_u64("block_queue_depth", number as u64).await?;
+			},
+			super::MetricValue::BlockLag(number) => {
+				self.record_u64("block_lag", number as u64).await?;
+			},
+			super::MetricValue::SamplingRounds(number) => {
+				self.record_u64("sampling_rounds", number as u64).await?;
+			},
+			super::MetricValue::SamplingAdditionalCells(number) => {
+				self.record_u64("sampling_additional_cells", number as u64)
+					.await?;
+			},
+			super::MetricValue::RPCNodeLatency(number) => {
+				self.record_f64("rpc_node_latency", number).await?;
+			},
+			super::MetricValue::RPCNodeHeadLag(number) => {
+				self.record_u64("rpc_node_head_lag", number.into()).await?;
+			},
+			super::MetricValue::RPCHealthyNodes(number) => {
+				self.record_u64("rpc_healthy_nodes", number as u64).await?;
+			},
 			#[cfg(feature = "crawl")]
 			super::MetricValue::CrawlCellsSuccessRate(number) => {
 				self.record_f64("crawl_cells_success_rate", number).await?;
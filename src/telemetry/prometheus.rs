@@ -0,0 +1,175 @@
+//! Prometheus metrics exporter.
+//!
+//! Counters and gauges are exposed on the local `/metrics` HTTP endpoint in the OpenMetrics text format,
+//! with the same attributes as the OpenTelemetry metrics, so they can be scraped without the OpenTelemetry Collector.
+
+use async_trait::async_trait;
+use color_eyre::{eyre::eyre, Result};
+use futures::Future;
+use prometheus_client::{
+	encoding::text::encode,
+	metrics::{counter::Counter, family::Family, gauge::Gauge},
+	registry::Registry,
+};
+use std::{
+	collections::HashMap,
+	net::SocketAddr,
+	sync::{atomic::AtomicU64, Arc, Mutex},
+};
+use tracing::{error, info};
+use warp::{
+	http::{header::CONTENT_TYPE, StatusCode},
+	Filter, Reply,
+};
+
+use super::{MetricAttributes, MetricCounter};
+
+const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+type Labels = Vec<(String, String)>;
+type GaugeFamily = Family<Labels, Gauge<f64, AtomicU64>>;
+
+pub struct Metrics {
+	registry: Arc<Mutex<Registry>>,
+	counters: HashMap<String, Family<Labels, Counter>>,
+	// Gauges are registered on the first record
+	gauges: Mutex<HashMap<&'static str, GaugeFamily>>,
+	attributes: MetricAttributes,
+}
+
+fn encode_registry(registry: &Mutex<Registry>) -> Result<String> {
+	let registry = registry.lock().expect("Lock should be acquired");
+	let mut buffer = String::new();
+	encode(&mut buffer, &registry).map_err(|error| eyre!("Cannot encode metrics: {error}"))?;
+	Ok(buffer)
+}
+
+impl Metrics {
+	async fn labels(&self) -> Labels {
+		self.attributes
+			.values()
+			.await
+			.into_iter()
+			.map(|(key, value)| (key.to_string(), value))
+			.collect()
+	}
+
+	/// Encodes all registered metrics in the OpenMetrics text format.
+	pub fn encode(&self) -> Result<String> {
+		encode_registry(&self.registry)
+	}
+
+	/// Creates a HTTP server which exposes metrics on the `/metrics` endpoint, and needs to be spawned into a runtime.
+	/// Returns an error if the server cannot be bound to the given address.
+	pub fn bind(&self, addr: SocketAddr) -> Result<impl Future<Output = ()>> {
+		let registry = self.registry.clone();
+		let metrics_route =
+			warp::path!("metrics")
+				.and(warp::get())
+				.map(move || match encode_registry(&registry) {
+					Ok(body) => {
+						warp::reply::with_header(body, CONTENT_TYPE, OPENMETRICS_CONTENT_TYPE)
+							.into_response()
+					},
+					Err(error) => {
+						error!("{error:#}");
+						StatusCode::INTERNAL_SERVER_ERROR.into_response()
+					},
+				});
+
+		let (addr, server) = warp::serve(metrics_route)
+			.try_bind_ephemeral(addr)
+			.map_err(|error| eyre!("Cannot bind Prometheus exporter to {addr}: {error}"))?;
+		info!("Prometheus metrics exporter is listening on {addr}");
+		Ok(server)
+	}
+}
+
+#[async_trait]
+impl super::Metrics for Metrics {
+	async fn count(&self, counter: super::MetricCounter) {
+		let labels = self.labels().await;
+		self.counters[&counter.to_string()]
+			.get_or_create(&labels)
+			.inc();
+	}
+
+	async fn record(&self, value: super::MetricValue) -> Result<()> {
+		let (name, value) = value.name_and_value();
+		let labels = self.labels().await;
+		let mut gauges = self.gauges.lock().expect("Lock should be acquired");
+		let gauge = gauges.entry(name).or_insert_with(|| {
+			let gauge = GaugeFamily::default();
+			let mut registry = self.registry.lock().expect("Lock should be acquired");
+			registry.register(name, name, gauge.clone());
+			gauge
+		});
+		gauge.get_or_create(&labels).set(value);
+		Ok(())
+	}
+
+	async fn set_multiaddress(&self, multiaddr: String) {
+		let mut m = self.attributes.multiaddress.write().await;
+		*m = multiaddr;
+	}
+}
+
+pub fn initialize(attributes: MetricAttributes) -> Metrics {
+	let mut registry = Registry::default();
+	// Counters are registered upfront, so they are exposed even before the first count
+	let counters = MetricCounter::ALL
+		.into_iter()
+		.map(|counter| {
+			let family = Family::<Labels, Counter>::default();
+			registry.register(counter.to_string(), counter.to_string(), family.clone());
+			(counter.to_string(), family)
+		})
+		.collect();
+
+	Metrics {
+		registry: Arc::new(Mutex::new(registry)),
+		counters,
+		gauges: Default::default(),
+		attributes,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::initialize;
+	use crate::telemetry::{MetricAttributes, MetricCounter, MetricValue, Metrics};
+	use tokio::sync::RwLock;
+
+	fn value(encoded: &str, name: &str) -> Option<f64> {
+		encoded
+			.lines()
+			.find(|line| line.starts_with(&format!("{name}{{")))
+			.and_then(|line| line.rsplit(' ').next())
+			.and_then(|value| value.parse().ok())
+	}
+
+	#[tokio::test]
+	async fn metrics_are_encoded_with_attributes() {
+		let metrics = initialize(MetricAttributes {
+			role: "lightnode".to_string(),
+			peer_id: "12D3KooWMm1c4pzeLPGkkCJMAgFbsfQ8xmVDusg272icWsaNHWzN".to_string(),
+			ip: RwLock::new("".to_string()),
+			multiaddress: RwLock::new("".to_string()),
+			origin: "external".to_string(),
+			avail_address: "".to_string(),
+			operating_mode: "client".to_string(),
+			partition_size: "n/a".to_string(),
+		});
+
+		metrics.count(MetricCounter::InvalidProof).await;
+		metrics.count(MetricCounter::InvalidProof).await;
+		metrics.record(MetricValue::BlockLag(3)).await.unwrap();
+		metrics.record(MetricValue::BlockLag(5)).await.unwrap();
+
+		let encoded = metrics.encode().unwrap();
+		assert_eq!(value(&encoded, "invalid_proof_counter_total"), Some(2.0));
+		assert_eq!(value(&encoded, "block_lag"), Some(5.0));
+		assert_eq!(value(&encoded, "session_block_counter_total"), None);
+		assert!(encoded.contains(r#"role="lightnode""#));
+	}
+}
@@ -0,0 +1,30 @@
+//! Test fixtures shared between the module tests.
+
+use avail_subxt::{
+	api::runtime_types::avail_core::{
+		data_lookup::compact::CompactDataLookup,
+		header::extension::{v3, HeaderExtension},
+		kate_commitment::v3::KateCommitment,
+	},
+	primitives::Header,
+};
+use sp_core::H256;
+use subxt::config::substrate::Digest;
+
+/// Returns header of the given block, with empty extension and digest.
+pub fn header(number: u32, parent_hash: H256) -> Header {
+	Header {
+		parent_hash,
+		number,
+		state_root: H256::default(),
+		extrinsics_root: H256::default(),
+		extension: HeaderExtension::V3(v3::HeaderExtension {
+			commitment: KateCommitment::default(),
+			app_lookup: CompactDataLookup {
+				size: 0,
+				index: vec![],
+			},
+		}),
+		digest: Digest { logs: vec![] },
+	}
+}
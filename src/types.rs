@@ -1,12 +1,15 @@
 //! Shared light client structs and enums.
 
+use crate::confidence::Confidence;
+use crate::consts::EXPECTED_SPEC_VERSIONS;
 use crate::network::p2p::MemoryStoreConfig;
 use crate::network::rpc::{Event, Node as RpcNode};
+use crate::sampling::{self, SamplingStrategy};
 use crate::utils::{extract_app_lookup, extract_kate};
 use avail_core::DataLookup;
 use avail_subxt::{primitives::Header as DaHeader, utils::H256};
 use bip39::{Language, Mnemonic, MnemonicType};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use codec::{Decode, Encode};
 use color_eyre::{
 	eyre::{eyre, WrapErr},
@@ -21,11 +24,13 @@ use libp2p::{Multiaddr, PeerId};
 use serde::{de::Error, Deserialize, Serialize};
 use sp_core::crypto::Ss58Codec;
 use sp_core::{blake2_256, bytes, ed25519};
+use std::cmp::Ordering;
 use std::fmt::{self, Display, Formatter};
 use std::fs;
 use std::num::{NonZeroU8, NonZeroUsize};
-use std::ops::Range;
+use std::ops::{Range, RangeInclusive};
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use subxt::ext::sp_core::{sr25519::Pair, Pair as _};
 use tokio::sync::broadcast;
@@ -79,6 +84,31 @@ pub struct CliOpts {
 	/// ed25519 private key for libp2p keypair generation
 	#[arg(long)]
 	pub private_key: Option<String>,
+	#[command(subcommand)]
+	pub command: Option<Command>,
+}
+
+/// Commands which are run against the local database, instead of running the light client
+#[derive(Subcommand)]
+pub enum Command {
+	/// Export verified headers, confidence, app data and finality checkpoint of the block range into the archive
+	Export {
+		/// First block to export
+		#[arg(long)]
+		from: u32,
+		/// Last block to export
+		#[arg(long)]
+		to: u32,
+		/// Path to the archive file
+		#[arg(short, long, value_name = "FILE")]
+		output: String,
+	},
+	/// Import the archive after validating its header chain
+	Import {
+		/// Path to the archive file
+		#[arg(short, long, value_name = "FILE")]
+		input: String,
+	},
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -93,7 +123,9 @@ pub struct RuntimeVersion {
 	transaction_version: u32,
 }
 
-/// Light to app client channel message struct
+/// Light to app client channel message struct.
+/// Blocks are sent in order by the light and fat client, while the blocks which achieved confidence on retry
+/// are sent once they are re-sampled, so the consumers can receive older blocks after the newer ones.
 #[derive(Clone, Debug)]
 pub struct BlockVerified {
 	pub header_hash: H256,
@@ -101,7 +133,7 @@ pub struct BlockVerified {
 	pub dimensions: Dimensions,
 	pub lookup: DataLookup,
 	pub commitments: Vec<[u8; 48]>,
-	pub confidence: Option<f64>,
+	pub confidence: Option<Confidence>,
 }
 
 pub struct ClientChannels {
@@ -109,9 +141,9 @@ pub struct ClientChannels {
 	pub rpc_event_receiver: broadcast::Receiver<Event>,
 }
 
-impl TryFrom<(DaHeader, Option<f64>)> for BlockVerified {
+impl TryFrom<(DaHeader, Option<Confidence>)> for BlockVerified {
 	type Error = Report;
-	fn try_from((header, confidence): (DaHeader, Option<f64>)) -> Result<Self, Self::Error> {
+	fn try_from((header, confidence): (DaHeader, Option<Confidence>)) -> Result<Self, Self::Error> {
 		let hash: H256 = Encode::using_encoded(&header, blake2_256).into();
 		let enc_lookup = extract_app_lookup(&header.extension)
 			.map_err(|e| eyre!("Invalid DataLookup: {}", e))?
@@ -146,6 +178,15 @@ impl From<KademliaMode> for KadMode {
 	}
 }
 
+impl From<KadMode> for KademliaMode {
+	fn from(value: KadMode) -> Self {
+		match value {
+			KadMode::Client => KademliaMode::Client,
+			KadMode::Server => KademliaMode::Server,
+		}
+	}
+}
+
 impl Display for KademliaMode {
 	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
 		match self {
@@ -271,6 +312,43 @@ pub enum SecretKey {
 	Key { key: String },
 }
 
+/// Cell selection strategy (see [crate::sampling] for details)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum SamplingStrategyConfig {
+	Uniform,
+	RowStratified,
+	ColumnStratified,
+	Seeded { seed: u64 },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncDirection {
+	/// Sync from the oldest to the newest block
+	Forward,
+	/// Sync from the newest to the oldest block
+	Backward,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+	/// Persistent storage in the RocksDB database
+	RocksDB,
+	/// Ephemeral in-memory storage, state is lost on restart
+	Memory,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricsExporter {
+	/// Push metrics to the OpenTelemetry Collector
+	Otlp,
+	/// Expose metrics on the local Prometheus `/metrics` endpoint
+	Prometheus,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type")]
 pub enum RetryConfig {
@@ -352,16 +430,44 @@ pub struct RuntimeConfig {
 	pub operation_mode: KademliaMode,
 	/// Vector of Relay nodes, which are used for hole punching
 	pub relays: Vec<MultiaddrConfig>,
+	/// Vector of peer IDs which are blocked on startup, along with the peers blocked via API (default: empty).
+	pub blocked_peers: Vec<PeerId>,
+	/// Token which authorizes the API requests blocking and unblocking peers, sent in the `Authorization: Bearer {token}` header.
+	/// If not set, such requests are rejected (default: None).
+	pub api_admin_token: Option<String>,
 	/// WebSocket endpoint of full node for subscribing to latest header, etc (default: [ws://127.0.0.1:9944]).
 	pub full_node_ws: Vec<String>,
+	/// Interval in seconds in which the `full_node_ws` nodes are checked for health. If the connected node is lagging,
+	/// slow or failing, client switches to the healthiest node. Set to 0 to disable health checks (default: 60).
+	pub rpc_health_check_interval: u64,
+	/// Number of blocks by which the node finalized head can lag behind the most advanced node, before the node is considered unhealthy (default: 3).
+	pub rpc_max_head_lag: u32,
+	/// Average RPC call latency in milliseconds above which the node is considered unhealthy (default: 2000).
+	pub rpc_max_latency: u64,
+	/// Runtime specification versions supported by the light client. Light client runs in degraded mode if the connected node
+	/// runs the runtime outside of this range, so the range can be extended if the new runtime is known to be compatible (default: 12 to 19).
+	pub supported_spec_versions: RangeInclusive<u32>,
 	/// Genesis hash of the network to be connected to. Set to a string beginning with "DEV" to connect to any network.
 	pub genesis_hash: String,
 	/// ID of application used to start application client. If app_id is not set, or set to 0, application client is not started (default: 0).
 	pub app_id: Option<u32>,
 	/// Confidence threshold, used to calculate how many cells need to be sampled to achieve desired confidence (default: 92.0).
 	pub confidence: f64,
+	/// Strategy used to select cells for sampling, one of `uniform`, `row-stratified`, `column-stratified`,
+	/// or `seeded` with the `seed` for reproducible test runs (default: uniform).
+	pub sampling_strategy: SamplingStrategyConfig,
+	/// Maximum number of sampling rounds per block. If some cells cannot be fetched or verified,
+	/// additional cells are sampled in the next round (default: 3).
+	pub max_sampling_rounds: u32,
+	/// Maximum number of cells sampled per block, including additional cells sampled in later rounds (default: 30).
+	pub max_sampled_cells: u32,
 	/// File system path where RocksDB used by light client, stores its data.
 	pub avail_path: String,
+	/// Storage backend, `rocksdb` stores data in the `avail_path` directory, `memory` keeps data in memory until restart (default: rocksdb).
+	pub storage_backend: StorageBackend,
+	/// Maximum size of the data stored in memory, in bytes, when the `memory` storage backend is used.
+	/// Oldest blocks are pruned by retention once exceeded, the same way as with `max_disk_size` (default: 1073741824).
+	pub memory_db_max_size: u64,
 	/// Log level, default is `INFO`. See `<https://docs.rs/log/0.4.14/log/enum.LevelFilter.html>` for possible log level values. (default: `INFO`).
 	pub log_level: String,
 	pub origin: String,
@@ -369,19 +475,32 @@ pub struct RuntimeConfig {
 	pub log_format_json: bool,
 	/// OpenTelemetry Collector endpoint (default: `http://otelcollector.avail.tools:4317`)
 	pub ot_collector_endpoint: String,
+	/// Metrics exporters, `otlp` pushes metrics to the OpenTelemetry Collector, `prometheus` exposes them on the local `/metrics` endpoint.
+	/// Several exporters can be active at once, empty list disables metrics export (default: ["otlp"]).
+	pub metrics_exporters: Vec<MetricsExporter>,
+	/// Port of the Prometheus `/metrics` endpoint, served on the HTTP server host (default: 9520).
+	pub prometheus_port: u16,
 	/// Disables fetching of cells from RPC, set to true if client expects cells to be available in DHT (default: false).
 	pub disable_rpc: bool,
+	/// Number of latest blocks whose verified cells are cached in the database, 0 disables the cache (default: 16).
+	pub cell_cache_blocks: u32,
 	/// Maximum number of parallel tasks spawned for GET and PUT operations on DHT (default: 20).
 	pub dht_parallelization_limit: usize,
 	/// Number of parallel queries for cell fetching via RPC from node (default: 8).
 	pub query_proof_rpc_parallel_tasks: usize,
 	/// Number of seconds to postpone block processing after block finalized message arrives (default: 0).
 	pub block_processing_delay: Option<u32>,
+	/// Maximum number of finalized blocks which light client samples concurrently (default: 1).
+	pub block_processing_concurrency: usize,
 	/// Fraction and number of the block matrix part to fetch (e.g. 2/20 means second 1/20 part of a matrix) (default: None)
 	#[serde(with = "block_matrix_partition_format")]
 	pub block_matrix_partition: Option<Partition>,
 	/// Starting block of the syncing process. Omitting it will disable syncing. (default: None).
 	pub sync_start_block: Option<u32>,
+	/// Order in which blocks are synced, `backward` syncs the newest blocks first (default: forward).
+	pub sync_direction: SyncDirection,
+	/// Number of blocks which are synced in parallel (default: 1).
+	pub sync_workers: usize,
 	/// Enable or disable synchronizing finality. If disabled, finality is assumed to be verified until the starting block at the point the LC is started and is only checked for new blocks. (default: true)
 	pub sync_finality_enable: bool,
 	/// Maximum number of cells per request for proof queries (default: 30).
@@ -432,6 +551,9 @@ pub struct RuntimeConfig {
 	pub max_kad_record_size: u64,
 	/// The maximum number of provider records for which the local node is the provider. (default: 1024).
 	pub max_kad_provided_keys: u64,
+	/// Reputation score at which the peer serving DHT records is blocked. Each block in which the peer served valid cells
+	/// increases its score by 1 (up to 100), and each block in which it served cells with invalid proofs decreases it by 10 (default: -30).
+	pub peer_reputation_threshold: i32,
 	/// Set the configuration based on which the retries will be orchestrated, max duration [in seconds] between retries and number of tries.
 	/// (default:
 	/// fibonacci:
@@ -440,6 +562,18 @@ pub struct RuntimeConfig {
 	///     retries: 6,
 	/// )
 	pub retry_config: RetryConfig,
+	/// Time in seconds after which re-sampling of the block which failed sampling is given up. Delays between re-sampling attempts are set by `retry_config` (default: 3600).
+	pub block_retry_deadline: u64,
+	/// Number of latest blocks for which block headers are kept in the database, older headers are pruned (default: None, headers are never pruned).
+	pub header_retention_blocks: Option<u32>,
+	/// Number of latest blocks for which application data is kept in the database, older data is pruned (default: None, data is never pruned).
+	pub app_data_retention_blocks: Option<u32>,
+	/// Number of latest blocks for which confidence is kept in the database, older confidence is pruned (default: None, confidence is never pruned).
+	pub confidence_retention_blocks: Option<u32>,
+	/// Maximum size of the database in bytes. If exceeded, oldest blocks are pruned until the database fits the budget (default: None).
+	pub max_disk_size: Option<u64>,
+	/// Sets the database retention pruning interval in blocks, must be greater than 0 (default: 60).
+	pub retention_pruning_interval: u32,
 	#[cfg(feature = "crawl")]
 	#[serde(flatten)]
 	pub crawl: crate::crawl_client::CrawlConfig,
@@ -456,7 +590,11 @@ pub struct Delay(pub Option<Duration>);
 /// Light client configuration (see [RuntimeConfig] for details)
 pub struct LightClientConfig {
 	pub confidence: f64,
+	pub sampling_strategy: Arc<dyn SamplingStrategy>,
+	pub max_sampling_rounds: u32,
+	pub max_sampled_cells: u32,
 	pub block_processing_delay: Delay,
+	pub block_processing_concurrency: usize,
 }
 
 impl Delay {
@@ -475,7 +613,11 @@ impl From<&RuntimeConfig> for LightClientConfig {
 
 		LightClientConfig {
 			confidence: val.confidence,
+			sampling_strategy: sampling::new(val.sampling_strategy),
+			max_sampling_rounds: val.max_sampling_rounds.max(1),
+			max_sampled_cells: val.max_sampled_cells,
 			block_processing_delay: Delay(block_processing_delay),
+			block_processing_concurrency: val.block_processing_concurrency.max(1),
 		}
 	}
 }
@@ -525,6 +667,7 @@ pub struct LibP2PConfig {
 	pub task_command_buffer_size: NonZeroUsize,
 	pub per_connection_event_buffer_size: usize,
 	pub dial_concurrency_factor: NonZeroU8,
+	pub peer_reputation_threshold: i32,
 }
 
 impl From<&LibP2PConfig> for libp2p::kad::Config {
@@ -580,6 +723,7 @@ impl From<&RuntimeConfig> for LibP2PConfig {
 			per_connection_event_buffer_size: val.per_connection_event_buffer_size,
 			dial_concurrency_factor: std::num::NonZeroU8::new(val.dial_concurrency_factor)
 				.expect("Invalid dial concurrency factor"),
+			peer_reputation_threshold: val.peer_reputation_threshold,
 		}
 	}
 }
@@ -719,18 +863,24 @@ impl From<&RuntimeConfig> for IdentifyConfig {
 #[derive(Clone)]
 pub struct SyncClientConfig {
 	pub confidence: f64,
+	pub sampling_strategy: Arc<dyn SamplingStrategy>,
 	pub disable_rpc: bool,
 	pub dht_parallelization_limit: usize,
 	pub is_last_step: bool,
+	pub direction: SyncDirection,
+	pub workers: usize,
 }
 
 impl From<&RuntimeConfig> for SyncClientConfig {
 	fn from(val: &RuntimeConfig) -> Self {
 		SyncClientConfig {
 			confidence: val.confidence,
+			sampling_strategy: sampling::new(val.sampling_strategy),
 			disable_rpc: val.disable_rpc,
 			dht_parallelization_limit: val.dht_parallelization_limit,
 			is_last_step: val.app_id.is_none(),
+			direction: val.sync_direction,
+			workers: val.sync_workers.max(1),
 		}
 	}
 }
@@ -751,6 +901,90 @@ impl From<&RuntimeConfig> for AppClientConfig {
 		}
 	}
 }
+/// Retry queue configuration (see [RuntimeConfig] for details)
+#[derive(Clone)]
+pub struct RetryQueueConfig {
+	pub confidence: f64,
+	pub sampling_strategy: Arc<dyn SamplingStrategy>,
+	pub max_sampling_rounds: u32,
+	pub max_sampled_cells: u32,
+	pub retry_config: RetryConfig,
+	pub deadline: Duration,
+}
+
+impl From<&RuntimeConfig> for RetryQueueConfig {
+	fn from(val: &RuntimeConfig) -> Self {
+		RetryQueueConfig {
+			confidence: val.confidence,
+			sampling_strategy: sampling::new(val.sampling_strategy),
+			max_sampling_rounds: val.max_sampling_rounds.max(1),
+			max_sampled_cells: val.max_sampled_cells,
+			retry_config: val.retry_config.clone(),
+			deadline: Duration::from_secs(val.block_retry_deadline),
+		}
+	}
+}
+
+/// RPC nodes health configuration (see [RuntimeConfig] for details)
+#[derive(Clone)]
+pub struct RpcHealthConfig {
+	pub check_interval: Option<Duration>,
+	pub max_head_lag: u32,
+	pub max_latency: Duration,
+}
+
+impl From<&RuntimeConfig> for RpcHealthConfig {
+	fn from(val: &RuntimeConfig) -> Self {
+		RpcHealthConfig {
+			check_interval: (val.rpc_health_check_interval > 0)
+				.then(|| Duration::from_secs(val.rpc_health_check_interval)),
+			max_head_lag: val.rpc_max_head_lag,
+			max_latency: Duration::from_millis(val.rpc_max_latency),
+		}
+	}
+}
+
+/// Retention configuration (see [RuntimeConfig] for details)
+#[derive(Clone)]
+pub struct RetentionConfig {
+	pub app_id: Option<u32>,
+	pub header_retention_blocks: Option<u32>,
+	pub app_data_retention_blocks: Option<u32>,
+	pub confidence_retention_blocks: Option<u32>,
+	pub max_disk_size: Option<u64>,
+	pub pruning_interval: u32,
+}
+
+impl From<&RuntimeConfig> for RetentionConfig {
+	fn from(val: &RuntimeConfig) -> Self {
+		RetentionConfig {
+			app_id: val.app_id,
+			header_retention_blocks: val.header_retention_blocks,
+			app_data_retention_blocks: val.app_data_retention_blocks,
+			confidence_retention_blocks: val.confidence_retention_blocks,
+			// In-memory storage is bounded by the retention, same as the disk storage
+			max_disk_size: match val.storage_backend {
+				StorageBackend::RocksDB => val.max_disk_size,
+				StorageBackend::Memory => {
+					Some(val.max_disk_size.map_or(val.memory_db_max_size, |max| {
+						max.min(val.memory_db_max_size)
+					}))
+				},
+			},
+			pruning_interval: val.retention_pruning_interval,
+		}
+	}
+}
+
+impl RetentionConfig {
+	pub fn is_enabled(&self) -> bool {
+		self.header_retention_blocks.is_some()
+			|| self.app_data_retention_blocks.is_some()
+			|| self.confidence_retention_blocks.is_some()
+			|| self.max_disk_size.is_some()
+	}
+}
+
 impl Default for RuntimeConfig {
 	fn default() -> Self {
 		RuntimeConfig {
@@ -767,20 +1001,37 @@ impl Default for RuntimeConfig {
 			bootstraps: vec![],
 			bootstrap_period: 3600,
 			relays: Vec::new(),
+			blocked_peers: Vec::new(),
+			api_admin_token: None,
 			full_node_ws: vec!["ws://127.0.0.1:9944".to_owned()],
+			rpc_health_check_interval: 60,
+			rpc_max_head_lag: 3,
+			rpc_max_latency: 2000,
+			supported_spec_versions: EXPECTED_SPEC_VERSIONS,
 			genesis_hash: "DEV".to_owned(),
 			app_id: None,
 			confidence: 99.9,
+			sampling_strategy: SamplingStrategyConfig::Uniform,
+			max_sampling_rounds: 3,
+			max_sampled_cells: 30,
 			avail_path: "avail_path".to_owned(),
+			storage_backend: StorageBackend::RocksDB,
+			memory_db_max_size: 1024 * 1024 * 1024,
 			log_level: "INFO".to_owned(),
 			log_format_json: false,
 			ot_collector_endpoint: "http://127.0.0.1:4317".to_string(),
+			metrics_exporters: vec![MetricsExporter::Otlp],
+			prometheus_port: 9520,
 			disable_rpc: false,
+			cell_cache_blocks: 16,
 			dht_parallelization_limit: 20,
 			query_proof_rpc_parallel_tasks: 8,
 			block_processing_delay: Some(20),
+			block_processing_concurrency: 1,
 			block_matrix_partition: None,
 			sync_start_block: None,
+			sync_direction: SyncDirection::Forward,
+			sync_workers: 1,
 			sync_finality_enable: false,
 			max_cells_per_rpc: Some(30),
 			kad_record_ttl: 24 * 60 * 60,
@@ -801,6 +1052,7 @@ impl Default for RuntimeConfig {
 			max_kad_record_number: 2400000,
 			max_kad_record_size: 8192,
 			max_kad_provided_keys: 1024,
+			peer_reputation_threshold: -30,
 			#[cfg(feature = "crawl")]
 			crawl: crate::crawl_client::CrawlConfig::default(),
 			origin: "external".to_string(),
@@ -810,6 +1062,12 @@ impl Default for RuntimeConfig {
 				max_delay: 10,
 				retries: 6,
 			}),
+			block_retry_deadline: 3600,
+			header_retention_blocks: None,
+			app_data_retention_blocks: None,
+			confidence_retention_blocks: None,
+			max_disk_size: None,
+			retention_pruning_interval: 60,
 		}
 	}
 }
@@ -949,6 +1207,10 @@ impl RuntimeConfig {
 			})
 		}
 
+		if self.retention_pruning_interval == 0 {
+			return Err(eyre!("Retention pruning interval must be greater than 0"));
+		}
+
 		Ok(())
 	}
 }
@@ -989,20 +1251,178 @@ impl IdentityConfig {
 	}
 }
 
-#[derive(Clone)]
+/// Set of block numbers, stored as sorted, non-overlapping and non-adjacent inclusive intervals
+#[derive(Clone, Debug, Default, PartialEq, Decode, Encode, Serialize, Deserialize)]
+pub struct BlockSet(Vec<(u32, u32)>);
+
+impl BlockSet {
+	pub fn contains(&self, block_number: u32) -> bool {
+		self.0
+			.binary_search_by(|&(first, last)| {
+				if last < block_number {
+					Ordering::Less
+				} else if first > block_number {
+					Ordering::Greater
+				} else {
+					Ordering::Equal
+				}
+			})
+			.is_ok()
+	}
+
+	pub fn insert(&mut self, block_number: u32) {
+		self.insert_range(block_number, block_number);
+	}
+
+	/// Inserts inclusive range of blocks, merging it with overlapping and adjacent intervals
+	pub fn insert_range(&mut self, first: u32, last: u32) {
+		if first > last {
+			return;
+		}
+		let start = self
+			.0
+			.partition_point(|&(_, end)| end.saturating_add(1) < first);
+		let end = self
+			.0
+			.partition_point(|&(begin, _)| begin <= last.saturating_add(1));
+		let (mut first, mut last) = (first, last);
+		if start < end {
+			first = first.min(self.0[start].0);
+			last = last.max(self.0[end - 1].1);
+		}
+		self.0.splice(start..end, [(first, last)]);
+	}
+
+	pub fn remove(&mut self, block_number: u32) {
+		self.remove_range(block_number, block_number);
+	}
+
+	/// Removes inclusive range of blocks, splitting intervals if needed
+	pub fn remove_range(&mut self, first: u32, last: u32) {
+		if first > last {
+			return;
+		}
+		let start = self.0.partition_point(|&(_, end)| end < first);
+		let end = self.0.partition_point(|&(begin, _)| begin <= last);
+		if start >= end {
+			return;
+		}
+		let mut remaining = vec![];
+		if self.0[start].0 < first {
+			remaining.push((self.0[start].0, first - 1));
+		}
+		if self.0[end - 1].1 > last {
+			remaining.push((last + 1, self.0[end - 1].1));
+		}
+		self.0.splice(start..end, remaining);
+	}
+
+	pub fn first(&self) -> Option<u32> {
+		self.0.first().map(|&(first, _)| first)
+	}
+
+	pub fn last(&self) -> Option<u32> {
+		self.0.last().map(|&(_, last)| last)
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	/// Returns intervals of blocks
+	pub fn intervals(&self) -> &[(u32, u32)] {
+		&self.0
+	}
+
+	/// Returns intervals of blocks within the given inclusive range
+	pub fn intervals_within(&self, first: u32, last: u32) -> impl Iterator<Item = (u32, u32)> + '_ {
+		self.0
+			.iter()
+			.filter(move |&&(begin, end)| begin <= last && end >= first)
+			.map(move |&(begin, end)| (begin.max(first), end.min(last)))
+	}
+}
+
+#[derive(Clone, Debug, Decode, Encode, Serialize, Deserialize)]
 pub struct BlockRange {
 	pub first: u32,
 	pub last: u32,
+	/// Blocks between the first and the last block, which were not processed while the client was not running
+	pub gaps: BlockSet,
+	/// Range is restored from the checkpoint, and no blocks were added since the restart
+	#[codec(skip)]
+	#[serde(skip)]
+	resumed: bool,
 }
 
 impl BlockRange {
 	pub fn init(last: u32) -> BlockRange {
 		let first = last;
-		BlockRange { first, last }
+		BlockRange {
+			first,
+			last,
+			gaps: BlockSet::default(),
+			resumed: false,
+		}
 	}
 
 	pub fn contains(&self, block_number: u32) -> bool {
-		self.first <= block_number && block_number <= self.last
+		self.first <= block_number && block_number <= self.last && !self.gaps.contains(block_number)
+	}
+
+	/// Sets the last block of the range.
+	/// Blocks skipped while the client was not running are recorded as a gap.
+	pub fn set_last(&mut self, last: u32) {
+		if self.resumed && last > self.last + 1 {
+			self.gaps.insert_range(self.last + 1, last - 1);
+		}
+		self.resumed = false;
+		self.last = last;
+		self.gaps.remove_range(last, u32::MAX);
+	}
+
+	/// Returns blocks covered by the range, excluding the gaps
+	pub fn blocks(&self) -> BlockSet {
+		let mut blocks = BlockSet::default();
+		blocks.insert_range(self.first, self.last);
+		for &(first, last) in self.gaps.intervals() {
+			blocks.remove_range(first, last);
+		}
+		blocks
+	}
+
+	/// Removes blocks before the given block from the range, returns `None` if no blocks are left
+	pub fn prune(mut self, first: u32) -> Option<BlockRange> {
+		if self.last < first {
+			return None;
+		}
+		if self.first < first {
+			self.first = first;
+			self.gaps.remove_range(0, first - 1);
+		}
+		Some(self)
+	}
+
+	/// Creates range spanning the given blocks, recording missing blocks as gaps
+	pub fn from_set(blocks: &BlockSet) -> Option<BlockRange> {
+		let (first, last) = (blocks.first()?, blocks.last()?);
+		let mut gaps = BlockSet::default();
+		gaps.insert_range(first, last);
+		for &(begin, end) in blocks.intervals() {
+			gaps.remove_range(begin, end);
+		}
+		Some(BlockRange {
+			first,
+			last,
+			gaps,
+			resumed: false,
+		})
+	}
+
+	/// Marks range as restored after the restart
+	fn resume(mut self) -> Self {
+		self.resumed = true;
+		self
 	}
 }
 
@@ -1019,10 +1439,111 @@ pub struct State {
 	pub sync_data_verified: Option<BlockRange>,
 	pub finality_synced: bool,
 	pub connected_node: RpcNode,
+	pub oldest_available: Option<u32>,
+	/// Blocks for which header couldn't be fetched
+	pub header_failed: BlockSet,
+	/// Blocks for which confidence wasn't achieved
+	pub confidence_failed: BlockSet,
+	/// Progress of the historical sync, if it is enabled
+	pub sync_progress: Option<SyncProgress>,
+	/// Reason why the light client runs in degraded mode, e.g. unsupported runtime of the connected node.
+	/// Degraded mode is reported via API, while the headers which can be decoded are still processed.
+	pub degraded: Option<String>,
+}
+
+/// Progress of the historical sync
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SyncProgress {
+	/// Number of blocks in the sync range
+	pub total: u32,
+	/// Number of processed blocks in the sync range
+	pub processed: u32,
+	/// Estimated time until all blocks in the sync range are processed
+	pub eta: Option<Duration>,
+}
+
+/// Blocks verified by the sync client, persisted so the sync can be resumed after restart
+#[derive(Clone, Debug, Default, Decode, Encode, Serialize, Deserialize)]
+pub struct SyncCursor {
+	pub processed: BlockSet,
+}
+
+/// Part of the [State] which is persisted in the database and restored on startup
+#[derive(Clone, Debug, Default, Decode, Encode, Serialize, Deserialize)]
+pub struct StateCheckpoint {
+	pub latest: u32,
+	pub header_verified: Option<BlockRange>,
+	pub confidence_achieved: Option<BlockRange>,
+	pub data_verified: Option<BlockRange>,
+	pub sync_latest: Option<u32>,
+	pub sync_header_verified: Option<BlockRange>,
+	pub sync_confidence_achieved: Option<BlockRange>,
+	pub sync_data_verified: Option<BlockRange>,
+	pub oldest_available: Option<u32>,
+	pub header_failed: BlockSet,
+	pub confidence_failed: BlockSet,
+}
+
+impl From<&State> for StateCheckpoint {
+	fn from(state: &State) -> Self {
+		StateCheckpoint {
+			latest: state.latest,
+			header_verified: state.header_verified.clone(),
+			confidence_achieved: state.confidence_achieved.clone(),
+			data_verified: state.data_verified.clone(),
+			sync_latest: state.sync_latest,
+			sync_header_verified: state.sync_header_verified.clone(),
+			sync_confidence_achieved: state.sync_confidence_achieved.clone(),
+			sync_data_verified: state.sync_data_verified.clone(),
+			oldest_available: state.oldest_available,
+			header_failed: state.header_failed.clone(),
+			confidence_failed: state.confidence_failed.clone(),
+		}
+	}
+}
+
+impl State {
+	/// Restores state from the checkpoint
+	pub fn restore(&mut self, checkpoint: StateCheckpoint) {
+		let resume = |range: Option<BlockRange>| range.map(BlockRange::resume);
+		self.latest = checkpoint.latest;
+		self.header_verified = resume(checkpoint.header_verified);
+		self.confidence_achieved = resume(checkpoint.confidence_achieved);
+		self.data_verified = resume(checkpoint.data_verified);
+		self.sync_latest = checkpoint.sync_latest;
+		self.sync_header_verified = resume(checkpoint.sync_header_verified);
+		self.sync_confidence_achieved = resume(checkpoint.sync_confidence_achieved);
+		self.sync_data_verified = resume(checkpoint.sync_data_verified);
+		self.oldest_available = checkpoint.oldest_available;
+		self.header_failed = checkpoint.header_failed;
+		self.confidence_failed = checkpoint.confidence_failed;
+	}
+
+	/// Sets the oldest available block, and removes pruned blocks from the block ranges
+	pub fn prune(&mut self, oldest_available: u32) {
+		let oldest_available = self.oldest_available.unwrap_or(0).max(oldest_available);
+		self.oldest_available = Some(oldest_available);
+		for range in [
+			&mut self.header_verified,
+			&mut self.confidence_achieved,
+			&mut self.data_verified,
+			&mut self.sync_header_verified,
+			&mut self.sync_confidence_achieved,
+			&mut self.sync_data_verified,
+		] {
+			*range = range.take().and_then(|range| range.prune(oldest_available));
+		}
+		if let Some(last_pruned) = oldest_available.checked_sub(1) {
+			self.header_failed.remove_range(0, last_pruned);
+			self.confidence_failed.remove_range(0, last_pruned);
+		}
+	}
 }
 
 pub trait OptionBlockRange {
 	fn set(&mut self, block_number: u32);
+	/// Inserts the block into the range, blocks between the range and the inserted block are recorded as gaps
+	fn insert(&mut self, block_number: u32);
 	fn first(&self) -> Option<u32>;
 	fn last(&self) -> Option<u32>;
 	fn contains(&self, block_number: u32) -> bool;
@@ -1031,11 +1552,28 @@ pub trait OptionBlockRange {
 impl OptionBlockRange for Option<BlockRange> {
 	fn set(&mut self, block_number: u32) {
 		match self {
-			Some(range) => range.last = block_number,
+			Some(range) => range.set_last(block_number),
 			None => *self = Some(BlockRange::init(block_number)),
 		};
 	}
 
+	fn insert(&mut self, block_number: u32) {
+		let Some(range) = self else {
+			*self = Some(BlockRange::init(block_number));
+			return;
+		};
+		if block_number < range.first {
+			range.gaps.insert_range(block_number + 1, range.first - 1);
+			range.first = block_number;
+		} else if block_number > range.last {
+			range.gaps.insert_range(range.last + 1, block_number - 1);
+			range.last = block_number;
+			range.resumed = false;
+		} else {
+			range.gaps.remove(block_number);
+		}
+	}
+
 	fn first(&self) -> Option<u32> {
 		self.as_ref().map(|range| range.first)
 	}
@@ -21,6 +21,8 @@ use kate_recovery::{
 	matrix::{Dimensions, Position},
 };
 
+use crate::confidence::EXTENSION_FACTOR;
+
 pub fn decode_app_data(data: &[u8]) -> Result<Option<Vec<u8>>> {
 	let extrisic: AppUncheckedExtrinsic =
 		<_ as Decode>::decode(&mut &data[..]).wrap_err("Couldn't decode AvailExtrinsic")?;
@@ -31,9 +33,12 @@ pub fn decode_app_data(data: &[u8]) -> Result<Option<Vec<u8>>> {
 	}
 }
 
-/// Calculates confidence from given number of verified cells
+/// Calculates confidence from given number of verified cells, each of which misses the withheld data
+/// with the probability of `1 / EXTENSION_FACTOR`. Number of cells is capped, since the miss probability
+/// of more cells is below the precision of the result, so the confidence is never reported as 100%.
 pub fn calculate_confidence(count: u32) -> f64 {
-	100f64 * (1f64 - 1f64 / 2u32.pow(count) as f64)
+	let miss_probability = 1f64 / f64::from(EXTENSION_FACTOR);
+	100f64 * (1f64 - miss_probability.powi(count.min(52) as i32))
 }
 
 /// Extract fields from extension header
@@ -89,6 +94,16 @@ pub fn filter_auth_set_changes(header: &DaHeader) -> Vec<Vec<(AuthorityId, u64)>
 	new_auths
 }
 
+/// Checks if the runtime is upgraded in the block, since the header contains runtime environment update digest
+pub fn is_runtime_upgraded(header: &DaHeader) -> bool {
+	header.digest.logs.iter().any(|log| {
+		matches!(
+			log,
+			avail_subxt::config::substrate::DigestItem::RuntimeEnvironmentUpdated
+		)
+	})
+}
+
 // TODO: Remove unused functions if not needed after next iteration
 
 #[allow(dead_code)]
@@ -113,7 +128,10 @@ fn diff_positions(positions: &[Position], cells: &[Cell]) -> Vec<Position> {
 
 #[cfg(test)]
 mod tests {
-	use super::{can_reconstruct, diff_positions};
+	use super::{can_reconstruct, diff_positions, is_runtime_upgraded};
+	use crate::test_utils::header;
+	use avail_subxt::config::substrate::{Digest, DigestItem};
+	use avail_subxt::{primitives::Header, utils::H256};
 	use kate_recovery::{
 		data::Cell,
 		matrix::{Dimensions, Position},
@@ -177,4 +195,23 @@ mod tests {
 		assert_eq!(diff_positions(&positions, &cells)[0], position(0, 0));
 		assert_eq!(diff_positions(&positions, &cells)[1], position(1, 1));
 	}
+
+	fn header_with_logs(logs: Vec<DigestItem>) -> Header {
+		Header {
+			digest: Digest { logs },
+			..header(1, H256::default())
+		}
+	}
+
+	#[test]
+	fn test_is_runtime_upgraded() {
+		assert!(!is_runtime_upgraded(&header_with_logs(vec![])));
+		assert!(!is_runtime_upgraded(&header_with_logs(vec![
+			DigestItem::Other(vec![1])
+		])));
+		assert!(is_runtime_upgraded(&header_with_logs(vec![
+			DigestItem::Other(vec![1]),
+			DigestItem::RuntimeEnvironmentUpdated
+		])));
+	}
 }